@@ -36,7 +36,7 @@ fn main() {
     文本 內文
 }
 ";
-    let tokens = lexer(&source);
+    let tokens = lexer(source);
     for token in &tokens {
         println!("{:?}", token);
     }
@@ -0,0 +1,457 @@
+// 供編輯器使用的分析：診斷、跳至定義、懸停說明與自動補全，位置一律以位元組偏移表示
+// 與 LSP 協定無關，協定的部分見 lsp 模組
+use crate::cst::{parse_cst, NodeKind, SyntaxElement, SyntaxNode, TokenKind};
+use crate::lexer::Token;
+use crate::*;
+use logos::Span;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub span: Span,
+    pub message: String,
+    pub help: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompletionKind {
+    DataType,
+    Category,
+    Pattern,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Completion {
+    pub label: String,
+    pub kind: CompletionKind,
+}
+
+const DATATYPES: &[(&str, &str)] = &[
+    ("單行", "不含換行的字串"),
+    ("文本", "任意字串，可接正則表達式或模式"),
+    ("數字", "整數"),
+    ("鍵結", "指向其他分類的文章"),
+    ("帶籤鍵結", "帶有標籤的鍵結"),
+    ("列表", "由同一型別的值組成的列表"),
+    ("列舉", "只能是其中之一的字串"),
+    ("布林", "真或假"),
+    ("日期時間", "RFC 3339 格式的日期與時間"),
+];
+
+// 原始碼中的一個 token 及其所在的分類與域
+#[derive(Debug)]
+struct Located {
+    span: Span,
+    token: Token<'static>,
+    category: Option<usize>, // categories 中的索引
+    field: Option<usize>,    // fields 中的索引
+}
+
+#[derive(Debug)]
+struct Item {
+    name: String,
+    span: Span, // 名稱的位置
+}
+
+pub struct Analysis {
+    force: Force,
+    errors: Vec<ForceError>,
+    tokens: Vec<Located>,
+    categories: Vec<Item>,
+    fields: Vec<Item>,
+    patterns: Vec<Item>,
+}
+
+struct Indexer {
+    offset: usize,
+    category: Option<usize>,
+    field: Option<usize>,
+    analysis: Analysis,
+}
+
+impl Indexer {
+    fn walk(&mut self, node: &SyntaxNode) {
+        let item = match node.kind {
+            NodeKind::Category => Some(&mut self.analysis.categories),
+            NodeKind::Field => Some(&mut self.analysis.fields),
+            NodeKind::Pattern => Some(&mut self.analysis.patterns),
+            NodeKind::Root | NodeKind::Import => None,
+        };
+        let named = item.is_some() && node.name().is_some();
+        if let (Some(items), Some(name)) = (item, node.name()) {
+            items.push(Item {
+                name: name.to_owned(),
+                span: 0..0,
+            });
+            let index = items.len() - 1;
+            match node.kind {
+                NodeKind::Category => self.category = Some(index),
+                NodeKind::Field => self.field = Some(index),
+                _ => {}
+            }
+        }
+        let mut last_identifier = None;
+        let mut past_name = false; // 已到反向連結名或計算域的算式
+        for child in &node.children {
+            match child {
+                SyntaxElement::Node(child) => self.walk(child),
+                SyntaxElement::Token(token) => {
+                    let span = self.offset..self.offset + token.text.len();
+                    self.offset = span.end;
+                    if let TokenKind::Token(t) = &token.kind {
+                        match t {
+                            Token::Identifier(_)
+                                if !past_name
+                                    && (last_identifier.is_none()
+                                        || node.kind == NodeKind::Field) =>
+                            {
+                                last_identifier = Some(span.clone());
+                            }
+                            Token::Inverse | Token::Equal => past_name = true,
+                            _ => {}
+                        }
+                        self.analysis.tokens.push(Located {
+                            span,
+                            token: t.clone(),
+                            category: self.category,
+                            field: self.field,
+                        });
+                    }
+                }
+            }
+        }
+        if !named {
+            return;
+        }
+        // 分類與模式取第一個識別子，域取反向連結與算式之前的最後一個
+        let items = match node.kind {
+            NodeKind::Category => &mut self.analysis.categories,
+            NodeKind::Field => &mut self.analysis.fields,
+            _ => &mut self.analysis.patterns,
+        };
+        if let (Some(item), Some(span)) = (items.last_mut(), last_identifier) {
+            item.span = span;
+        }
+        match node.kind {
+            NodeKind::Category => self.category = None,
+            NodeKind::Field => self.field = None,
+            _ => {}
+        }
+    }
+}
+
+impl Analysis {
+    pub fn new(source: &str) -> Analysis {
+        let (root, force, errors) = parse_cst(source);
+        let mut indexer = Indexer {
+            offset: 0,
+            category: None,
+            field: None,
+            analysis: Analysis {
+                force,
+                errors,
+                tokens: Vec::new(),
+                categories: Vec::new(),
+                fields: Vec::new(),
+                patterns: Vec::new(),
+            },
+        };
+        indexer.walk(&root);
+        indexer.analysis
+    }
+    pub fn force(&self) -> &Force {
+        &self.force
+    }
+    fn category_span(&self, name: &str) -> Option<Span> {
+        let item = self.categories.iter().find(|item| item.name == name)?;
+        Some(item.span.clone())
+    }
+    pub(crate) fn pattern_span(&self, name: &str) -> Option<Span> {
+        let item = self.patterns.iter().find(|item| item.name == name)?;
+        Some(item.span.clone())
+    }
+    // 分類名或其中某個域名的位置，繼承而來的域不在原始碼中
+    pub(crate) fn name_span(&self, category: &str, field: Option<&str>) -> Option<Span> {
+        let field = match field {
+            Some(field) => field,
+            None => return self.category_span(category),
+        };
+        self.tokens
+            .iter()
+            .find(|located| {
+                located
+                    .category
+                    .is_some_and(|i| self.categories[i].name == category)
+                    && located.field.is_some_and(|i| {
+                        self.fields[i].name == field && self.fields[i].span == located.span
+                    })
+            })
+            .map(|located| located.span.clone())
+    }
+    // 在某分類（和域）之中找出名稱為 text 的識別子
+    fn find_identifier(&self, category: &str, field: Option<&str>, text: &str) -> Option<Span> {
+        self.tokens
+            .iter()
+            .find(|located| {
+                located.token == Token::Identifier(text.into())
+                    && located
+                        .category
+                        .is_some_and(|i| self.categories[i].name == category)
+                    && field.is_none_or(|field| {
+                        located.field.is_some_and(|i| self.fields[i].name == field)
+                    })
+                    && (field.is_some() || located.field.is_none())
+            })
+            .map(|located| located.span.clone())
+    }
+    // 語意錯誤沒有位置，以相關的識別子代之
+    fn locate(&self, error: &ForceError) -> Span {
+        let span = match error {
+            ForceError::UnknownBondee {
+                category,
+                field,
+                bondee,
+            } => self.find_identifier(category, Some(field), bondee),
+            ForceError::UnknownParent { category, parent } => {
+                self.find_identifier(category, None, parent)
+            }
+            ForceError::InverseConflict {
+                category,
+                field,
+                inverse,
+                ..
+            } => self.find_identifier(category, Some(field), inverse),
+            ForceError::InvalidReference {
+                category,
+                field,
+                reference,
+            } => self.find_identifier(category, Some(field), reference),
+            ForceError::InheritanceCycle { categories } => self.category_span(&categories[0]),
+            error => error.span(),
+        };
+        span.unwrap_or(0..0)
+    }
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        let mut diagnostics: Vec<Diagnostic> = self
+            .errors
+            .iter()
+            .map(|error| Diagnostic {
+                span: self.locate(error),
+                message: error.to_string(),
+                help: error.help(),
+            })
+            .collect();
+        if let Err(errors) = self.force.validate() {
+            for error in errors {
+                let message = error.to_string();
+                if diagnostics.iter().all(|d| d.message != message) {
+                    diagnostics.push(Diagnostic {
+                        span: self.locate(&error),
+                        message,
+                        help: error.help(),
+                    });
+                }
+            }
+        }
+        diagnostics
+    }
+    fn token_at(&self, offset: usize) -> Option<&Located> {
+        self.tokens
+            .iter()
+            .find(|located| located.span.start <= offset && offset < located.span.end)
+    }
+    fn is_field_name(&self, located: &Located) -> bool {
+        located
+            .field
+            .is_some_and(|i| self.fields[i].span == located.span)
+    }
+    // 鍵結對象或父分類名稱跳至該分類，模式名跳至其宣告
+    pub fn definition(&self, offset: usize) -> Option<Span> {
+        let located = self.token_at(offset)?;
+        let name = match &located.token {
+            Token::Identifier(name) => name.as_ref(),
+            _ => return None,
+        };
+        if self.is_field_name(located) {
+            return None;
+        }
+        self.category_span(name).or_else(|| {
+            let item = self.patterns.iter().find(|item| item.name == name)?;
+            Some(item.span.clone())
+        })
+    }
+    fn describe_category(&self, category: &Category) -> String {
+        let mut ret = format!("分類 {}", category.name);
+        if let Some(doc) = &category.doc {
+            ret.push_str(&format!("\n\n{}", doc));
+        }
+        for field in &category.fields {
+            ret.push_str(&format!("\n- {} {}", field.datatype, field.name));
+        }
+        ret
+    }
+    pub fn hover(&self, offset: usize) -> Option<String> {
+        let located = self.token_at(offset)?;
+        if let Some((keyword, description)) = DATATYPES
+            .iter()
+            .find(|(keyword, _)| Some(keyword) == token_keyword(&located.token).as_ref())
+        {
+            return Some(format!("{}：{}", keyword, description));
+        }
+        let name = match &located.token {
+            Token::Identifier(name) => name.as_ref(),
+            _ => return None,
+        };
+        if self.is_field_name(located) {
+            let category = &self.categories[located.category?].name;
+            let field = self.force.categories.get(category)?.field(name)?;
+            let mut ret = format!("{} {}", field.datatype, field.name);
+            if field.optional {
+                ret.push_str("（可省略）");
+            }
+            if let Some(default) = &field.default {
+                ret.push_str(&format!(" = {}", default));
+            }
+            if let Some(doc) = &field.doc {
+                ret.push_str(&format!("\n\n{}", doc));
+            }
+            return Some(ret);
+        }
+        if let Some(category) = self.force.categories.get(name) {
+            return Some(self.describe_category(category));
+        }
+        let regex = self.force.patterns.get(name)?;
+        Some(format!("模式 {} = /{}/", name, regex))
+    }
+    pub fn completions(&self, offset: usize) -> Vec<Completion> {
+        let before: Vec<&Token> = self
+            .tokens
+            .iter()
+            .filter(|located| located.span.end <= offset)
+            .map(|located| &located.token)
+            .collect();
+        let depth = |open: Token, close: Token| {
+            before.iter().fold(0i64, |depth, token| {
+                if **token == open {
+                    depth + 1
+                } else if **token == close {
+                    depth - 1
+                } else {
+                    depth
+                }
+            })
+        };
+        let mut names: Vec<&String> = self.force.categories.keys().collect();
+        names.sort();
+        let categories = names.into_iter().map(|name| Completion {
+            label: name.clone(),
+            kind: CompletionKind::Category,
+        });
+        if depth(Token::LeftSquareBracket, Token::RightSquareBracket) > 0 {
+            return categories.collect();
+        }
+        if depth(Token::LeftCurlyBrace, Token::RightCurlyBrace) == 0 {
+            // 分類名之後的 : 接父分類
+            return match before.last() {
+                Some(Token::Colon) | Some(Token::Comma) => categories.collect(),
+                _ => Vec::new(),
+            };
+        }
+        if let Some(Token::Text) = before.last() {
+            let mut patterns: Vec<&String> = self.force.patterns.keys().collect();
+            patterns.sort();
+            return patterns
+                .into_iter()
+                .map(|name| Completion {
+                    label: name.clone(),
+                    kind: CompletionKind::Pattern,
+                })
+                .collect();
+        }
+        DATATYPES
+            .iter()
+            .map(|(keyword, _)| Completion {
+                label: keyword.to_string(),
+                kind: CompletionKind::DataType,
+            })
+            .collect()
+    }
+}
+
+fn token_keyword(token: &Token) -> Option<&'static str> {
+    match token {
+        Token::OneLine => Some("單行"),
+        Token::Text => Some("文本"),
+        Token::Number => Some("數字"),
+        Token::Bond => Some("鍵結"),
+        Token::TaggedBond => Some("帶籤鍵結"),
+        Token::Array => Some("列表"),
+        Token::Enum => Some("列舉"),
+        Token::Bool => Some("布林"),
+        Token::DateTime => Some("日期時間"),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    const SOURCE: &str = "/// 一篇文章
+文章 {單行 標題}
+回覆 {鍵結[文章] 原文 鍵結[問卦] 引用 數字? 讚數 = 0}
+留言 : 貼文 {文本 內文}
+";
+    fn offset(needle: &str, nth: usize) -> usize {
+        SOURCE.match_indices(needle).nth(nth).unwrap().0
+    }
+    #[test]
+    fn test_diagnostics() {
+        let analysis = Analysis::new(SOURCE);
+        let diagnostics = analysis.diagnostics();
+        let spans: Vec<&str> = diagnostics
+            .iter()
+            .map(|d| &SOURCE[d.span.clone()])
+            .collect();
+        assert_eq!(spans, vec!["貼文", "問卦"]);
+        let analysis = Analysis::new("文章 {單行}");
+        assert_eq!(analysis.diagnostics()[0].span, 14..15);
+    }
+    #[test]
+    fn test_definition() {
+        let analysis = Analysis::new(SOURCE);
+        let target = offset("文章", 1)..offset("文章", 1) + "文章".len();
+        assert_eq!(analysis.definition(offset("文章", 2)), Some(target));
+        assert_eq!(analysis.definition(offset("原文", 0)), None);
+        assert_eq!(analysis.definition(offset("問卦", 0)), None);
+    }
+    #[test]
+    fn test_hover() {
+        let analysis = Analysis::new(SOURCE);
+        assert_eq!(
+            analysis.hover(offset("讚數", 0)),
+            Some("數字 讚數（可省略） = 0".to_owned())
+        );
+        assert_eq!(
+            analysis.hover(offset("文章", 2)),
+            Some("分類 文章\n\n一篇文章\n- 單行 標題".to_owned())
+        );
+        assert_eq!(
+            analysis.hover(offset("單行", 0)),
+            Some("單行：不含換行的字串".to_owned())
+        );
+    }
+    #[test]
+    fn test_completions() {
+        let analysis = Analysis::new(SOURCE);
+        let labels = |offset: usize| -> Vec<String> {
+            analysis
+                .completions(offset)
+                .into_iter()
+                .map(|c| c.label)
+                .collect()
+        };
+        assert_eq!(labels(offset("文章]", 0)), vec!["回覆", "文章", "留言"]);
+        assert_eq!(labels(offset("原文", 0) + "原文".len() + 1).len(), 9);
+        assert!(labels(0).is_empty());
+        assert_eq!(labels(offset("貼文", 0)), vec!["回覆", "文章", "留言"]);
+    }
+}
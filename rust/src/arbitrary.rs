@@ -0,0 +1,369 @@
+// 由一串位元組產生隨機但合法的力以及對應的原始碼，供模糊測試與性質測試使用
+// 位元組用盡後一律視為 0 ，因此任何輸入都能產生出一份力，模糊測試器可直接餵入位元組
+// 產生的力經 format_force 輸出，再解析回來必定得到相同的分類與模式
+use crate::format::format_force;
+use crate::*;
+use regex::Regex;
+
+// 不與關鍵字衝突的識別子
+const NAMES: &[&str] = &[
+    "甲", "乙", "丙", "丁", "戊", "己", "庚", "辛", "壬", "癸", "文章", "留言", "alpha", "beta_2",
+];
+const REGEXES: &[&str] = &["[a-z]+", ".+@.+", "[0-9]{3}", "^a*b?$"];
+const DOCS: &[&str] = &["說明", "一段 文件 註解", "doc"];
+const STRINGS: &[&str] = &["", "a", "預設", "含 \"引號\" 與 \\"];
+
+// 依序消耗位元組做出選擇
+pub struct Unstructured<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Unstructured<'a> {
+    pub fn new(data: &'a [u8]) -> Unstructured<'a> {
+        Unstructured { data }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+    fn byte(&mut self) -> u8 {
+        match self.data.split_first() {
+            Some((byte, rest)) => {
+                self.data = rest;
+                *byte
+            }
+            None => 0,
+        }
+    }
+    // 0..n 之一， n 不超過 256
+    fn below(&mut self, n: usize) -> usize {
+        self.byte() as usize % n.max(1)
+    }
+    fn chance(&mut self) -> bool {
+        self.byte() & 1 == 1
+    }
+    fn choose<'b>(&mut self, items: &[&'b str]) -> &'b str {
+        items[self.below(items.len())]
+    }
+    fn int(&mut self) -> i64 {
+        self.below(201) as i64 - 100
+    }
+    // 從 NAMES 中取出 min 到 max 個不重複的名稱
+    fn names(&mut self, min: usize, max: usize) -> Vec<String> {
+        let len = min + self.below(max - min + 1);
+        let mut names: Vec<String> = Vec::new();
+        while names.len() < len {
+            // 重複時往後找第一個未用過的，以免位元組用盡後不斷選到同一個
+            let mut i = self.below(NAMES.len());
+            while names.iter().any(|n| n == NAMES[i]) {
+                i = (i + 1) % NAMES.len();
+            }
+            names.push(NAMES[i].to_owned());
+        }
+        names
+    }
+    fn doc(&mut self) -> Option<String> {
+        if self.chance() {
+            Some(self.choose(DOCS).to_owned())
+        } else {
+            None
+        }
+    }
+}
+
+// 以 splitmix64 將種子展開成位元組，方便以迴圈跑大量的性質測試
+pub fn seeded_bytes(seed: u64, len: usize) -> Vec<u8> {
+    let mut state = seed;
+    let mut bytes = Vec::with_capacity(len);
+    while bytes.len() < len {
+        state = state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^= z >> 31;
+        bytes.extend(z.to_le_bytes().iter().take(len - bytes.len()));
+    }
+    bytes
+}
+
+fn arbitrary_bounds(u: &mut Unstructured, lower: i64) -> (Option<i64>, Option<i64>) {
+    let min = if u.chance() {
+        Some(u.int().max(lower))
+    } else {
+        None
+    };
+    let max = if u.chance() {
+        let max = u.int().max(lower);
+        Some(min.map_or(max, |min| max.max(min)))
+    } else {
+        None
+    };
+    (min, max)
+}
+
+fn arbitrary_length(u: &mut Unstructured) -> (Option<usize>, Option<usize>) {
+    let (min, max) = arbitrary_bounds(u, 0);
+    (min.map(|n| n as usize), max.map(|n| n as usize))
+}
+
+fn arbitrary_bondee(u: &mut Unstructured, categories: &[String]) -> Bondee {
+    if u.chance() {
+        return Bondee::All;
+    }
+    if u.below(4) == 0 {
+        return Bondee::AllExcept(vec![categories[u.below(categories.len())].clone()]);
+    }
+    let len = 1 + u.below(categories.len());
+    let mut choices: Vec<String> = Vec::new();
+    for _ in 0..len {
+        let choice = &categories[u.below(categories.len())];
+        if !choices.contains(choice) {
+            choices.push(choice.clone());
+        }
+    }
+    Bondee::Choices(choices)
+}
+
+fn arbitrary_datatype(
+    u: &mut Unstructured,
+    categories: &[String],
+    patterns: &Patterns,
+    depth: usize,
+) -> DataType {
+    // 列表至多兩層
+    let kinds = if depth < 2 { 9 } else { 8 };
+    match u.below(kinds) {
+        0 => {
+            let bondee = arbitrary_bondee(u, categories);
+            let cardinality = if u.chance() {
+                Cardinality::One
+            } else {
+                Cardinality::Many
+            };
+            let relation = Relation {
+                cardinality,
+                inverse: None,
+            };
+            DataType::Bond(bondee, relation)
+        }
+        1 => {
+            let bondee = arbitrary_bondee(u, categories);
+            let tags = u
+                .names(0, 3)
+                .into_iter()
+                .map(|name| Tag {
+                    name,
+                    transfuse: (0..u.below(3)).map(|_| u.int()).collect(),
+                })
+                .collect();
+            DataType::TaggedBond(bondee, tags)
+        }
+        2 => {
+            let (min, max) = arbitrary_length(u);
+            DataType::OneLine(Bounds { min, max })
+        }
+        3 => {
+            let mut regexes: Vec<&str> = patterns.values().map(|s| s.as_str()).collect();
+            regexes.sort();
+            regexes.extend(REGEXES);
+            if u.chance() {
+                DataType::Text(Some(Regex::new(u.choose(&regexes)).unwrap()))
+            } else {
+                DataType::Text(None)
+            }
+        }
+        4 => {
+            let (min, max) = arbitrary_bounds(u, i64::MIN);
+            DataType::Number(Bounds { min, max })
+        }
+        5 => DataType::Enum(u.names(1, 3)),
+        6 => DataType::Bool,
+        7 => DataType::DateTime,
+        _ => {
+            let inner = Box::new(arbitrary_datatype(u, categories, patterns, depth + 1));
+            let (min, max) = arbitrary_length(u);
+            DataType::Array {
+                inner,
+                length: Bounds { min, max },
+            }
+        }
+    }
+}
+
+// 只為單行、數字、列舉與布林產生預設值，且必定符合其型別
+fn arbitrary_default(u: &mut Unstructured, datatype: &DataType) -> Option<serde_json::Value> {
+    if !u.chance() {
+        return None;
+    }
+    match datatype {
+        DataType::OneLine(bounds) => {
+            let s = u.choose(STRINGS);
+            if bounds.contains(s.chars().count()) {
+                Some(s.into())
+            } else {
+                None
+            }
+        }
+        DataType::Number(bounds) => {
+            let n = bounds.min.or(bounds.max).unwrap_or_else(|| u.int());
+            Some(n.into())
+        }
+        DataType::Enum(variants) => Some(variants[u.below(variants.len())].clone().into()),
+        DataType::Bool => Some(u.chance().into()),
+        _ => None,
+    }
+}
+
+fn arbitrary_attribute(u: &mut Unstructured, name: String) -> Attribute {
+    Attribute {
+        name,
+        arguments: (0..u.below(3))
+            .map(|_| {
+                let items = if u.chance() { NAMES } else { STRINGS };
+                u.choose(items).to_owned()
+            })
+            .collect(),
+    }
+}
+
+fn arbitrary_expr(u: &mut Unstructured, fields: &[String], depth: usize) -> Expr {
+    let leaf = depth >= 3 || u.chance();
+    match u.below(if leaf { 2 } else { 4 }) {
+        1 if !fields.is_empty() => Expr::Field(fields[u.below(fields.len())].clone()),
+        0 | 1 => Expr::Number(u.int()),
+        2 => Expr::Negate(Box::new(arbitrary_expr(u, fields, depth + 1))),
+        _ => {
+            let op = [BinaryOp::Add, BinaryOp::Sub, BinaryOp::Mul, BinaryOp::Div][u.below(4)];
+            Expr::Binary {
+                op,
+                left: Box::new(arbitrary_expr(u, fields, depth + 1)),
+                right: Box::new(arbitrary_expr(u, fields, depth + 1)),
+            }
+        }
+    }
+}
+
+fn arbitrary_category(
+    u: &mut Unstructured,
+    name: &str,
+    categories: &[String],
+    patterns: &Patterns,
+) -> Category {
+    let mut fields: Vec<Field> = u
+        .names(0, 5)
+        .into_iter()
+        .map(|name| {
+            let datatype = arbitrary_datatype(u, categories, patterns, 0);
+            Field {
+                optional: u.chance(),
+                default: arbitrary_default(u, &datatype),
+                doc: u.doc(),
+                unique: u.chance(),
+                key: false,
+                // 自訂屬性在嚴格模式下無法解析，只產生內建的
+                attributes: [READONLY, INDEXED]
+                    .iter()
+                    .filter_map(|name| {
+                        if u.chance() {
+                            Some(arbitrary_attribute(u, name.to_string()))
+                        } else {
+                            None
+                        }
+                    })
+                    .collect(),
+                datatype,
+                name,
+                computed: None,
+            }
+        })
+        .collect();
+    // 計算域只能引用非計算的數字域
+    let numbers: Vec<usize> = (0..fields.len())
+        .filter(|i| matches!(fields[*i].datatype, DataType::Number(_)))
+        .collect();
+    let computed: Vec<usize> = numbers.iter().copied().filter(|_| u.chance()).collect();
+    let sources: Vec<String> = numbers
+        .iter()
+        .filter(|i| !computed.contains(i))
+        .map(|i| fields[*i].name.clone())
+        .collect();
+    for i in computed {
+        fields[i].default = None;
+        fields[i].computed = Some(arbitrary_expr(u, &sources, 0));
+    }
+    if !fields.is_empty() && u.chance() {
+        let i = u.below(fields.len());
+        fields[i].key = true;
+    }
+    let title_field = if !fields.is_empty() && u.chance() {
+        Some(fields[u.below(fields.len())].name.clone())
+    } else {
+        None
+    };
+    let attributes = u
+        .names(0, 2)
+        .into_iter()
+        .map(|name| arbitrary_attribute(u, name))
+        .collect();
+    Category {
+        source: String::new(),
+        name: name.to_owned(),
+        fields,
+        title_field,
+        doc: u.doc(),
+        parents: Vec::new(),
+        attributes,
+    }
+}
+
+// 產生一份力以及其原始碼， force 與 parse(&source) 的結果相同
+pub fn arbitrary_force(u: &mut Unstructured) -> (Force, String) {
+    let mut patterns = Patterns::new();
+    for name in u.names(0, 2) {
+        patterns.insert(name, u.choose(REGEXES).to_owned());
+    }
+    let names = u.names(1, 4);
+    let categories = names
+        .iter()
+        .map(|name| {
+            let category = arbitrary_category(u, name, &names, &patterns);
+            (name.clone(), category)
+        })
+        .collect();
+    let mut force = Force {
+        categories,
+        patterns,
+    };
+    let source = format_force(&force);
+    // 分類的 source 是其在原始碼中的片段，只能由解析得知
+    let parsed = parse(&source).expect("產生的原始碼應能解析");
+    for (name, category) in force.categories.iter_mut() {
+        category.source = parsed.categories[name].source.clone();
+    }
+    (force, source)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_round_trip() -> ForceResult<()> {
+        for seed in 0..300 {
+            let bytes = seeded_bytes(seed, 256);
+            let (force, source) = arbitrary_force(&mut Unstructured::new(&bytes));
+            let parsed = parse(&source)?;
+            assert_eq!(parsed.categories, force.categories, "{}", source);
+            assert_eq!(parsed.patterns, force.patterns);
+            assert_eq!(format_force(&parsed), source);
+            assert!(force.validate().is_ok(), "{}", source);
+            let loaded = Force::from_ast_json(&force.to_ast_json())?;
+            assert_eq!(loaded.categories, force.categories);
+        }
+        Ok(())
+    }
+    #[test]
+    fn test_exhausted() {
+        let (force, _) = arbitrary_force(&mut Unstructured::new(&[]));
+        assert_eq!(force.category_names(), vec!["甲"]);
+        assert!(force.patterns.is_empty());
+    }
+}
@@ -0,0 +1,475 @@
+// 供 Python 腳本、持續整合檢查等非 Rust 工具使用的 JSON 格式，與內部的資料結構分開定義
+// AST_VERSION 不變時，既有欄位的名稱與意義保持穩定，只可能新增欄位
+// 頂層為 {"version", "patterns", "categories"} ，模式與分類依名稱排序，域維持原本順序，各節點的欄位見下方的 *_json 函式
+// 名稱的位置 span 為 {"start", "end"} ，以 UTF-8 位元組計，只有 source_to_ast_json 會填入，其餘為 null
+// 型別以 "kind" 區分： bond 、 tagged_bond 、 one_line 、 text 、 number 、 enum 、 bool 、 date_time 、 array
+// 計算域的算式亦以 "kind" 區分： number 、 field 、 negate 、 binary ，非計算域為 null
+use crate::analysis::Analysis;
+use crate::*;
+use logos::Span;
+use regex::Regex;
+use serde_json::{json, Value};
+
+// 格式改變時遞增，舊版的 JSON 將被拒絕
+pub const AST_VERSION: u64 = 1;
+
+fn span_json(span: Option<Span>) -> Value {
+    match span {
+        Some(span) => json!({"start": span.start, "end": span.end}),
+        None => Value::Null,
+    }
+}
+
+// kind 為 all 、 all_except 或 choices ，後兩者帶 names
+fn bondee_json(bondee: &Bondee) -> Value {
+    match bondee {
+        Bondee::All => json!({"kind": "all"}),
+        Bondee::AllExcept(names) => json!({"kind": "all_except", "names": names}),
+        Bondee::Choices(names) => json!({"kind": "choices", "names": names}),
+    }
+}
+
+fn datatype_json(datatype: &DataType) -> Value {
+    match datatype {
+        DataType::Bond(bondee, relation) => json!({
+            "kind": "bond",
+            "bondee": bondee_json(bondee),
+            "cardinality": match relation.cardinality {
+                Cardinality::One => "one",
+                Cardinality::Many => "many",
+            },
+            "inverse": relation.inverse,
+        }),
+        DataType::TaggedBond(bondee, tags) => {
+            let tags: Value = tags
+                .iter()
+                .map(|tag| json!({"name": tag.name, "transfuse": tag.transfuse}))
+                .collect();
+            json!({"kind": "tagged_bond", "bondee": bondee_json(bondee), "tags": tags})
+        }
+        DataType::OneLine(bounds) => {
+            json!({"kind": "one_line", "min": bounds.min, "max": bounds.max})
+        }
+        DataType::Text(regex) => {
+            json!({"kind": "text", "regex": regex.as_ref().map(|regex| regex.as_str())})
+        }
+        DataType::Number(bounds) => json!({"kind": "number", "min": bounds.min, "max": bounds.max}),
+        DataType::Enum(variants) => json!({"kind": "enum", "variants": variants}),
+        DataType::Bool => json!({"kind": "bool"}),
+        DataType::DateTime => json!({"kind": "date_time"}),
+        DataType::Array { inner, length } => json!({
+            "kind": "array",
+            "inner": datatype_json(inner),
+            "min": length.min,
+            "max": length.max,
+        }),
+    }
+}
+
+const OPS: [(BinaryOp, &str); 4] = [
+    (BinaryOp::Add, "add"),
+    (BinaryOp::Sub, "sub"),
+    (BinaryOp::Mul, "mul"),
+    (BinaryOp::Div, "div"),
+];
+
+fn expr_json(expr: &Expr) -> Value {
+    match expr {
+        Expr::Number(n) => json!({"kind": "number", "value": n}),
+        Expr::Field(name) => json!({"kind": "field", "name": name}),
+        Expr::Negate(inner) => json!({"kind": "negate", "operand": expr_json(inner)}),
+        Expr::Binary { op, left, right } => {
+            let (_, op) = OPS.iter().find(|(o, _)| o == op).unwrap();
+            json!({"kind": "binary", "op": op, "left": expr_json(left), "right": expr_json(right)})
+        }
+    }
+}
+
+fn attributes_json(attributes: &[Attribute]) -> Value {
+    attributes
+        .iter()
+        .map(|attribute| json!({"name": attribute.name, "arguments": attribute.arguments}))
+        .collect()
+}
+
+// fields 包含繼承而來的域，其 span 為 null
+fn category_json(category: &Category, analysis: Option<&Analysis>) -> Value {
+    let span = |field: Option<&str>| {
+        span_json(analysis.and_then(|analysis| analysis.name_span(&category.name, field)))
+    };
+    let fields: Value = category
+        .fields
+        .iter()
+        .map(|field| {
+            json!({
+                "name": field.name,
+                "span": span(Some(&field.name)),
+                "doc": field.doc,
+                "type": datatype_json(&field.datatype),
+                "optional": field.optional,
+                "unique": field.unique,
+                "key": field.key,
+                "default": field.default,
+                "attributes": attributes_json(&field.attributes),
+                "computed": field.computed.as_ref().map(expr_json),
+            })
+        })
+        .collect();
+    json!({
+        "name": category.name,
+        "span": span(None),
+        "doc": category.doc,
+        "attributes": attributes_json(&category.attributes),
+        "parents": category.parents,
+        "title_field": category.title_field,
+        "fields": fields,
+        "source": category.source,
+    })
+}
+
+fn force_json(force: &Force, analysis: Option<&Analysis>) -> Value {
+    let mut names: Vec<&String> = force.patterns.keys().collect();
+    names.sort();
+    let patterns: Value = names
+        .into_iter()
+        .map(|name| {
+            let span = analysis.and_then(|analysis| analysis.pattern_span(name));
+            json!({"name": name, "span": span_json(span), "regex": force.patterns[name]})
+        })
+        .collect();
+    let categories: Value = force
+        .category_names()
+        .into_iter()
+        .map(|name| category_json(&force.categories[name], analysis))
+        .collect();
+    json!({"version": AST_VERSION, "patterns": patterns, "categories": categories})
+}
+
+// 解析原始碼並附上各名稱的位置
+pub fn source_to_ast_json(source: &str) -> ForceResult<Value> {
+    let force = parse(source)?;
+    let analysis = Analysis::new(source);
+    Ok(force_json(&force, Some(&analysis)))
+}
+
+// 讀取中的節點及其路徑，錯誤訊息以 categories[0].fields[1].type 的形式指出位置
+struct Node<'v> {
+    value: &'v Value,
+    path: String,
+}
+
+impl<'v> Node<'v> {
+    fn invalid(&self, message: &str) -> ForceError {
+        ForceError::InvalidAst {
+            message: format!("{} {}", self.path, message),
+        }
+    }
+    // 不存在的欄位視為 null
+    fn get(&self, key: &str) -> Node<'v> {
+        Node {
+            value: self.value.get(key).unwrap_or(&Value::Null),
+            path: format!("{}.{}", self.path, key),
+        }
+    }
+    fn items(&self) -> ForceResult<Vec<Node<'v>>> {
+        let items = match self.value {
+            Value::Array(items) => items,
+            Value::Null => return Ok(Vec::new()),
+            _ => return Err(self.invalid("應為陣列")),
+        };
+        let nodes = items.iter().enumerate().map(|(i, value)| Node {
+            value,
+            path: format!("{}[{}]", self.path, i),
+        });
+        Ok(nodes.collect())
+    }
+    fn string(&self) -> ForceResult<String> {
+        match self.value {
+            Value::String(s) => Ok(s.clone()),
+            _ => Err(self.invalid("應為字串")),
+        }
+    }
+    fn optional_string(&self) -> ForceResult<Option<String>> {
+        match self.value {
+            Value::Null => Ok(None),
+            _ => self.string().map(Some),
+        }
+    }
+    fn strings(&self) -> ForceResult<Vec<String>> {
+        self.items()?.iter().map(Node::string).collect()
+    }
+    fn bool(&self) -> ForceResult<bool> {
+        match self.value {
+            Value::Bool(b) => Ok(*b),
+            Value::Null => Ok(false),
+            _ => Err(self.invalid("應為布林值")),
+        }
+    }
+    fn optional_i64(&self) -> ForceResult<Option<i64>> {
+        match self.value {
+            Value::Null => Ok(None),
+            value => value
+                .as_i64()
+                .map(Some)
+                .ok_or_else(|| self.invalid("應為整數")),
+        }
+    }
+    fn optional_usize(&self) -> ForceResult<Option<usize>> {
+        match self.value {
+            Value::Null => Ok(None),
+            value => value
+                .as_u64()
+                .map(|n| Some(n as usize))
+                .ok_or_else(|| self.invalid("應為非負整數")),
+        }
+    }
+    fn regex(&self) -> ForceResult<Regex> {
+        Regex::new(&self.string()?).map_err(|_| self.invalid("不是合法的正則表達式"))
+    }
+    fn bondee(&self) -> ForceResult<Bondee> {
+        let kind = self.get("kind");
+        match kind.string()?.as_str() {
+            "all" => Ok(Bondee::All),
+            "all_except" => Ok(Bondee::AllExcept(self.get("names").strings()?)),
+            "choices" => Ok(Bondee::Choices(self.get("names").strings()?)),
+            other => Err(kind.invalid(&format!("未知的鍵結對象 {}", other))),
+        }
+    }
+    fn datatype(&self) -> ForceResult<DataType> {
+        let kind = self.get("kind");
+        let datatype = match kind.string()?.as_str() {
+            "bond" => {
+                let cardinality = self.get("cardinality");
+                let cardinality = match cardinality.optional_string()?.as_deref() {
+                    Some("one") => Cardinality::One,
+                    Some("many") | None => Cardinality::Many,
+                    Some(other) => return Err(cardinality.invalid(&format!("未知的 {}", other))),
+                };
+                let relation = Relation {
+                    cardinality,
+                    inverse: self.get("inverse").optional_string()?,
+                };
+                DataType::Bond(self.get("bondee").bondee()?, relation)
+            }
+            "tagged_bond" => {
+                let mut tags = Vec::new();
+                for tag in self.get("tags").items()? {
+                    let transfuse = tag.get("transfuse").items()?;
+                    let transfuse = transfuse
+                        .iter()
+                        .map(|n| n.optional_i64()?.ok_or_else(|| n.invalid("應為整數")));
+                    tags.push(Tag {
+                        name: tag.get("name").string()?,
+                        transfuse: transfuse.collect::<ForceResult<_>>()?,
+                    });
+                }
+                DataType::TaggedBond(self.get("bondee").bondee()?, tags)
+            }
+            "one_line" => DataType::OneLine(Bounds {
+                min: self.get("min").optional_usize()?,
+                max: self.get("max").optional_usize()?,
+            }),
+            "text" => {
+                let regex = self.get("regex");
+                match regex.value {
+                    Value::Null => DataType::Text(None),
+                    _ => DataType::Text(Some(regex.regex()?)),
+                }
+            }
+            "number" => DataType::Number(Bounds {
+                min: self.get("min").optional_i64()?,
+                max: self.get("max").optional_i64()?,
+            }),
+            "enum" => DataType::Enum(self.get("variants").strings()?),
+            "bool" => DataType::Bool,
+            "date_time" => DataType::DateTime,
+            "array" => DataType::Array {
+                inner: Box::new(self.get("inner").datatype()?),
+                length: Bounds {
+                    min: self.get("min").optional_usize()?,
+                    max: self.get("max").optional_usize()?,
+                },
+            },
+            other => return Err(kind.invalid(&format!("未知的型別 {}", other))),
+        };
+        Ok(datatype)
+    }
+    fn attributes(&self) -> ForceResult<Vec<Attribute>> {
+        let attributes = self.items()?.into_iter().map(|attribute| {
+            Ok(Attribute {
+                name: attribute.get("name").string()?,
+                arguments: attribute.get("arguments").strings()?,
+            })
+        });
+        attributes.collect()
+    }
+    fn field(&self) -> ForceResult<Field> {
+        let default = match self.get("default").value {
+            Value::Null => None,
+            value => Some(value.clone()),
+        };
+        let computed = self.get("computed");
+        let computed = match computed.value {
+            Value::Null => None,
+            _ => Some(computed.expr()?),
+        };
+        Ok(Field {
+            datatype: self.get("type").datatype()?,
+            name: self.get("name").string()?,
+            optional: self.get("optional").bool()?,
+            default,
+            doc: self.get("doc").optional_string()?,
+            unique: self.get("unique").bool()?,
+            key: self.get("key").bool()?,
+            attributes: self.get("attributes").attributes()?,
+            computed,
+        })
+    }
+    fn expr(&self) -> ForceResult<Expr> {
+        let kind = self.get("kind");
+        let expr = match kind.string()?.as_str() {
+            "number" => {
+                let value = self.get("value");
+                Expr::Number(
+                    value
+                        .optional_i64()?
+                        .ok_or_else(|| value.invalid("應為整數"))?,
+                )
+            }
+            "field" => Expr::Field(self.get("name").string()?),
+            "negate" => Expr::Negate(Box::new(self.get("operand").expr()?)),
+            "binary" => {
+                let op = self.get("op");
+                let name = op.string()?;
+                let (op, _) = OPS
+                    .iter()
+                    .find(|(_, n)| *n == name)
+                    .ok_or_else(|| op.invalid(&format!("未知的運算子 {}", name)))?;
+                Expr::Binary {
+                    op: *op,
+                    left: Box::new(self.get("left").expr()?),
+                    right: Box::new(self.get("right").expr()?),
+                }
+            }
+            other => return Err(kind.invalid(&format!("未知的算式 {}", other))),
+        };
+        Ok(expr)
+    }
+    fn category(&self) -> ForceResult<Category> {
+        let fields = self.get("fields").items()?;
+        Ok(Category {
+            source: self.get("source").optional_string()?.unwrap_or_default(),
+            name: self.get("name").string()?,
+            fields: fields.iter().map(Node::field).collect::<ForceResult<_>>()?,
+            title_field: self.get("title_field").optional_string()?,
+            doc: self.get("doc").optional_string()?,
+            parents: self.get("parents").strings()?,
+            attributes: self.get("attributes").attributes()?,
+        })
+    }
+}
+
+impl Force {
+    pub fn to_ast_json(&self) -> Value {
+        force_json(self, None)
+    }
+    // span 與 source 可省略，不做語意檢查
+    pub fn from_ast_json(value: &Value) -> ForceResult<Force> {
+        let root = Node {
+            value,
+            path: "$".to_owned(),
+        };
+        let version = root.get("version");
+        if version.value.as_u64() != Some(AST_VERSION) {
+            return Err(version.invalid(&format!("不支援的版本 {}", version.value)));
+        }
+        let mut force = Force::default();
+        for pattern in root.get("patterns").items()? {
+            pattern.get("regex").regex()?;
+            let name = pattern.get("name").string()?;
+            force.patterns.insert(name, pattern.get("regex").string()?);
+        }
+        for category in root.get("categories").items()? {
+            let category = category.category()?;
+            force.categories.insert(category.name.clone(), category);
+        }
+        Ok(force)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_ast_round_trip() -> ForceResult<()> {
+        let source = "模式 信箱 = /.+@.+/
+            /// 文章
+            @限定(管理員) 文章 {@title @key 單行(..64) 標題 數字(-5..) 溫度 = -1 @索引 文本 信箱? 聯絡
+                計算 數字 熱度 = -溫度 * 2}
+            回覆 : 文章 {鍵結[文章] 單一 原文 反向 回覆 列表<鍵結[* except 回覆]>(1..3) 引用
+                帶籤鍵結[*] {挺 {輸能: [-3, 2]} 噓 {}} 評價 列舉 {草稿, 封存} 狀態 = \"草稿\"}";
+        let force = parse(source)?;
+        let ast = source_to_ast_json(source)?;
+        assert_eq!(ast["version"], 1);
+        assert_eq!(ast["patterns"][0]["name"], "信箱");
+        let category = &ast["categories"][1];
+        assert_eq!(category["name"], "文章");
+        assert_eq!(
+            category["attributes"][0],
+            json!({"name": "限定", "arguments": ["管理員"]})
+        );
+        let span = &category["fields"][0]["span"];
+        let span = span["start"].as_u64().unwrap() as usize..span["end"].as_u64().unwrap() as usize;
+        assert_eq!(&source[span], "標題");
+        assert_eq!(category["fields"][0]["computed"], Value::Null);
+        assert_eq!(
+            category["fields"][3]["computed"],
+            json!({
+                "kind": "binary",
+                "op": "mul",
+                "left": {"kind": "negate", "operand": {"kind": "field", "name": "溫度"}},
+                "right": {"kind": "number", "value": 2},
+            })
+        );
+        // 繼承而來的域
+        assert_eq!(ast["categories"][0]["fields"][0]["span"], Value::Null);
+        assert_eq!(
+            ast["categories"][0]["fields"][5]["type"],
+            json!({
+                "kind": "array",
+                "inner": {
+                    "kind": "bond",
+                    "bondee": {"kind": "all_except", "names": ["回覆"]},
+                    "cardinality": "many",
+                    "inverse": null,
+                },
+                "min": 1,
+                "max": 3,
+            })
+        );
+
+        let loaded = Force::from_ast_json(&ast)?;
+        assert_eq!(loaded.categories, force.categories);
+        assert_eq!(loaded.patterns, force.patterns);
+        assert_eq!(force.to_ast_json()["categories"][1]["span"], Value::Null);
+        Ok(())
+    }
+    #[test]
+    fn test_invalid_ast() {
+        let message = |value: Value| match Force::from_ast_json(&value) {
+            Err(ForceError::InvalidAst { message }) => message,
+            other => panic!("預期 InvalidAst ，但得到 {:?}", other),
+        };
+        assert_eq!(message(json!({"version": 99})), "$.version 不支援的版本 99");
+        let value = json!({
+            "version": 1,
+            "categories": [{"name": "文章", "fields": [{"name": "標題", "type": {"kind": "單行"}}]}],
+        });
+        assert_eq!(
+            message(value),
+            "$.categories[0].fields[0].type.kind 未知的型別 單行"
+        );
+        let value = json!({"version": 1, "categories": [{"name": "文章", "fields": {}}]});
+        assert_eq!(message(value), "$.categories[0].fields 應為陣列");
+    }
+}
@@ -0,0 +1,7 @@
+// 以標準輸入輸出與編輯器溝通的力語言伺服器
+fn main() {
+    if let Err(error) = force::lsp::run() {
+        eprintln!("{}", error);
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,7 @@
+// 互動式的力語言環境，輸入 help 查看可用的指令
+fn main() {
+    if let Err(error) = force::repl::run() {
+        eprintln!("{}", error);
+        std::process::exit(1);
+    }
+}
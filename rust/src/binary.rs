@@ -0,0 +1,504 @@
+// 預先編譯好的力的二進位格式，伺服器啟動時可直接載入而不必重新解析原始碼
+// 開頭為 FORC 與兩個位元組的版本號，之後的整數皆以 LEB128 變長編碼，有號數先做 zigzag
+// 分類與模式依名稱排序，同一份力總是編碼出相同的位元組
+use crate::*;
+use regex::Regex;
+
+const MAGIC: &[u8; 4] = b"FORC";
+// 格式改變時遞增，舊版的位元組將被拒絕
+pub const BINARY_VERSION: u16 = 6;
+
+struct Writer {
+    out: Vec<u8>,
+}
+
+impl Writer {
+    fn byte(&mut self, byte: u8) {
+        self.out.push(byte);
+    }
+    fn unsigned(&mut self, mut n: u64) {
+        loop {
+            let byte = (n & 0x7f) as u8;
+            n >>= 7;
+            if n == 0 {
+                self.byte(byte);
+                return;
+            }
+            self.byte(byte | 0x80);
+        }
+    }
+    fn signed(&mut self, n: i64) {
+        self.unsigned(((n << 1) ^ (n >> 63)) as u64);
+    }
+    fn string(&mut self, s: &str) {
+        self.unsigned(s.len() as u64);
+        self.out.extend_from_slice(s.as_bytes());
+    }
+    fn option<T>(&mut self, value: &Option<T>, write: impl FnOnce(&mut Writer, &T)) {
+        match value {
+            None => self.byte(0),
+            Some(value) => {
+                self.byte(1);
+                write(self, value);
+            }
+        }
+    }
+    fn strings(&mut self, strings: &[String]) {
+        self.unsigned(strings.len() as u64);
+        for s in strings {
+            self.string(s);
+        }
+    }
+    fn bondee(&mut self, bondee: &Bondee) {
+        match bondee {
+            Bondee::All => self.byte(0),
+            Bondee::Choices(choices) => {
+                self.byte(1);
+                self.strings(choices);
+            }
+            Bondee::AllExcept(excluded) => {
+                self.byte(2);
+                self.strings(excluded);
+            }
+        }
+    }
+    fn datatype(&mut self, datatype: &DataType) {
+        match datatype {
+            DataType::Bond(bondee, relation) => {
+                self.byte(0);
+                self.bondee(bondee);
+                self.byte(match relation.cardinality {
+                    Cardinality::One => 0,
+                    Cardinality::Many => 1,
+                });
+                self.option(&relation.inverse, |w, inverse| w.string(inverse));
+            }
+            DataType::TaggedBond(bondee, tags) => {
+                self.byte(1);
+                self.bondee(bondee);
+                self.unsigned(tags.len() as u64);
+                for tag in tags {
+                    self.string(&tag.name);
+                    self.unsigned(tag.transfuse.len() as u64);
+                    for n in &tag.transfuse {
+                        self.signed(*n);
+                    }
+                }
+            }
+            DataType::OneLine(bounds) => {
+                self.byte(2);
+                self.option(&bounds.min, |w, n| w.unsigned(*n as u64));
+                self.option(&bounds.max, |w, n| w.unsigned(*n as u64));
+            }
+            DataType::Text(regex) => {
+                self.byte(3);
+                self.option(regex, |w, regex| w.string(regex.as_str()));
+            }
+            DataType::Number(bounds) => {
+                self.byte(4);
+                self.option(&bounds.min, |w, n| w.signed(*n));
+                self.option(&bounds.max, |w, n| w.signed(*n));
+            }
+            DataType::Enum(variants) => {
+                self.byte(5);
+                self.strings(variants);
+            }
+            DataType::Bool => self.byte(7),
+            DataType::DateTime => self.byte(8),
+            DataType::Array { inner, length } => {
+                self.byte(6);
+                self.datatype(inner);
+                self.option(&length.min, |w, n| w.unsigned(*n as u64));
+                self.option(&length.max, |w, n| w.unsigned(*n as u64));
+            }
+        }
+    }
+    fn field(&mut self, field: &Field) {
+        self.string(&field.name);
+        self.datatype(&field.datatype);
+        self.byte(field.optional as u8);
+        self.option(&field.default, |w, value| w.string(&value.to_string()));
+        self.option(&field.doc, |w, doc| w.string(doc));
+        self.byte(field.unique as u8);
+        self.byte(field.key as u8);
+        self.attributes(&field.attributes);
+        self.option(&field.computed, Writer::expr);
+    }
+    fn expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Number(n) => {
+                self.byte(0);
+                self.signed(*n);
+            }
+            Expr::Field(name) => {
+                self.byte(1);
+                self.string(name);
+            }
+            Expr::Negate(inner) => {
+                self.byte(2);
+                self.expr(inner);
+            }
+            Expr::Binary { op, left, right } => {
+                self.byte(3);
+                self.byte(match op {
+                    BinaryOp::Add => 0,
+                    BinaryOp::Sub => 1,
+                    BinaryOp::Mul => 2,
+                    BinaryOp::Div => 3,
+                });
+                self.expr(left);
+                self.expr(right);
+            }
+        }
+    }
+    fn attributes(&mut self, attributes: &[Attribute]) {
+        self.unsigned(attributes.len() as u64);
+        for attribute in attributes {
+            self.string(&attribute.name);
+            self.strings(&attribute.arguments);
+        }
+    }
+    fn category(&mut self, category: &Category) {
+        self.string(&category.name);
+        self.string(&category.source);
+        self.strings(&category.parents);
+        self.option(&category.title_field, |w, title| w.string(title));
+        self.option(&category.doc, |w, doc| w.string(doc));
+        self.attributes(&category.attributes);
+        self.unsigned(category.fields.len() as u64);
+        for field in &category.fields {
+            self.field(field);
+        }
+    }
+}
+
+// 型別與算式的巢狀層數上限，避免惡意資料耗盡堆疊
+const MAX_DEPTH: usize = 128;
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    depth: usize,
+}
+
+fn invalid(message: &str) -> ForceError {
+    ForceError::InvalidBinary {
+        message: message.to_owned(),
+    }
+}
+
+impl<'a> Reader<'a> {
+    fn nested<T>(&mut self, read: fn(&mut Reader<'a>) -> ForceResult<T>) -> ForceResult<T> {
+        if self.depth >= MAX_DEPTH {
+            return Err(invalid("巢狀過深"));
+        }
+        self.depth += 1;
+        let result = read(self);
+        self.depth -= 1;
+        result
+    }
+    fn byte(&mut self) -> ForceResult<u8> {
+        let byte = *self
+            .bytes
+            .get(self.pos)
+            .ok_or_else(|| invalid("資料提前結束"))?;
+        self.pos += 1;
+        Ok(byte)
+    }
+    fn unsigned(&mut self) -> ForceResult<u64> {
+        let mut n = 0u64;
+        let mut shift = 0;
+        loop {
+            let byte = self.byte()?;
+            if shift >= 64 {
+                return Err(invalid("整數過長"));
+            }
+            n |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(n);
+            }
+            shift += 7;
+        }
+    }
+    fn signed(&mut self) -> ForceResult<i64> {
+        let n = self.unsigned()?;
+        Ok(((n >> 1) as i64) ^ -((n & 1) as i64))
+    }
+    fn usize(&mut self) -> ForceResult<usize> {
+        Ok(self.unsigned()? as usize)
+    }
+    // 長度不可能超過剩餘的位元組數，先檢查以免被偽造的長度耗盡記憶體
+    fn len(&mut self) -> ForceResult<usize> {
+        let len = self.usize()?;
+        if len > self.bytes.len() - self.pos {
+            return Err(invalid("長度超出資料範圍"));
+        }
+        Ok(len)
+    }
+    fn string(&mut self) -> ForceResult<String> {
+        let len = self.len()?;
+        let bytes = &self.bytes[self.pos..self.pos + len];
+        self.pos += len;
+        String::from_utf8(bytes.to_vec()).map_err(|_| invalid("字串不是合法的 UTF-8"))
+    }
+    fn bool(&mut self) -> ForceResult<bool> {
+        match self.byte()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(invalid("不合法的布林值")),
+        }
+    }
+    fn option<T>(
+        &mut self,
+        read: impl FnOnce(&mut Reader<'a>) -> ForceResult<T>,
+    ) -> ForceResult<Option<T>> {
+        if self.bool()? {
+            Ok(Some(read(self)?))
+        } else {
+            Ok(None)
+        }
+    }
+    fn strings(&mut self) -> ForceResult<Vec<String>> {
+        let len = self.len()?;
+        (0..len).map(|_| self.string()).collect()
+    }
+    fn bondee(&mut self) -> ForceResult<Bondee> {
+        match self.byte()? {
+            0 => Ok(Bondee::All),
+            1 => Ok(Bondee::Choices(self.strings()?)),
+            2 => Ok(Bondee::AllExcept(self.strings()?)),
+            _ => Err(invalid("不合法的鍵結對象")),
+        }
+    }
+    fn datatype(&mut self) -> ForceResult<DataType> {
+        let datatype = match self.byte()? {
+            0 => {
+                let bondee = self.bondee()?;
+                let cardinality = match self.byte()? {
+                    0 => Cardinality::One,
+                    1 => Cardinality::Many,
+                    _ => return Err(invalid("不合法的鍵結數量")),
+                };
+                let inverse = self.option(Reader::string)?;
+                DataType::Bond(
+                    bondee,
+                    Relation {
+                        cardinality,
+                        inverse,
+                    },
+                )
+            }
+            1 => {
+                let bondee = self.bondee()?;
+                let len = self.len()?;
+                let mut tags = Vec::new();
+                for _ in 0..len {
+                    let name = self.string()?;
+                    let count = self.len()?;
+                    let transfuse = (0..count)
+                        .map(|_| self.signed())
+                        .collect::<ForceResult<_>>()?;
+                    tags.push(Tag { name, transfuse });
+                }
+                DataType::TaggedBond(bondee, tags)
+            }
+            2 => DataType::OneLine(Bounds {
+                min: self.option(Reader::usize)?,
+                max: self.option(Reader::usize)?,
+            }),
+            3 => {
+                let regex = self.option(Reader::string)?;
+                let regex = regex
+                    .map(|regex| Regex::new(&regex).map_err(|_| invalid("不合法的正則表達式")))
+                    .transpose()?;
+                DataType::Text(regex)
+            }
+            4 => DataType::Number(Bounds {
+                min: self.option(Reader::signed)?,
+                max: self.option(Reader::signed)?,
+            }),
+            5 => DataType::Enum(self.strings()?),
+            6 => DataType::Array {
+                inner: Box::new(self.nested(Reader::datatype)?),
+                length: Bounds {
+                    min: self.option(Reader::usize)?,
+                    max: self.option(Reader::usize)?,
+                },
+            },
+            7 => DataType::Bool,
+            8 => DataType::DateTime,
+            _ => return Err(invalid("不合法的型別")),
+        };
+        Ok(datatype)
+    }
+    fn field(&mut self) -> ForceResult<Field> {
+        Ok(Field {
+            name: self.string()?,
+            datatype: self.datatype()?,
+            optional: self.bool()?,
+            default: self.option(|r| {
+                serde_json::from_str(&r.string()?).map_err(|_| invalid("不合法的預設值"))
+            })?,
+            doc: self.option(Reader::string)?,
+            unique: self.bool()?,
+            key: self.bool()?,
+            attributes: self.attributes()?,
+            computed: self.option(Reader::expr)?,
+        })
+    }
+    fn expr(&mut self) -> ForceResult<Expr> {
+        let expr = match self.byte()? {
+            0 => Expr::Number(self.signed()?),
+            1 => Expr::Field(self.string()?),
+            2 => Expr::Negate(Box::new(self.nested(Reader::expr)?)),
+            3 => {
+                let op = match self.byte()? {
+                    0 => BinaryOp::Add,
+                    1 => BinaryOp::Sub,
+                    2 => BinaryOp::Mul,
+                    3 => BinaryOp::Div,
+                    _ => return Err(invalid("不合法的運算子")),
+                };
+                Expr::Binary {
+                    op,
+                    left: Box::new(self.nested(Reader::expr)?),
+                    right: Box::new(self.nested(Reader::expr)?),
+                }
+            }
+            _ => return Err(invalid("不合法的算式")),
+        };
+        Ok(expr)
+    }
+    fn attributes(&mut self) -> ForceResult<Vec<Attribute>> {
+        let len = self.len()?;
+        (0..len)
+            .map(|_| {
+                Ok(Attribute {
+                    name: self.string()?,
+                    arguments: self.strings()?,
+                })
+            })
+            .collect()
+    }
+    fn category(&mut self) -> ForceResult<Category> {
+        let name = self.string()?;
+        let source = self.string()?;
+        let parents = self.strings()?;
+        let title_field = self.option(Reader::string)?;
+        let doc = self.option(Reader::string)?;
+        let attributes = self.attributes()?;
+        let len = self.len()?;
+        let fields = (0..len).map(|_| self.field()).collect::<ForceResult<_>>()?;
+        Ok(Category {
+            source,
+            name,
+            fields,
+            title_field,
+            doc,
+            parents,
+            attributes,
+        })
+    }
+}
+
+impl Force {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut writer = Writer { out: Vec::new() };
+        writer.out.extend_from_slice(MAGIC);
+        writer.out.extend_from_slice(&BINARY_VERSION.to_le_bytes());
+        let mut names: Vec<&String> = self.patterns.keys().collect();
+        names.sort();
+        writer.unsigned(names.len() as u64);
+        for name in names {
+            writer.string(name);
+            writer.string(&self.patterns[name]);
+        }
+        let names = self.category_names();
+        writer.unsigned(names.len() as u64);
+        for name in names {
+            writer.category(&self.categories[name]);
+        }
+        writer.out
+    }
+    pub fn from_bytes(bytes: &[u8]) -> ForceResult<Force> {
+        if bytes.len() < 6 || &bytes[..4] != MAGIC {
+            return Err(invalid("不是力的二進位格式"));
+        }
+        let version = u16::from_le_bytes([bytes[4], bytes[5]]);
+        if version != BINARY_VERSION {
+            return Err(ForceError::InvalidBinary {
+                message: format!("不支援的版本 {}", version),
+            });
+        }
+        let mut reader = Reader {
+            bytes,
+            pos: 6,
+            depth: 0,
+        };
+        let mut force = Force {
+            categories: Categories::new(),
+            patterns: Patterns::new(),
+        };
+        for _ in 0..reader.len()? {
+            let name = reader.string()?;
+            let pattern = reader.string()?;
+            force.patterns.insert(name, pattern);
+        }
+        for _ in 0..reader.len()? {
+            let category = reader.category()?;
+            force.categories.insert(category.name.clone(), category);
+        }
+        if reader.pos != bytes.len() {
+            return Err(invalid("結尾有多餘的資料"));
+        }
+        Ok(force)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_round_trip() -> ForceResult<()> {
+        let source = "模式 信箱 = /.+@.+/
+            /// 文章
+            @限定(管理員) 文章 {@title @key 單行(..64) 標題 數字(-5..) 溫度 = -1 @unique @唯讀 文本 信箱? 聯絡}
+            回覆 : 文章 {鍵結[文章] 單一 原文 反向 回覆 列表<鍵結[文章]>(1..3) 引用 帶籤鍵結[*] {挺 {輸能: [-3, 2]} 噓 {}} 評價
+                列舉 {草稿, 封存} 狀態 = \"草稿\" 計算 數字 熱度 = -(溫度 + 1) * 2 / 3}";
+        let force = parse(source)?;
+        let bytes = force.to_bytes();
+        assert_eq!(&bytes[..6], b"FORC\x06\x00");
+        let loaded = Force::from_bytes(&bytes)?;
+        assert_eq!(loaded.categories, force.categories);
+        assert_eq!(loaded.patterns, force.patterns);
+        assert_eq!(loaded.to_bytes(), bytes);
+        Ok(())
+    }
+    #[test]
+    fn test_invalid_bytes() -> ForceResult<()> {
+        let bytes = parse("文章 {單行 標題}")?.to_bytes();
+        assert!(Force::from_bytes(b"FORC").is_err());
+        let mut newer = bytes.clone();
+        newer[4] = 9;
+        match Force::from_bytes(&newer) {
+            Err(ForceError::InvalidBinary { message }) => assert_eq!(message, "不支援的版本 9"),
+            other => panic!("預期 InvalidBinary ，但得到 {:?}", other),
+        }
+        for len in 6..bytes.len() {
+            assert!(Force::from_bytes(&bytes[..len]).is_err());
+        }
+        let mut longer = bytes.clone();
+        longer.push(0);
+        assert!(Force::from_bytes(&longer).is_err());
+        // 在域的型別處塞入大量的列表標記
+        let list = parse("文章 {列表<單行> 標題}")?.to_bytes();
+        let start = list.windows(2).rposition(|w| w == [6, 2]).unwrap();
+        let mut deep = list[..start].to_vec();
+        deep.resize(start + 2_000_000, 6);
+        match Force::from_bytes(&deep) {
+            Err(ForceError::InvalidBinary { message }) => assert_eq!(message, "巢狀過深"),
+            other => panic!("預期 InvalidBinary ，但得到 {:?}", other),
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,319 @@
+// 依 diff 的結果判斷新版力是否相容於舊版，供 CI 擋下會破壞既有資料的修改
+// 向後相容：以舊版寫入的資料在新版下依然有效
+// 向前相容：以新版寫入的資料在舊版下依然有效
+use crate::diff::{diff, SchemaChange};
+use crate::*;
+use serde::Serialize;
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum Direction {
+    Backward, // 既有資料在新版下失效
+    Forward,  // 新資料在舊版下失效
+    Both,
+}
+
+impl Direction {
+    fn new(backward: bool, forward: bool) -> Option<Direction> {
+        match (backward, forward) {
+            (true, true) => Some(Direction::Both),
+            (true, false) => Some(Direction::Backward),
+            (false, true) => Some(Direction::Forward),
+            (false, false) => None,
+        }
+    }
+    pub fn is_backward(&self) -> bool {
+        matches!(self, Direction::Backward | Direction::Both)
+    }
+    pub fn is_forward(&self) -> bool {
+        matches!(self, Direction::Forward | Direction::Both)
+    }
+}
+
+// 一項不相容的改變，以及它破壞了哪個方向的相容性
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Breakage {
+    pub change: SchemaChange,
+    pub direction: Direction,
+}
+
+impl fmt::Display for Breakage {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let direction = match self.direction {
+            Direction::Backward => "破壞向後相容",
+            Direction::Forward => "破壞向前相容",
+            Direction::Both => "破壞雙向相容",
+        };
+        match &self.change {
+            SchemaChange::CategoryAdded(category) => {
+                write!(f, "{}：新增分類 {}", direction, category)
+            }
+            SchemaChange::CategoryRemoved(category) => {
+                write!(f, "{}：移除分類 {}", direction, category)
+            }
+            SchemaChange::FieldAdded { category, field } => {
+                write!(f, "{}：{} 新增必填域 {}", direction, category, field)
+            }
+            SchemaChange::FieldRemoved { category, field } => {
+                write!(f, "{}：{} 移除域 {}", direction, category, field)
+            }
+            SchemaChange::FieldRetyped {
+                category,
+                field,
+                from,
+                to,
+            } => write!(
+                f,
+                "{}：{} 的域 {} 由 {:?} 改為 {:?}",
+                direction, category, field, from, to
+            ),
+            SchemaChange::BondTargetChanged {
+                category,
+                field,
+                from,
+                to,
+            } => write!(
+                f,
+                "{}：{} 的域 {} 鍵結對象由 {} 改為 {}",
+                direction, category, field, from, to
+            ),
+            SchemaChange::OptionalChanged {
+                category,
+                field,
+                optional,
+            } => write!(
+                f,
+                "{}：{} 的域 {} 改為{}",
+                direction,
+                category,
+                field,
+                if *optional { "可省略" } else { "必填" }
+            ),
+            SchemaChange::DefaultChanged {
+                category, field, ..
+            } => write!(f, "{}：{} 的域 {} 預設值改變", direction, category, field),
+            SchemaChange::UniqueChanged {
+                category,
+                field,
+                unique,
+            } => write!(
+                f,
+                "{}：{} 的域 {} 改為{}",
+                direction,
+                category,
+                field,
+                if *unique { "不可重複" } else { "可重複" }
+            ),
+            SchemaChange::ComputedChanged {
+                category, field, ..
+            } => write!(f, "{}：{} 的域 {} 的算式改變", direction, category, field),
+        }
+    }
+}
+
+fn bounds_contain<T: PartialOrd + Copy>(wide: &Bounds<T>, narrow: &Bounds<T>) -> bool {
+    let min = match (wide.min, narrow.min) {
+        (None, _) => true,
+        (Some(_), None) => false,
+        (Some(wide), Some(narrow)) => narrow >= wide,
+    };
+    let max = match (wide.max, narrow.max) {
+        (None, _) => true,
+        (Some(_), None) => false,
+        (Some(wide), Some(narrow)) => narrow <= wide,
+    };
+    min && max
+}
+
+fn bondee_contains(wide: &Bondee, narrow: &Bondee) -> bool {
+    match (wide, narrow) {
+        (Bondee::All, _) => true,
+        (Bondee::AllExcept(wide), Bondee::AllExcept(narrow)) => {
+            wide.iter().all(|excluded| narrow.contains(excluded))
+        }
+        (Bondee::AllExcept(wide), Bondee::Choices(narrow)) => {
+            !narrow.iter().any(|choice| wide.contains(choice))
+        }
+        (Bondee::AllExcept(_) | Bondee::Choices(_), Bondee::All)
+        | (Bondee::Choices(_), Bondee::AllExcept(_)) => false,
+        (Bondee::Choices(wide), Bondee::Choices(narrow)) => {
+            narrow.iter().all(|choice| wide.contains(choice))
+        }
+    }
+}
+
+// wide 是否接受所有 narrow 接受的值
+fn accepts(wide: &DataType, narrow: &DataType) -> bool {
+    match (wide, narrow) {
+        // 反向連結只是名稱，不影響資料
+        (DataType::Bond(wide, wide_relation), DataType::Bond(narrow, narrow_relation)) => {
+            bondee_contains(wide, narrow)
+                && (wide_relation.cardinality == Cardinality::Many
+                    || narrow_relation.cardinality == Cardinality::One)
+        }
+        (DataType::TaggedBond(wide, wide_tags), DataType::TaggedBond(narrow, narrow_tags)) => {
+            bondee_contains(wide, narrow) && narrow_tags.iter().all(|tag| wide_tags.contains(tag))
+        }
+        (DataType::OneLine(wide), DataType::OneLine(narrow)) => bounds_contain(wide, narrow),
+        (DataType::OneLine(bounds), DataType::Enum(variants)) => variants
+            .iter()
+            .all(|v| !v.contains('\n') && bounds.contains(v.chars().count())),
+        (DataType::Text(None), DataType::Text(_))
+        | (DataType::Text(None), DataType::OneLine(_))
+        | (DataType::Text(None), DataType::Enum(_))
+        | (DataType::Text(None), DataType::DateTime)
+        | (DataType::Bool, DataType::Bool)
+        | (DataType::DateTime, DataType::DateTime) => true,
+        (DataType::Text(Some(_)), DataType::Text(_)) => wide == narrow,
+        (DataType::Number(wide), DataType::Number(narrow)) => bounds_contain(wide, narrow),
+        (DataType::Enum(wide), DataType::Enum(narrow)) => {
+            narrow.iter().all(|variant| wide.contains(variant))
+        }
+        (
+            DataType::Array {
+                inner: wide_inner,
+                length: wide_length,
+            },
+            DataType::Array {
+                inner: narrow_inner,
+                length: narrow_length,
+            },
+        ) => bounds_contain(wide_length, narrow_length) && accepts(wide_inner, narrow_inner),
+        _ => false,
+    }
+}
+
+// 實例可以不帶此域，計算域的值不受檢查
+fn may_be_absent(field: &Field) -> bool {
+    field.optional || field.default.is_some() || field.computed.is_some()
+}
+
+fn find_field<'a>(force: &'a Force, category: &str, field: &str) -> &'a Field {
+    force.categories[category].field(field).unwrap()
+}
+
+fn classify(old: &Force, new: &Force, change: &SchemaChange) -> Option<Direction> {
+    match change {
+        // 舊版不認得新分類的實例
+        SchemaChange::CategoryAdded(_) => Direction::new(false, true),
+        SchemaChange::CategoryRemoved(_) => Direction::new(true, false),
+        // 舊版會忽略不認得的域，新增的域只要舊資料可以不帶即相容
+        SchemaChange::FieldAdded { category, field } => {
+            Direction::new(!may_be_absent(find_field(new, category, field)), false)
+        }
+        // 既有資料中此域的值將遺失；若舊版要求此域，新資料也將不合舊版
+        SchemaChange::FieldRemoved { category, field } => {
+            Direction::new(true, !may_be_absent(find_field(old, category, field)))
+        }
+        SchemaChange::FieldRetyped { from, to, .. } => {
+            Direction::new(!accepts(to, from), !accepts(from, to))
+        }
+        SchemaChange::BondTargetChanged { from, to, .. } => {
+            Direction::new(!bondee_contains(to, from), !bondee_contains(from, to))
+        }
+        SchemaChange::OptionalChanged {
+            category,
+            field,
+            optional,
+        } => {
+            if *optional {
+                Direction::new(false, !may_be_absent(find_field(old, category, field)))
+            } else {
+                Direction::new(!may_be_absent(find_field(new, category, field)), false)
+            }
+        }
+        // 預設值只在填入預設值時使用，不影響既有資料的有效性
+        SchemaChange::DefaultChanged { .. } => None,
+        // 既有資料可能已有重複的值
+        SchemaChange::UniqueChanged { unique, .. } => Direction::new(*unique, !*unique),
+        // 只改算式時值會重新算出；在一般域與計算域之間轉換則如同改變是否可省略
+        SchemaChange::ComputedChanged {
+            category,
+            field,
+            from,
+            to,
+        } => match (from, to) {
+            (None, Some(_)) => {
+                Direction::new(false, !may_be_absent(find_field(old, category, field)))
+            }
+            (Some(_), None) => {
+                Direction::new(!may_be_absent(find_field(new, category, field)), false)
+            }
+            _ => None,
+        },
+    }
+}
+
+// 回傳由 old 變成 new 的所有不相容改變，順序同 diff
+pub fn check(old: &Force, new: &Force) -> Vec<Breakage> {
+    diff(old, new)
+        .into_iter()
+        .filter_map(|change| {
+            let direction = classify(old, new, &change)?;
+            Some(Breakage { change, direction })
+        })
+        .collect()
+}
+
+// 既有資料在新版下是否依然有效
+pub fn is_backward_compatible(old: &Force, new: &Force) -> bool {
+    check(old, new)
+        .iter()
+        .all(|breakage| !breakage.direction.is_backward())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    fn directions(old: &str, new: &str) -> ForceResult<Vec<Direction>> {
+        let breakages = check(&parse(old)?, &parse(new)?);
+        Ok(breakages.iter().map(|b| b.direction).collect())
+    }
+    #[test]
+    fn test_check() -> ForceResult<()> {
+        use Direction::*;
+        let old = "新聞 {單行(..10) 標題 數字 讚數 鍵結[問卦,留言] 來源 單行 網址} 問卦 {} 留言 {}";
+        // 放寬限制、新增可省略的域
+        let new = "新聞 {文本 標題 數字 讚數 鍵結[*] 來源 單行? 網址 單行? 媒體 數字 熱度 = 0} \
+                   問卦 {} 留言 {}";
+        assert_eq!(directions(old, new)?, vec![Forward, Forward, Forward]);
+        assert!(is_backward_compatible(&parse(old)?, &parse(new)?));
+
+        // 收緊限制、移除域與鍵結對象、新增必填域
+        let new = "新聞 {單行(..5) 標題 數字(0..) 讚數 鍵結[問卦] 來源 單行 媒體} 問卦 {}";
+        assert_eq!(
+            directions(old, new)?,
+            vec![Both, Backward, Backward, Backward, Backward, Backward]
+        );
+        assert!(!is_backward_compatible(&parse(old)?, &parse(new)?));
+        let breakages = check(&parse(old)?, &parse(new)?);
+        assert_eq!(breakages[5].to_string(), "破壞向後相容：移除分類 留言");
+
+        let old = "文章 {數字 讚數 數字 噓數 數字 熱度}";
+        let computed = "文章 {數字 讚數 數字 噓數 計算 數字 熱度 = 讚數 - 噓數}";
+        let doubled = "文章 {數字 讚數 數字 噓數 計算 數字 熱度 = (讚數 - 噓數) * 2}";
+        assert_eq!(directions(old, computed)?, vec![Forward]);
+        assert_eq!(directions(computed, old)?, vec![Backward]);
+        assert_eq!(directions(computed, doubled)?, vec![]);
+        assert_eq!(diff(&parse(computed)?, &parse(doubled)?).len(), 1);
+        Ok(())
+    }
+    #[test]
+    fn test_accepts() -> ForceResult<()> {
+        let force = parse("甲 {列舉 {紅, 綠} 甲 列表<數字(0..9)>(1..) 乙 文本 /a/ 丙}")?;
+        let fields = &force.categories["甲"].fields;
+        let wider = parse("甲 {單行 甲 列表<數字> 乙 文本 丙}")?;
+        for (narrow, wide) in fields.iter().zip(&wider.categories["甲"].fields) {
+            assert!(accepts(&wide.datatype, &narrow.datatype));
+            assert!(!accepts(&narrow.datatype, &wide.datatype));
+        }
+        let force =
+            parse("甲 {鍵結[*] 甲 鍵結[* except 乙] 乙 鍵結[* except 乙, 丙] 丙 鍵結[丁] 丁}")?;
+        let fields = &force.categories["甲"].fields;
+        for pair in fields.windows(2) {
+            assert!(accepts(&pair[0].datatype, &pair[1].datatype));
+            assert!(!accepts(&pair[1].datatype, &pair[0].datatype));
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,176 @@
+// 預先算好查詢索引的力，建立後不再變動，可包在 Arc 中供多個執行緒共用而不須加鎖
+// 正則表達式在解析時即已編譯，此處另外預先展開每個域可鍵結的分類
+use crate::validate::{BondCheck, InstanceValidator};
+use crate::*;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+
+struct CompiledCategory {
+    category: Category,
+    targets: Vec<HashSet<String>>, // 與 fields 一一對應，非鍵結的域為空集合
+}
+
+pub struct CompiledForce {
+    patterns: Patterns,
+    indices: HashMap<String, usize>,   // 分類名在 categories 中的位置
+    categories: Vec<CompiledCategory>, // 依名稱排序
+}
+
+impl CompiledForce {
+    pub fn new(force: Force) -> CompiledForce {
+        let mut names: Vec<String> = force.categories.keys().cloned().collect();
+        names.sort();
+        let indices = names
+            .iter()
+            .enumerate()
+            .map(|(index, name)| (name.clone(), index))
+            .collect();
+        let categories = names
+            .iter()
+            .map(|name| {
+                let category = force.categories[name].clone();
+                let targets = category
+                    .fields
+                    .iter()
+                    .map(|field| match field.datatype.bondee() {
+                        Some(bondee) => force
+                            .bond_targets(bondee)
+                            .into_iter()
+                            .filter(|target| force.categories.contains_key(target))
+                            .collect(),
+                        None => HashSet::new(),
+                    })
+                    .collect();
+                CompiledCategory { category, targets }
+            })
+            .collect();
+        CompiledForce {
+            patterns: force.patterns,
+            indices,
+            categories,
+        }
+    }
+    pub fn patterns(&self) -> &Patterns {
+        &self.patterns
+    }
+    pub fn category_index(&self, name: &str) -> Option<usize> {
+        self.indices.get(name).copied()
+    }
+    // 依名稱排序，index 即 category_index 的回傳值
+    pub fn categories(&self) -> impl Iterator<Item = &Category> {
+        self.categories.iter().map(|compiled| &compiled.category)
+    }
+    pub fn category(&self, name: &str) -> Option<&Category> {
+        let index = self.category_index(name)?;
+        Some(&self.categories[index].category)
+    }
+    // category 的 field 能否鍵結到 target ，分類或域不存在時為 false
+    pub fn can_bond(&self, category: &str, field: &str, target: &str) -> bool {
+        let compiled = match self.category_index(category) {
+            Some(index) => &self.categories[index],
+            None => return false,
+        };
+        compiled
+            .category
+            .fields
+            .iter()
+            .position(|f| f.name == field)
+            .is_some_and(|index| compiled.targets[index].contains(target))
+    }
+    // 與 validate::validate_instance 的結果相同
+    pub fn validate_instance(
+        &self,
+        category: &str,
+        data: &Value,
+    ) -> Result<(), Vec<InstanceError>> {
+        let compiled = match self.category_index(category) {
+            Some(index) => &self.categories[index],
+            None => return Err(vec![InstanceError::UnknownCategory(category.to_owned())]),
+        };
+        if !data.is_object() {
+            return Err(vec![InstanceError::NotObject]);
+        }
+        let mut errors = Vec::new();
+        for (field, targets) in compiled.category.fields.iter().zip(&compiled.targets) {
+            let mut validator = InstanceValidator {
+                bonds: BondCheck::Targets(targets),
+                errors,
+            };
+            validator.validate_field(field, data);
+            errors = validator.errors;
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+    pub fn to_force(&self) -> Force {
+        Force {
+            categories: self
+                .categories()
+                .map(|category| (category.name.clone(), category.clone()))
+                .collect(),
+            patterns: self.patterns.clone(),
+        }
+    }
+}
+
+impl From<Force> for CompiledForce {
+    fn from(force: Force) -> CompiledForce {
+        CompiledForce::new(force)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::sync::Arc;
+    use std::thread;
+
+    fn assert_send_sync<T: Send + Sync>() {}
+
+    #[test]
+    fn test_compiled_force() -> ForceResult<()> {
+        assert_send_sync::<CompiledForce>();
+        let source = "管理公告 {} 問卦 {文本/.{3,}/ 內文} \
+                      回覆 {鍵結[* except 管理公告] 原文 列表<鍵結[問卦, 留言]> 引用}";
+        let force = parse(source)?;
+        let compiled = Arc::new(CompiledForce::new(parse(source)?));
+        assert_eq!(compiled.category_index("回覆"), Some(1));
+        let names: Vec<&str> = compiled.categories().map(|c| c.name()).collect();
+        assert_eq!(names, vec!["問卦", "回覆", "管理公告"]);
+        assert!(compiled.can_bond("回覆", "原文", "回覆"));
+        assert!(!compiled.can_bond("回覆", "原文", "管理公告"));
+        // 未定義的分類不可鍵結
+        assert!(!compiled.can_bond("回覆", "引用", "留言"));
+        assert_eq!(compiled.to_force().categories, force.categories);
+
+        let instances = [
+            (
+                "回覆",
+                json!({"原文": {"category": "問卦", "id": 1}, "引用": []}),
+            ),
+            ("回覆", json!({"原文": {"category": "管理公告", "id": 1}})),
+            ("問卦", json!({"內文": "短"})),
+            ("留言", json!({})),
+        ];
+        let handles: Vec<_> = instances
+            .iter()
+            .cloned()
+            .map(|(category, data)| {
+                let compiled = Arc::clone(&compiled);
+                thread::spawn(move || compiled.validate_instance(category, &data))
+            })
+            .collect();
+        let results: Vec<_> = handles.into_iter().map(|h| h.join().unwrap()).collect();
+        let expected: Vec<_> = instances
+            .iter()
+            .map(|(category, data)| validate_instance(&force, category, data))
+            .collect();
+        assert_eq!(results, expected);
+        assert_eq!(results.iter().filter(|result| result.is_ok()).count(), 1);
+        Ok(())
+    }
+}
@@ -0,0 +1,281 @@
+// 無損的具體語法樹，保留空白、註解與 token 順序，供編輯器與重構工具使用
+// 由解析器在解析時一併建出，將所有節點的文字依序串接即為原始碼
+use crate::lexer::Token;
+use crate::parser::Parser;
+use crate::prelude::*;
+use crate::*;
+use core::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeKind {
+    Root,
+    Import,
+    Pattern,
+    Category, // 含其前的文件註解
+    Field,    // 含其前的文件註解
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenKind {
+    Whitespace,
+    Comment, // 行註解與區塊註解，文件註解是一般 token
+    Token(Token<'static>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxToken {
+    pub kind: TokenKind,
+    pub text: String,
+}
+
+impl SyntaxToken {
+    pub fn is_trivia(&self) -> bool {
+        matches!(self.kind, TokenKind::Whitespace | TokenKind::Comment)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SyntaxNode {
+    pub kind: NodeKind,
+    pub children: Vec<SyntaxElement>,
+}
+
+impl SyntaxNode {
+    fn write_text(&self, out: &mut String) {
+        for child in &self.children {
+            match child {
+                SyntaxElement::Node(node) => node.write_text(out),
+                SyntaxElement::Token(token) => out.push_str(&token.text),
+            }
+        }
+    }
+    pub fn text(&self) -> String {
+        let mut out = String::new();
+        self.write_text(&mut out);
+        out
+    }
+    pub fn child_nodes(&self) -> impl Iterator<Item = &SyntaxNode> {
+        self.children.iter().filter_map(|child| match child {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None,
+        })
+    }
+    pub fn tokens(&self) -> impl Iterator<Item = &SyntaxToken> {
+        self.children.iter().filter_map(|child| match child {
+            SyntaxElement::Token(token) => Some(token),
+            SyntaxElement::Node(_) => None,
+        })
+    }
+    // 所有 token ，包含子節點中的，依原始碼順序
+    pub fn descendant_tokens(&self) -> Vec<&SyntaxToken> {
+        let mut ret = Vec::new();
+        for child in &self.children {
+            match child {
+                SyntaxElement::Node(node) => ret.extend(node.descendant_tokens()),
+                SyntaxElement::Token(token) => ret.push(token),
+            }
+        }
+        ret
+    }
+    // 分類或域的名稱，域名之後的反向連結名、計算域的算式與前面的屬性不算
+    pub fn name(&self) -> Option<&str> {
+        enum State {
+            Normal,
+            AttributeName, // @ 之後
+            Attribute,     // 屬性名之後，可能接著引數
+            Arguments,
+        }
+        let mut state = State::Normal;
+        let mut identifiers = self
+            .tokens()
+            .take_while(|token| {
+                token.kind != TokenKind::Token(Token::Inverse)
+                    && token.kind != TokenKind::Token(Token::Equal)
+            })
+            .filter_map(move |token| {
+                let token = match &token.kind {
+                    TokenKind::Token(token) => token,
+                    _ => return None,
+                };
+                match (&state, token) {
+                    (State::Arguments, Token::RightParenthesis) => state = State::Normal,
+                    (State::Arguments, _) => {}
+                    (_, Token::At) => state = State::AttributeName,
+                    (State::AttributeName, _) => state = State::Attribute,
+                    (State::Attribute, Token::LeftParenthesis) => state = State::Arguments,
+                    (_, Token::Identifier(name)) => {
+                        state = State::Normal;
+                        return Some(name.as_ref());
+                    }
+                    _ => state = State::Normal,
+                }
+                None
+            });
+        match self.kind {
+            NodeKind::Category | NodeKind::Pattern => identifiers.next(),
+            NodeKind::Field => identifiers.last(),
+            NodeKind::Root | NodeKind::Import => None,
+        }
+    }
+}
+
+impl fmt::Display for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.text())
+    }
+}
+
+// 將空白與註解切成 token
+fn split_trivia(mut text: &str, out: &mut Vec<SyntaxElement>) {
+    while !text.is_empty() {
+        let (kind, len) = if text.starts_with("//") {
+            (TokenKind::Comment, text.find('\n').unwrap_or(text.len()))
+        } else if text.starts_with("/*") {
+            let len = text.find("*/").map_or(text.len(), |i| i + 2);
+            (TokenKind::Comment, len)
+        } else {
+            let len = text
+                .find(|c: char| !c.is_whitespace())
+                .unwrap_or(text.len());
+            // 不應出現在 token 之間的內容，視為錯誤
+            if len == 0 {
+                let len = text.chars().next().unwrap().len_utf8();
+                (TokenKind::Token(Token::Error), len)
+            } else {
+                (TokenKind::Whitespace, len)
+            }
+        };
+        out.push(SyntaxElement::Token(SyntaxToken {
+            kind,
+            text: text[..len].to_owned(),
+        }));
+        text = &text[len..];
+    }
+}
+
+// 解析器的事件接收端，以堆疊記錄尚未結束的節點
+pub(crate) struct Builder {
+    stack: Vec<SyntaxNode>,
+    pos: usize, // 已輸出到原始碼的哪個位元組
+}
+
+impl Builder {
+    pub(crate) fn new() -> Builder {
+        Builder {
+            stack: vec![SyntaxNode {
+                kind: NodeKind::Root,
+                children: Vec::new(),
+            }],
+            pos: 0,
+        }
+    }
+    fn children(&mut self) -> &mut Vec<SyntaxElement> {
+        &mut self.stack.last_mut().unwrap().children
+    }
+    pub(crate) fn trivia(&mut self, source: &str, end: usize) {
+        if end > self.pos {
+            let pos = self.pos;
+            split_trivia(&source[pos..end], self.children());
+            self.pos = end;
+        }
+    }
+    pub(crate) fn token(&mut self, source: &str, token: Token, span: logos::Span) {
+        self.trivia(source, span.start);
+        let text = source[span.clone()].to_owned();
+        self.children().push(SyntaxElement::Token(SyntaxToken {
+            kind: TokenKind::Token(token.into_owned()),
+            text,
+        }));
+        self.pos = span.end;
+    }
+    // 目前節點的子元素數，之後可由此處開始包成新節點
+    pub(crate) fn checkpoint(&mut self, source: &str, end: usize) -> usize {
+        self.trivia(source, end);
+        self.stack.last().unwrap().children.len()
+    }
+    pub(crate) fn start_node_at(&mut self, checkpoint: usize, kind: NodeKind) {
+        let children = self.children().split_off(checkpoint);
+        self.stack.push(SyntaxNode { kind, children });
+    }
+    pub(crate) fn finish_node(&mut self) {
+        let node = self.stack.pop().unwrap();
+        self.children().push(SyntaxElement::Node(node));
+    }
+    pub(crate) fn depth(&self) -> usize {
+        self.stack.len()
+    }
+    // 出錯時結束所有比 depth 深的節點
+    pub(crate) fn finish_to(&mut self, depth: usize) {
+        while self.stack.len() > depth {
+            self.finish_node();
+        }
+    }
+    pub(crate) fn finish(mut self, source: &str) -> SyntaxNode {
+        self.finish_to(1);
+        self.trivia(source, source.len());
+        self.stack.pop().unwrap()
+    }
+}
+
+// 同時回傳具體語法樹，以及由同一次解析得到的力與所有錯誤
+pub fn parse_cst(source: &str) -> (SyntaxNode, Force, Vec<ForceError>) {
+    let mut parser = Parser::new(source);
+    parser.enable_cst();
+    let (force, errors) = parser.parse_all_errors();
+    (parser.take_cst().unwrap(), force, errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_lossless() {
+        let source = "// 開頭的註解\n模式 信箱 = /.+@.+/\n\n/// 新聞\n新聞 {  /* 記者 */ \
+                      @title 單行 記者\n    文本 信箱 聯絡 = \"a@b\" // 行尾\n}\n  錯誤 { 單行 }\n";
+        let (root, force, errors) = parse_cst(source);
+        assert_eq!(root.text(), source);
+        assert_eq!(errors.len(), 1);
+        assert!(force.categories.contains_key("新聞"));
+
+        let nodes: Vec<&SyntaxNode> = root.child_nodes().collect();
+        let kinds: Vec<NodeKind> = nodes.iter().map(|node| node.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![NodeKind::Pattern, NodeKind::Category, NodeKind::Category]
+        );
+        assert_eq!(nodes[0].name(), Some("信箱"));
+        assert!(nodes[1].text().starts_with("/// 新聞\n新聞 {"));
+        let fields: Vec<&SyntaxNode> = nodes[1].child_nodes().collect();
+        assert_eq!(fields[0].text(), "@title 單行 記者");
+        assert_eq!(fields[1].text(), "文本 信箱 聯絡 = \"a@b\"");
+        assert_eq!(fields[1].name(), Some("聯絡"));
+        assert!(nodes[1]
+            .tokens()
+            .any(|token| token.kind == TokenKind::Comment && token.text == "/* 記者 */"));
+        assert_eq!(nodes[2].text(), "錯誤 { 單行 }");
+
+        let (root, _, _) = parse_cst("@限定(管理員, 版主) @隱藏 公告 {@title 單行 標題}");
+        let node = root.child_nodes().next().unwrap();
+        assert_eq!(node.name(), Some("公告"));
+        assert_eq!(node.child_nodes().next().unwrap().name(), Some("標題"));
+    }
+    #[test]
+    fn test_edit() -> ForceResult<()> {
+        let source = "新聞 {\n    單行 記者 // 姓名\n}\n";
+        let (mut root, _, _) = parse_cst(source);
+        if let SyntaxElement::Node(category) = &mut root.children[0] {
+            if let SyntaxElement::Token(token) = &mut category.children[0] {
+                token.text = "報導".to_owned();
+            }
+        }
+        assert_eq!(root.text(), "報導 {\n    單行 記者 // 姓名\n}\n");
+        assert!(parse(&root.text())?.categories.contains_key("報導"));
+        Ok(())
+    }
+}
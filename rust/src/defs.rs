@@ -1,87 +1,1004 @@
 use crate::lexer;
+use crate::prelude::*;
+use core::fmt;
+use logos::Span;
+#[cfg(feature = "std")]
 use regex::Regex;
-use std::collections::HashMap;
-use std::fmt;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum Bondee {
     All,
+    AllExcept(Vec<String>), // [* except 甲, 乙] ，除了這些分類以外皆可
     Choices(Vec<String>),
 }
-// TODO: 處理輸能等等額外設定
-#[derive(Debug, Clone, PartialEq)]
+fn quote_all(names: &[String]) -> String {
+    let names: Vec<String> = names
+        .iter()
+        .map(|name| lexer::quote_identifier(name))
+        .collect();
+    names.join(",")
+}
+
+impl fmt::Display for Bondee {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Bondee::All => write!(f, "[*]"),
+            Bondee::AllExcept(excluded) => write!(f, "[* except {}]", quote_all(excluded)),
+            Bondee::Choices(choices) => write!(f, "[{}]", quote_all(choices)),
+        }
+    }
+}
+impl Bondee {
+    // 是否可鍵結到名為 category 的分類，不檢查該分類是否存在
+    pub fn accepts(&self, category: &str) -> bool {
+        match self {
+            Bondee::All => true,
+            Bondee::AllExcept(excluded) => !excluded.iter().any(|name| name == category),
+            Bondee::Choices(choices) => choices.iter().any(|name| name == category),
+        }
+    }
+    // 明確寫出的分類名，包含排除的分類
+    pub fn names(&self) -> &[String] {
+        match self {
+            Bondee::All => &[],
+            Bondee::AllExcept(names) | Bondee::Choices(names) => names,
+        }
+    }
+    pub(crate) fn names_mut(&mut self) -> &mut [String] {
+        match self {
+            Bondee::All => &mut [],
+            Bondee::AllExcept(names) | Bondee::Choices(names) => names,
+        }
+    }
+}
+
+// 同一個被鍵結的對象可被多少個實例鍵結
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Cardinality {
+    One, // 單一，即一對一
+    #[default]
+    Many, // 多個，即多對一
+}
+
+// 鍵結的關聯資訊，供產生資料庫綱要、GraphQL 型別等工具使用
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Relation {
+    #[serde(default)]
+    pub cardinality: Cardinality,
+    // 被鍵結的分類上反向連結的名稱
+    #[serde(default)]
+    pub inverse: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Tag {
     pub name: String,
+    #[serde(default)]
+    pub transfuse: Vec<i64>, // 輸能
+}
+
+// 數值或長度的上下限，皆為閉區間，可省略其一
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Bounds<T> {
+    pub min: Option<T>,
+    pub max: Option<T>,
+}
+
+impl<T: PartialOrd + Copy> Bounds<T> {
+    pub fn contains(&self, value: T) -> bool {
+        self.min.is_none_or(|min| value >= min) && self.max.is_none_or(|max| value <= max)
+    }
+    pub fn is_unbounded(&self) -> bool {
+        self.min.is_none() && self.max.is_none()
+    }
+}
+
+// 無上下限時不輸出任何東西
+impl<T: PartialOrd + Copy + fmt::Display> fmt::Display for Bounds<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_unbounded() {
+            return Ok(());
+        }
+        let show = |n: &Option<T>| n.as_ref().map_or(String::new(), |n| n.to_string());
+        write!(f, "({}..{})", show(&self.min), show(&self.max))
+    }
 }
 
+// regex 需要 std ，無 std 時改用 regex-automata ，並保留原始字串以供輸出
+#[cfg(not(feature = "std"))]
 #[derive(Debug, Clone)]
+pub struct Regex {
+    source: String,
+    regex: regex_automata::meta::Regex,
+}
+
+#[cfg(not(feature = "std"))]
+impl Regex {
+    // 錯誤訊息與 regex 相同，語法錯誤時最後一行為錯誤原因
+    pub fn new(pattern: &str) -> Result<Regex, String> {
+        let regex = regex_automata::meta::Regex::new(pattern).map_err(|error| {
+            let syntax = error.syntax_error();
+            syntax.map_or_else(|| error.to_string(), |syntax| syntax.to_string())
+        })?;
+        Ok(Regex {
+            source: pattern.to_owned(),
+            regex,
+        })
+    }
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+    pub fn is_match(&self, s: &str) -> bool {
+        self.regex.is_match(s)
+    }
+}
+
+// Regex 本身無法序列化，以其原始字串代之
+mod optional_regex {
+    use super::Regex;
+    use crate::prelude::*;
+    use serde::{de, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(regex: &Option<Regex>, s: S) -> Result<S::Ok, S::Error> {
+        match regex {
+            Some(regex) => s.serialize_some(regex.as_str()),
+            None => s.serialize_none(),
+        }
+    }
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Regex>, D::Error> {
+        match Option::<String>::deserialize(d)? {
+            Some(s) => Regex::new(&s).map(Some).map_err(de::Error::custom),
+            None => Ok(None),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum DataType {
-    Bond(Bondee),
+    Bond(Bondee, Relation),
     TaggedBond(Bondee, Vec<Tag>),
-    OneLine,
-    Text(Option<Regex>), // 正則表達式
-    Number,
+    OneLine(Bounds<usize>),                                // 以字元計的長度
+    Text(#[serde(with = "optional_regex")] Option<Regex>), // 正則表達式
+    Number(Bounds<i64>),
+    Enum(Vec<String>), // 只能是其中之一的字串
+    Bool,
+    DateTime, // RFC 3339 格式的字串
+    Array {
+        inner: Box<DataType>,
+        length: Bounds<usize>, // 元素個數
+    },
+}
+
+impl DataType {
+    // 若為鍵結（或鍵結的列表），回傳其鍵結對象
+    pub fn bondee(&self) -> Option<&Bondee> {
+        match self {
+            DataType::Bond(bondee, _) | DataType::TaggedBond(bondee, _) => Some(bondee),
+            DataType::Array { inner, .. } => inner.bondee(),
+            _ => None,
+        }
+    }
+    // 若為鍵結（或鍵結的列表），回傳其關聯資訊，帶籤鍵結沒有關聯資訊
+    pub fn relation(&self) -> Option<&Relation> {
+        match self {
+            DataType::Bond(_, relation) => Some(relation),
+            DataType::Array { inner, .. } => inner.relation(),
+            _ => None,
+        }
+    }
+    pub fn relation_mut(&mut self) -> Option<&mut Relation> {
+        match self {
+            DataType::Bond(_, relation) => Some(relation),
+            DataType::Array { inner, .. } => inner.relation_mut(),
+            _ => None,
+        }
+    }
 }
 
 impl PartialEq for DataType {
     fn eq(&self, other: &DataType) -> bool {
         match (self, other) {
-            (DataType::Bond(bondee), DataType::Bond(other_bondee)) => bondee == other_bondee,
+            (DataType::Bond(bondee, relation), DataType::Bond(other_bondee, other_relation)) => {
+                bondee == other_bondee && relation == other_relation
+            }
             (
                 DataType::TaggedBond(bondee, tags),
                 DataType::TaggedBond(other_bondee, other_tags),
             ) => bondee == other_bondee && tags == other_tags,
-            (DataType::OneLine, DataType::OneLine) => true,
+            (DataType::OneLine(bounds), DataType::OneLine(other_bounds)) => bounds == other_bounds,
+            (DataType::Text(None), DataType::Text(None)) => true,
             (DataType::Text(Some(regex)), DataType::Text(Some(other_regex))) => {
                 regex.as_str() == other_regex.as_str()
             }
-            (DataType::Number, DataType::Number) => true,
+            (DataType::Number(bounds), DataType::Number(other_bounds)) => bounds == other_bounds,
+            (DataType::Enum(variants), DataType::Enum(other_variants)) => {
+                variants == other_variants
+            }
+            (DataType::Bool, DataType::Bool) | (DataType::DateTime, DataType::DateTime) => true,
+            (
+                DataType::Array { inner, length },
+                DataType::Array {
+                    inner: other_inner,
+                    length: other_length,
+                },
+            ) => inner == other_inner && length == other_length,
             _ => false,
         }
     }
 }
 
-#[derive(Debug, PartialEq)]
+impl fmt::Display for DataType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            // 反向連結寫在域名之後，不屬於型別
+            DataType::Bond(bondee, relation) => match relation.cardinality {
+                Cardinality::One => write!(f, "鍵結{} 單一", bondee),
+                Cardinality::Many => write!(f, "鍵結{}", bondee),
+            },
+            DataType::TaggedBond(bondee, _) => write!(f, "帶籤鍵結{}", bondee),
+            DataType::OneLine(bounds) => write!(f, "單行{}", bounds),
+            DataType::Text(None) => write!(f, "文本"),
+            DataType::Text(Some(regex)) => write!(f, "文本/{}/", regex.as_str()),
+            DataType::Number(bounds) => write!(f, "數字{}", bounds),
+            DataType::Enum(variants) => write!(f, "列舉{{{}}}", quote_all(variants)),
+            DataType::Bool => write!(f, "布林"),
+            DataType::DateTime => write!(f, "日期時間"),
+            DataType::Array { inner, length } => write!(f, "列表<{}>{}", inner, length),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Field {
     pub datatype: DataType,
     pub name: String,
+    #[serde(default)]
+    pub optional: bool, // 型別後加 ? 者可省略
+    pub default: Option<serde_json::Value>, // 省略時代入的預設值
+    pub doc: Option<String>,                // 文件註解
+    // 以 @unique 標記者，同一分類的實例間不可重複
+    #[serde(default)]
+    pub unique: bool,
+    // 以 @key 標記的主鍵，每個分類至多一個，亦不可重複
+    #[serde(default)]
+    pub key: bool,
+    // @title 、 @unique 、 @key 以外的屬性，如 @唯讀 、 @索引
+    #[serde(default)]
+    pub attributes: Vec<Attribute>,
+    // 以 計算 宣告的域，其值由同一實例中的其他域算出，見 evaluate
+    #[serde(default)]
+    pub computed: Option<Expr>,
+}
+
+impl Field {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    pub fn datatype(&self) -> &DataType {
+        &self.datatype
+    }
+    pub fn is_optional(&self) -> bool {
+        self.optional
+    }
+    pub fn default(&self) -> Option<&serde_json::Value> {
+        self.default.as_ref()
+    }
+    pub fn doc(&self) -> Option<&str> {
+        self.doc.as_deref()
+    }
+    // 主鍵亦不可重複
+    pub fn is_unique(&self) -> bool {
+        self.unique || self.key
+    }
+    pub fn is_key(&self) -> bool {
+        self.key
+    }
+    pub fn attributes(&self) -> &[Attribute] {
+        &self.attributes
+    }
+    pub fn attribute(&self, name: &str) -> Option<&Attribute> {
+        self.attributes
+            .iter()
+            .find(|attribute| attribute.name == name)
+    }
+    // 以 @唯讀 標記者，實例建立後不應再修改
+    pub fn is_readonly(&self) -> bool {
+        self.attribute(READONLY).is_some()
+    }
+    // 以 @索引 標記者，建議儲存端為其建立索引
+    pub fn is_indexed(&self) -> bool {
+        self.attribute(INDEXED).is_some()
+    }
+    pub fn computed(&self) -> Option<&Expr> {
+        self.computed.as_ref()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
 }
 
-#[derive(Debug, PartialEq)]
+impl BinaryOp {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+        }
+    }
+    fn precedence(&self) -> u8 {
+        match self {
+            BinaryOp::Add | BinaryOp::Sub => 1,
+            BinaryOp::Mul | BinaryOp::Div => 2,
+        }
+    }
+}
+
+// 計算域的算式，只有四則運算與同一分類中的域，不能呼叫函式或存取其他實例
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Expr {
+    Number(i64),
+    Field(String),
+    Negate(Box<Expr>),
+    Binary {
+        op: BinaryOp,
+        left: Box<Expr>,
+        right: Box<Expr>,
+    },
+}
+
+impl Expr {
+    fn precedence(&self) -> u8 {
+        match self {
+            Expr::Binary { op, .. } => op.precedence(),
+            Expr::Negate(_) => 3,
+            Expr::Number(_) | Expr::Field(_) => 4,
+        }
+    }
+    // 算式中引用的域名，依出現順序，可能重複
+    pub fn references(&self) -> Vec<&str> {
+        match self {
+            Expr::Number(_) => Vec::new(),
+            Expr::Field(name) => vec![name.as_str()],
+            Expr::Negate(inner) => inner.references(),
+            Expr::Binary { left, right, .. } => {
+                let mut ret = left.references();
+                ret.extend(right.references());
+                ret
+            }
+        }
+    }
+}
+
+impl Expr {
+    // 以 leaf 輸出數字與域名，其餘照本語言的寫法，供匯出器轉為其他語言的算式
+    // 只在必要處加括號，運算皆為左結合，故右側同級者須加括號
+    pub fn render(&self, leaf: &dyn Fn(&Expr) -> String) -> String {
+        let child = |expr: &Expr, min: u8| {
+            if expr.precedence() < min {
+                format!("({})", expr.render(leaf))
+            } else {
+                expr.render(leaf)
+            }
+        };
+        match self {
+            Expr::Number(_) | Expr::Field(_) => leaf(self),
+            // -1 會被當成一個整數，故負號後的數字加括號
+            Expr::Negate(inner) => match inner.as_ref() {
+                Expr::Number(_) => format!("-({})", inner.render(leaf)),
+                inner => format!("-{}", child(inner, 3)),
+            },
+            Expr::Binary { op, left, right } => format!(
+                "{} {} {}",
+                child(left, op.precedence()),
+                op.symbol(),
+                child(right, op.precedence() + 1)
+            ),
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.render(&|leaf| match leaf {
+            Expr::Number(n) => n.to_string(),
+            Expr::Field(name) => lexer::quote_identifier(name),
+            _ => unreachable!(),
+        }))
+    }
+}
+
+pub const READONLY: &str = "唯讀";
+pub const INDEXED: &str = "索引";
+// 嚴格模式下，域前只接受這些屬性
+pub const FIELD_ATTRIBUTES: [&str; 5] = ["title", "unique", "key", READONLY, INDEXED];
+
+// 分類或域前的屬性，如 @隱藏 、 @限定(管理員, 版主) ，其意義由使用此函式庫的程式決定
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Attribute {
+    pub name: String,
+    #[serde(default)]
+    pub arguments: Vec<String>, // 括號內的識別子或字串
+}
+
+pub type CategoryAttribute = Attribute;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Category {
     pub source: String,
     pub name: String,
     pub fields: Vec<Field>,
+    pub title_field: Option<String>, // 以 @title 標記的標題域
+    pub doc: Option<String>,         // 文件註解
+    // 繼承的分類，其域已展開併入 fields
+    #[serde(default)]
+    pub parents: Vec<String>,
+    // 屬性不會被子分類繼承
+    #[serde(default)]
+    pub attributes: Vec<Attribute>,
+}
+
+impl Category {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+    // 包含繼承而來的域
+    pub fn fields(&self) -> &[Field] {
+        &self.fields
+    }
+    pub fn field(&self, name: &str) -> Option<&Field> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+    pub fn doc(&self) -> Option<&str> {
+        self.doc.as_deref()
+    }
+    pub fn parents(&self) -> &[String] {
+        &self.parents
+    }
+    pub fn attributes(&self) -> &[Attribute] {
+        &self.attributes
+    }
+    pub fn attribute(&self, name: &str) -> Option<&Attribute> {
+        self.attributes
+            .iter()
+            .find(|attribute| attribute.name == name)
+    }
+    pub fn title_field(&self) -> Option<&Field> {
+        let title = self.title_field.as_ref()?;
+        self.fields.iter().find(|field| &field.name == title)
+    }
 }
 
-pub type Categories = HashMap<String, Category>;
+pub type Categories = Map<String, Category>;
 
-#[derive(Debug)]
+// 模式名稱對應到正則表達式的原始字串
+pub type Patterns = Map<String, String>;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
 pub struct Force {
     pub categories: Categories,
+    #[serde(default)]
+    pub patterns: Patterns,
 }
 
-#[derive(Debug)]
+impl Force {
+    pub fn categories(&self) -> &Categories {
+        &self.categories
+    }
+    pub fn category(&self, name: &str) -> Option<&Category> {
+        self.categories.get(name)
+    }
+    // 依名稱排序
+    pub fn category_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.categories.keys().map(|name| name.as_str()).collect();
+        names.sort();
+        names
+    }
+    // 可被鍵結的分類，列出的對象維持原本順序，其餘依名稱排序
+    pub fn bond_targets(&self, bondee: &Bondee) -> Vec<String> {
+        match bondee {
+            Bondee::Choices(choices) => choices.clone(),
+            bondee => self
+                .category_names()
+                .into_iter()
+                .filter(|name| bondee.accepts(name))
+                .map(|name| name.to_owned())
+                .collect(),
+        }
+    }
+}
+
+// 日後可能新增變體，比對時須保留 _ 分支
+#[derive(Debug, Clone)]
+#[non_exhaustive]
 pub enum ForceError {
+    // 無法切分成 token 的片段
+    LexError {
+        text: String,
+        span: Span,
+    },
+    // 遇到的 token 不在預期的集合之中
+    Unexpected {
+        expected: Vec<lexer::Token<'static>>,
+        fact: lexer::Token<'static>,
+        span: Span,
+    },
+    // 還預期著 expected 時原始碼已結束， span 為原始碼結尾的空區間
+    UnexpectedEof {
+        expected: Vec<lexer::Token<'static>>,
+        span: Span,
+    },
+    // 舊版的語法錯誤，解析器已改為回報 Unexpected
     NonExpect {
-        expect: lexer::Token,
-        fact: lexer::Token,
+        expect: lexer::Token<'static>,
+        fact: lexer::Token<'static>,
+        span: Span,
     },
     NoMeet {
         expect: String,
-        fact: lexer::Token,
+        fact: lexer::Token<'static>,
+        span: Span,
     },
     InvalidRegex {
         regex: String,
+        message: String, // regex 回報的錯誤原因
+        span: Span,
+    },
+    UnknownAttribute {
+        attribute: String,
+        span: Span,
+    },
+    // @title 之後沒有接著域
+    TitleWithoutField {
+        category: String,
+        span: Span,
+    },
+    DuplicateTitle {
+        category: String,
+        span: Span,
+    },
+    // 繼承多個 @key 時沒有單一的位置
+    DuplicateKey {
+        category: String,
+        span: Option<Span>,
+    },
+    DuplicateVariant {
+        variant: String,
+        span: Span,
+    },
+    DuplicateTag {
+        tag: String,
+        span: Span,
+    },
+    DuplicateAttribute {
+        attribute: String,
+        span: Span,
+    },
+    // 預設值不符合域的型別
+    InvalidDefault {
+        field: String,
+        span: Span,
+    },
+    // 範圍下限大於上限，或長度為負
+    InvalidRange {
+        span: Span,
+    },
+    DuplicateCategory {
+        category: String,
+        span: Span,
+    },
+    DuplicateField {
+        category: String,
+        field: String,
+        span: Span,
+    },
+    DuplicatePattern {
+        pattern: String,
+        span: Span,
+    },
+    UnknownPattern {
+        pattern: String,
+        span: Span,
+    },
+    // 載入器無法讀取檔案，或未提供載入器
+    LoadFailed {
+        path: String,
+        message: String,
+        span: Span,
+    },
+    ImportCycle {
+        path: String,
+        span: Span,
+    },
+    // 被匯入的檔案中的錯誤，span 為匯入指令的位置
+    ImportError {
+        path: String,
+        error: Box<ForceError>,
+        span: Span,
+    },
+    UnknownParent {
+        category: String,
+        parent: String,
+    },
+    // 依繼承順序排列，首尾相同
+    InheritanceCycle {
+        categories: Vec<String>,
+    },
+    // Force::from_bytes 無法解讀的位元組
+    InvalidBinary {
+        message: String,
+    },
+    // Force::from_ast_json 無法解讀的 JSON
+    InvalidAst {
+        message: String,
+    },
+    // 反向連結與被鍵結分類中的域同名
+    InverseConflict {
+        category: String,
+        field: String,
+        inverse: String,
+        target: String,
+    },
+    // 超過 ParserOptions 所設的上限
+    TooLarge {
+        limit: usize,
+        span: Span,
+    },
+    TooDeep {
+        limit: usize,
+        span: Span,
+    },
+    // 不符合 ParserOptions::identifiers 的識別子
+    InvalidIdentifier {
+        name: String,
+        span: Span,
+    },
+    // 舊版合法而現在會被拆開的識別子，如 play-boy
+    LegacyIdentifier {
+        name: String,
+        span: Span,
+    },
+    // Force::merge 的兩個力中有同名而定義不同的分類或模式
+    MergeConflict {
+        name: String,
     },
+    // 計算域引用了不存在、非數字或同為計算域的域
+    InvalidReference {
+        category: String,
+        field: String,
+        reference: String,
+    },
+    // 鍵結到未定義的分類
+    UnknownBondee {
+        category: String,
+        field: String,
+        bondee: String,
+    },
+}
+
+impl ForceError {
+    // 錯誤在原始碼中的位置，語意錯誤沒有位置
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            ForceError::LexError { span, .. }
+            | ForceError::Unexpected { span, .. }
+            | ForceError::UnexpectedEof { span, .. }
+            | ForceError::NonExpect { span, .. }
+            | ForceError::NoMeet { span, .. }
+            | ForceError::InvalidRegex { span, .. }
+            | ForceError::UnknownAttribute { span, .. }
+            | ForceError::TitleWithoutField { span, .. }
+            | ForceError::DuplicateTitle { span, .. }
+            | ForceError::DuplicateVariant { span, .. }
+            | ForceError::DuplicateTag { span, .. }
+            | ForceError::DuplicateAttribute { span, .. }
+            | ForceError::InvalidDefault { span, .. }
+            | ForceError::DuplicateCategory { span, .. }
+            | ForceError::DuplicateField { span, .. }
+            | ForceError::DuplicatePattern { span, .. }
+            | ForceError::UnknownPattern { span, .. }
+            | ForceError::LoadFailed { span, .. }
+            | ForceError::ImportCycle { span, .. }
+            | ForceError::ImportError { span, .. }
+            | ForceError::TooLarge { span, .. }
+            | ForceError::TooDeep { span, .. }
+            | ForceError::InvalidIdentifier { span, .. }
+            | ForceError::LegacyIdentifier { span, .. }
+            | ForceError::InvalidRange { span } => Some(span.clone()),
+            ForceError::DuplicateKey { span, .. } => span.clone(),
+            ForceError::UnknownParent { .. }
+            | ForceError::InheritanceCycle { .. }
+            | ForceError::InvalidBinary { .. }
+            | ForceError::InvalidAst { .. }
+            | ForceError::InverseConflict { .. }
+            | ForceError::MergeConflict { .. }
+            | ForceError::InvalidReference { .. }
+            | ForceError::UnknownBondee { .. } => None,
+        }
+    }
+    // 只解析原始碼中的一段時，用以將位置換算回整份原始碼
+    #[cfg(feature = "std")]
+    pub(crate) fn span_mut(&mut self) -> Option<&mut Span> {
+        match self {
+            ForceError::LexError { span, .. }
+            | ForceError::Unexpected { span, .. }
+            | ForceError::UnexpectedEof { span, .. }
+            | ForceError::NonExpect { span, .. }
+            | ForceError::NoMeet { span, .. }
+            | ForceError::InvalidRegex { span, .. }
+            | ForceError::UnknownAttribute { span, .. }
+            | ForceError::TitleWithoutField { span, .. }
+            | ForceError::DuplicateTitle { span, .. }
+            | ForceError::DuplicateVariant { span, .. }
+            | ForceError::DuplicateTag { span, .. }
+            | ForceError::DuplicateAttribute { span, .. }
+            | ForceError::InvalidDefault { span, .. }
+            | ForceError::DuplicateCategory { span, .. }
+            | ForceError::DuplicateField { span, .. }
+            | ForceError::DuplicatePattern { span, .. }
+            | ForceError::UnknownPattern { span, .. }
+            | ForceError::LoadFailed { span, .. }
+            | ForceError::ImportCycle { span, .. }
+            | ForceError::ImportError { span, .. }
+            | ForceError::TooLarge { span, .. }
+            | ForceError::TooDeep { span, .. }
+            | ForceError::InvalidIdentifier { span, .. }
+            | ForceError::LegacyIdentifier { span, .. }
+            | ForceError::InvalidRange { span } => Some(span),
+            ForceError::DuplicateKey { span, .. } => span.as_mut(),
+            ForceError::UnknownParent { .. }
+            | ForceError::InheritanceCycle { .. }
+            | ForceError::InvalidBinary { .. }
+            | ForceError::InvalidAst { .. }
+            | ForceError::InverseConflict { .. }
+            | ForceError::MergeConflict { .. }
+            | ForceError::InvalidReference { .. }
+            | ForceError::UnknownBondee { .. } => None,
+        }
+    }
+    // 修正錯誤的建議，附在 render 的輸出之後
+    pub fn help(&self) -> Option<String> {
+        let help = match self {
+            ForceError::Unexpected { expected, fact, .. } => {
+                let wants_identifier = expected
+                    .iter()
+                    .any(|token| matches!(token, lexer::Token::Identifier(_)));
+                match fact.keyword() {
+                    Some(keyword) if wants_identifier => {
+                        format!("「{}」是關鍵字，不能作為名稱", keyword)
+                    }
+                    // 算式之外的 - 多半是名稱的一部分
+                    None if *fact == lexer::Token::Minus => {
+                        "名稱中有 - 時，可以 ` 括起，如 `play-boy`".to_owned()
+                    }
+                    _ => return None,
+                }
+            }
+            ForceError::UnexpectedEof { expected, .. } => {
+                let closing = [
+                    (lexer::Token::RightCurlyBrace, "}"),
+                    (lexer::Token::RightSquareBracket, "]"),
+                    (lexer::Token::RightParenthesis, ")"),
+                    (lexer::Token::GreaterThan, ">"),
+                ];
+                let (_, close) = closing.iter().find(|(token, _)| expected.contains(token))?;
+                format!("可能少了一個 {}", close)
+            }
+            ForceError::NoMeet {
+                fact: lexer::Token::Inverse,
+                ..
+            } => "反向只能接在鍵結或鍵結的列表之後".to_owned(),
+            ForceError::TitleWithoutField { .. } => "@title 之後須接著一個域".to_owned(),
+            ForceError::DuplicateKey { .. } => "每個分類至多只能有一個 @key".to_owned(),
+            ForceError::InvalidRange { .. } => {
+                "範圍寫作 (下限..上限) 或 (min=下限, max=上限)，下限不可大於上限，長度不可為負"
+                    .to_owned()
+            }
+            ForceError::InvalidRegex { message, .. } => format!("正則表達式語法錯誤：{}", message),
+            ForceError::UnknownPattern { pattern, .. } => {
+                format!("請以 模式 {} = /.../ 宣告，模式須宣告於使用之前", pattern)
+            }
+            ForceError::UnknownBondee { bondee, .. }
+            | ForceError::UnknownParent { parent: bondee, .. } => {
+                format!("請確認 {} 的拼寫，命名空間中的分類可寫完整名稱", bondee)
+            }
+            ForceError::InverseConflict { .. } => "請為反向連結另取一個名稱".to_owned(),
+            ForceError::InheritanceCycle { .. } => "須移除其中一個繼承關係".to_owned(),
+            ForceError::MergeConflict { .. } => {
+                "可改用 MergeStrategy::PreferLeft 或 MergeStrategy::RenameWithPrefix".to_owned()
+            }
+            ForceError::InvalidReference { .. } => {
+                "計算域只能引用同一分類中非計算的數字域".to_owned()
+            }
+            ForceError::TooLarge { .. } | ForceError::TooDeep { .. } => {
+                "上限可於 ParserOptions 調整".to_owned()
+            }
+            ForceError::InvalidIdentifier { .. } => {
+                "命名規則由 ParserOptions 的 identifiers 決定".to_owned()
+            }
+            ForceError::LegacyIdentifier { name, .. } => {
+                format!("以 ` 括起，寫成 {}", lexer::quote_identifier(name))
+            }
+            ForceError::LexError { text, .. } if text.starts_with('/') => {
+                "除號之後須留一個空白，以免被當成正則表達式的開頭".to_owned()
+            }
+            ForceError::LexError { .. } => {
+                "名稱中有空白或符號時，可以 ` 括起，如 `發布 日期`".to_owned()
+            }
+            ForceError::UnknownAttribute { .. } => format!(
+                "域前可用的屬性為 @{}，於 ParserOptions 開啟 unknown_attributes 可保留自訂屬性",
+                FIELD_ATTRIBUTES.join("、@")
+            ),
+            _ => return None,
+        };
+        Some(help)
+    }
+}
+
+// 如 「,」、「]」 或 識別子
+fn describe_expected(expected: &[lexer::Token]) -> String {
+    let expected: Vec<String> = expected.iter().map(lexer::Token::describe).collect();
+    match expected.split_last() {
+        Some((last, rest)) if !rest.is_empty() => format!("{} 或 {}", rest.join("、"), last),
+        _ => expected.concat(),
+    }
 }
 
 impl fmt::Display for ForceError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
-        write!(f, "力語言錯誤")
+        match self {
+            ForceError::LexError { text, .. } => write!(f, "無法辨識的字元 {}", text),
+            ForceError::Unexpected { expected, fact, .. } => {
+                let expected = describe_expected(expected);
+                write!(f, "預期 {} ，但得到 {}", expected, fact.describe())
+            }
+            ForceError::UnexpectedEof { expected, .. } => {
+                write!(f, "預期 {} ，但已到檔案結尾", describe_expected(expected))
+            }
+            ForceError::NonExpect { expect, fact, .. } => {
+                write!(f, "預期 {} ，但得到 {}", expect.describe(), fact.describe())
+            }
+            ForceError::NoMeet { expect, fact, .. } => {
+                write!(f, "預期 {} ，但得到 {}", expect, fact.describe())
+            }
+            ForceError::InvalidRegex { regex, .. } => write!(f, "不合法的正則表達式 /{}/", regex),
+            ForceError::UnknownAttribute { attribute, .. } => {
+                write!(f, "未知的屬性 @{}", attribute)
+            }
+            ForceError::TitleWithoutField { category, .. } => {
+                write!(f, "分類 {} 的 @title 之後沒有域", category)
+            }
+            ForceError::DuplicateTitle { category, .. } => {
+                write!(f, "分類 {} 有多個 @title", category)
+            }
+            ForceError::DuplicateKey { category, .. } => {
+                write!(f, "分類 {} 有多個 @key", category)
+            }
+            ForceError::DuplicateVariant { variant, .. } => {
+                write!(f, "列舉中重複的值 {}", variant)
+            }
+            ForceError::DuplicateTag { tag, .. } => write!(f, "重複的標籤 {}", tag),
+            ForceError::TooLarge { limit, .. } => write!(f, "原始碼超過 {} 位元組", limit),
+            ForceError::TooDeep { limit, .. } => write!(f, "巢狀超過 {} 層", limit),
+            ForceError::MergeConflict { name } => write!(f, "合併的兩個力中 {} 的定義不同", name),
+            ForceError::InvalidIdentifier { name, .. } => {
+                write!(f, "識別子 {} 不符合命名規則", name)
+            }
+            ForceError::LegacyIdentifier { name, .. } => {
+                write!(f, "{} 在舊版是一個識別子，現在會被拆開", name)
+            }
+            ForceError::DuplicateAttribute { attribute, .. } => {
+                write!(f, "重複的屬性 @{}", attribute)
+            }
+            ForceError::InvalidDefault { field, .. } => {
+                write!(f, "域 {} 的預設值不符合其型別", field)
+            }
+            ForceError::InvalidRange { .. } => write!(f, "不合法的範圍"),
+            ForceError::DuplicateCategory { category, .. } => {
+                write!(f, "重複的分類 {}", category)
+            }
+            ForceError::DuplicateField {
+                category, field, ..
+            } => {
+                write!(f, "分類 {} 中重複的域 {}", category, field)
+            }
+            ForceError::DuplicatePattern { pattern, .. } => write!(f, "重複的模式 {}", pattern),
+            ForceError::UnknownPattern { pattern, .. } => write!(f, "未定義的模式 {}", pattern),
+            ForceError::LoadFailed { path, message, .. } => {
+                write!(f, "無法載入 {}：{}", path, message)
+            }
+            ForceError::ImportCycle { path, .. } => write!(f, "循環匯入 {}", path),
+            ForceError::ImportError { path, error, .. } => write!(f, "{}：{}", path, error),
+            ForceError::UnknownParent { category, parent } => {
+                write!(f, "分類 {} 繼承了未定義的分類 {}", category, parent)
+            }
+            ForceError::InheritanceCycle { categories } => {
+                write!(f, "循環繼承 {}", categories.join(" : "))
+            }
+            ForceError::InverseConflict {
+                category,
+                field,
+                inverse,
+                target,
+            } => write!(
+                f,
+                "分類 {} 的域 {} 的反向連結 {} 與分類 {} 中的域同名",
+                category, field, inverse, target
+            ),
+            ForceError::InvalidBinary { message } => write!(f, "無法載入二進位格式：{}", message),
+            ForceError::InvalidAst { message } => write!(f, "無法載入 JSON 格式：{}", message),
+            ForceError::InvalidReference {
+                category,
+                field,
+                reference,
+            } => write!(
+                f,
+                "分類 {} 的計算域 {} 引用了無法計算的域 {}",
+                category, field, reference
+            ),
+            ForceError::UnknownBondee {
+                category,
+                field,
+                bondee,
+            } => write!(
+                f,
+                "分類 {} 的域 {} 鍵結到未定義的分類 {}",
+                category, field, bondee
+            ),
+        }
     }
 }
 
-impl std::error::Error for ForceError {}
+impl core::error::Error for ForceError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            ForceError::ImportError { error, .. } => Some(error.as_ref()),
+            _ => None,
+        }
+    }
+}
 
 pub type ForceResult<T> = Result<T, ForceError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse;
+    #[test]
+    fn test_serde_round_trip() -> ForceResult<()> {
+        let force =
+            parse("新聞 {單行 記者 文本 備註 文本/.{1,256}/ 內文 鍵結[*] 來源 列表<數字>(1..) 分數} 回覆 {帶籤鍵結[新聞] {挺 {輸能: [1]} 回 {}} 原文} 解答 {鍵結[問卦,留言] 問題}")?;
+        let json = serde_json::to_string(&force).unwrap();
+        let restored: Force = serde_json::from_str(&json).unwrap();
+        assert_eq!(restored.categories, force.categories);
+        Ok(())
+    }
+    #[test]
+    fn test_deserialize_invalid_regex() {
+        let json = r#"{"name": "文章", "datatype": {"Text": "("}}"#;
+        assert!(serde_json::from_str::<Field>(json).is_err());
+    }
+    #[test]
+    fn test_getters() -> ForceResult<()> {
+        let force = parse("/// 新聞\n新聞 {@title 單行 記者 數字? 讚數} 回覆 : 新聞 {文本 內文}")?;
+        assert_eq!(force.category_names(), vec!["回覆", "新聞"]);
+        let news = force.category("新聞").unwrap();
+        assert_eq!(news.name(), "新聞");
+        assert_eq!(news.doc(), Some("新聞"));
+        assert_eq!(news.fields().len(), 2);
+        let likes = news.field("讚數").unwrap();
+        assert!(likes.is_optional());
+        assert_eq!(likes.datatype(), &DataType::Number(Bounds::default()));
+        assert_eq!(likes.default(), None);
+        assert_eq!(news.title_field().map(Field::name), Some("記者"));
+        assert_eq!(force.category("回覆").unwrap().parents(), ["新聞"]);
+        assert!(force.category("問卦").is_none());
+        Ok(())
+    }
+}
@@ -0,0 +1,135 @@
+// 仿照 rustc 的格式，將錯誤連同出錯的原始碼一併呈現
+use crate::lexer::Location;
+use crate::*;
+use std::fmt::Write;
+
+const TAB: &str = "    ";
+
+// 終端機中的顯示寬度：東亞全形字與表情符號佔兩格，組合字元不佔位置
+fn char_width(c: char) -> usize {
+    match c as u32 {
+        0x0300..=0x036F | 0x200B..=0x200F | 0xFE00..=0xFE0F => 0,
+        0x1100..=0x115F
+        | 0x2E80..=0x303E
+        | 0x3041..=0x33FF
+        | 0x3400..=0x4DBF
+        | 0x4E00..=0x9FFF
+        | 0xA000..=0xA4CF
+        | 0xAC00..=0xD7A3
+        | 0xF900..=0xFAFF
+        | 0xFE30..=0xFE4F
+        | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6
+        | 0x1F300..=0x1F64F
+        | 0x1F900..=0x1F9FF
+        | 0x20000..=0x2FFFD
+        | 0x30000..=0x3FFFD => 2,
+        _ if c == '\t' => TAB.len(),
+        _ => 1,
+    }
+}
+
+fn display_width(s: &str) -> usize {
+    s.chars().map(char_width).sum()
+}
+
+impl ForceError {
+    pub fn render(&self, source: &str) -> String {
+        let mut out = format!("錯誤：{}\n", self);
+        let span = match self.span() {
+            Some(span) => span,
+            None => {
+                if let Some(help) = self.help() {
+                    writeln!(out, "提示：{}", help).unwrap();
+                }
+                return out;
+            }
+        };
+        let location = Location::new(source, span.start);
+        let line_start = source[..span.start].rfind('\n').map_or(0, |i| i + 1);
+        let line = source[line_start..].lines().next().unwrap_or("");
+        // 跨行的錯誤只標示到第一行結尾
+        let underline_end = span.end.min(line_start + line.len()).max(span.start);
+        let offset = display_width(&source[line_start..span.start]);
+        let width = display_width(&source[span.start..underline_end]).max(1);
+
+        let number = location.line.to_string();
+        let padding = " ".repeat(number.len());
+        writeln!(out, "{}--> {}:{}", padding, location.line, location.column).unwrap();
+        writeln!(out, "{} |", padding).unwrap();
+        // 定位符號展開成空白，以免各終端機的寬度不同
+        writeln!(out, "{} | {}", number, line.replace('\t', TAB)).unwrap();
+        writeln!(
+            out,
+            "{} | {}{}",
+            padding,
+            " ".repeat(offset),
+            "^".repeat(width)
+        )
+        .unwrap();
+        if let Some(help) = self.help() {
+            writeln!(out, "{} = 提示：{}", padding, help).unwrap();
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_render() {
+        let source = "新聞 {\n    單行 記者\n    數字 }";
+        let error = parse(source).unwrap_err();
+        assert_eq!(
+            error.render(source),
+            "錯誤：預期 識別子 ，但得到 「}」\n \
+             --> 3:8\n  |\n3 |     數字 }\n  |          ^\n"
+        );
+    }
+    #[test]
+    fn test_render_wide_span() {
+        let source = "作文 {文本/(/ 內文}";
+        let error = parse(source).unwrap_err();
+        assert!(error
+            .render(source)
+            .ends_with("1 | 作文 {文本/(/ 內文}\n  |           ^^^\n  = 提示：正則表達式語法錯誤：unclosed group\n"));
+    }
+    #[test]
+    fn test_render_tab() {
+        let source = "新聞 {\n\t單行\t數字\n}";
+        let error = parse(source).unwrap_err();
+        assert!(error
+            .render(source)
+            .contains("2 |     單行    數字\n  |             ^^^^\n"));
+    }
+    #[test]
+    fn test_render_without_span() -> ForceResult<()> {
+        let source = "解答 {鍵結[問卦] 問題}";
+        let errors = parse(source)?.validate().unwrap_err();
+        assert_eq!(
+            errors[0].render(source),
+            "錯誤：分類 解答 的域 問題 鍵結到未定義的分類 問卦\n\
+             提示：請確認 問卦 的拼寫，命名空間中的分類可寫完整名稱\n"
+        );
+        Ok(())
+    }
+    #[test]
+    fn test_render_help() {
+        let source = "新聞 {\n    單行 數字\n}";
+        let error = parse(source).unwrap_err();
+        assert_eq!(
+            error.render(source),
+            "錯誤：預期 識別子 ，但得到 「數字」\n \
+             --> 2:8\n  |\n2 |     單行 數字\n  |          ^^^^\n  \
+             = 提示：「數字」是關鍵字，不能作為名稱\n"
+        );
+        let source = "新聞 {列舉 {甲 乙}}";
+        let error = parse(source).unwrap_err();
+        assert!(error
+            .to_string()
+            .starts_with("預期 「,」 或 「}」 ，但得到 識別子"));
+        let error = parse("新聞 {單行 記者").unwrap_err();
+        assert_eq!(error.help(), Some("可能少了一個 }".to_owned()));
+    }
+}
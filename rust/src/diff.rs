@@ -0,0 +1,250 @@
+// 比較兩版力的差異，供產生資料庫遷移之用
+use crate::*;
+use serde::Serialize;
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub enum SchemaChange {
+    CategoryAdded(String),
+    CategoryRemoved(String),
+    FieldAdded {
+        category: String,
+        field: String,
+    },
+    FieldRemoved {
+        category: String,
+        field: String,
+    },
+    FieldRetyped {
+        category: String,
+        field: String,
+        from: DataType,
+        to: DataType,
+    },
+    // 型別不變，僅鍵結對象改變
+    BondTargetChanged {
+        category: String,
+        field: String,
+        from: Bondee,
+        to: Bondee,
+    },
+    OptionalChanged {
+        category: String,
+        field: String,
+        optional: bool,
+    },
+    DefaultChanged {
+        category: String,
+        field: String,
+        from: Option<serde_json::Value>,
+        to: Option<serde_json::Value>,
+    },
+    // 是否須在實例間唯一，主鍵亦算唯一
+    UniqueChanged {
+        category: String,
+        field: String,
+        unique: bool,
+    },
+    // 計算域的算式改變，或在一般域與計算域之間轉換
+    ComputedChanged {
+        category: String,
+        field: String,
+        from: Option<Expr>,
+        to: Option<Expr>,
+    },
+}
+
+// 除了鍵結對象之外，兩型別是否相同
+fn same_except_bondee(from: &DataType, to: &DataType) -> bool {
+    match (from, to) {
+        (DataType::Bond(_, from_relation), DataType::Bond(_, to_relation)) => {
+            from_relation == to_relation
+        }
+        (DataType::TaggedBond(_, from_tags), DataType::TaggedBond(_, to_tags)) => {
+            from_tags == to_tags
+        }
+        (
+            DataType::Array {
+                inner: from_inner,
+                length: from_length,
+            },
+            DataType::Array {
+                inner: to_inner,
+                length: to_length,
+            },
+        ) => from_length == to_length && same_except_bondee(from_inner, to_inner),
+        _ => false,
+    }
+}
+
+fn diff_field(category: &str, from: &Field, to: &Field, changes: &mut Vec<SchemaChange>) {
+    if from.datatype != to.datatype {
+        let change = match (from.datatype.bondee(), to.datatype.bondee()) {
+            (Some(from_bondee), Some(to_bondee))
+                if same_except_bondee(&from.datatype, &to.datatype) =>
+            {
+                SchemaChange::BondTargetChanged {
+                    category: category.to_owned(),
+                    field: to.name.clone(),
+                    from: from_bondee.clone(),
+                    to: to_bondee.clone(),
+                }
+            }
+            _ => SchemaChange::FieldRetyped {
+                category: category.to_owned(),
+                field: to.name.clone(),
+                from: from.datatype.clone(),
+                to: to.datatype.clone(),
+            },
+        };
+        changes.push(change);
+    }
+    if from.optional != to.optional {
+        changes.push(SchemaChange::OptionalChanged {
+            category: category.to_owned(),
+            field: to.name.clone(),
+            optional: to.optional,
+        });
+    }
+    if from.default != to.default {
+        changes.push(SchemaChange::DefaultChanged {
+            category: category.to_owned(),
+            field: to.name.clone(),
+            from: from.default.clone(),
+            to: to.default.clone(),
+        });
+    }
+    if from.is_unique() != to.is_unique() {
+        changes.push(SchemaChange::UniqueChanged {
+            category: category.to_owned(),
+            field: to.name.clone(),
+            unique: to.is_unique(),
+        });
+    }
+    if from.computed != to.computed {
+        changes.push(SchemaChange::ComputedChanged {
+            category: category.to_owned(),
+            field: to.name.clone(),
+            from: from.computed.clone(),
+            to: to.computed.clone(),
+        });
+    }
+}
+
+fn diff_category(from: &Category, to: &Category, changes: &mut Vec<SchemaChange>) {
+    let find =
+        |category: &Category, name: &str| category.fields.iter().any(|field| field.name == name);
+    for field in &from.fields {
+        if !find(to, &field.name) {
+            changes.push(SchemaChange::FieldRemoved {
+                category: to.name.clone(),
+                field: field.name.clone(),
+            });
+        }
+    }
+    for field in &to.fields {
+        match from.fields.iter().find(|f| f.name == field.name) {
+            Some(from_field) => diff_field(&to.name, from_field, field, changes),
+            None => changes.push(SchemaChange::FieldAdded {
+                category: to.name.clone(),
+                field: field.name.clone(),
+            }),
+        }
+    }
+}
+
+// 回傳由 from 變成 to 的所有改變，分類依名稱排序
+pub fn diff(from: &Force, to: &Force) -> Vec<SchemaChange> {
+    let mut names: Vec<&String> = from.categories.keys().chain(to.categories.keys()).collect();
+    names.sort();
+    names.dedup();
+    let mut changes = Vec::new();
+    for name in names {
+        match (from.categories.get(name), to.categories.get(name)) {
+            (Some(from), Some(to)) => diff_category(from, to, &mut changes),
+            (Some(_), None) => changes.push(SchemaChange::CategoryRemoved(name.clone())),
+            (None, Some(_)) => changes.push(SchemaChange::CategoryAdded(name.clone())),
+            (None, None) => unreachable!(),
+        }
+    }
+    changes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_diff() -> ForceResult<()> {
+        let from = parse("問卦 {文本 內文} 新聞 {單行 記者 數字 讚數 鍵結[問卦] 來源 單行 網址}")?;
+        let to = parse(
+            "留言 {文本 內文} 新聞 {單行 記者 單行 讚數 列表<鍵結[留言]>? 來源 單行 媒體 = \"PTT\"}",
+        )?;
+        let changes = diff(&from, &to);
+        let s = |s: &str| s.to_owned();
+        assert_eq!(
+            changes,
+            vec![
+                SchemaChange::CategoryRemoved("問卦".to_owned()),
+                SchemaChange::FieldRemoved {
+                    category: s("新聞"),
+                    field: s("網址")
+                },
+                SchemaChange::FieldRetyped {
+                    category: s("新聞"),
+                    field: s("讚數"),
+                    from: DataType::Number(Bounds::default()),
+                    to: DataType::OneLine(Bounds::default())
+                },
+                SchemaChange::FieldRetyped {
+                    category: s("新聞"),
+                    field: s("來源"),
+                    from: DataType::Bond(
+                        Bondee::Choices(vec!["問卦".to_owned()]),
+                        Relation::default()
+                    ),
+                    to: DataType::Array {
+                        inner: Box::new(DataType::Bond(
+                            Bondee::Choices(vec!["留言".to_owned()]),
+                            Relation::default()
+                        )),
+                        length: Bounds::default()
+                    }
+                },
+                SchemaChange::OptionalChanged {
+                    category: s("新聞"),
+                    field: s("來源"),
+                    optional: true
+                },
+                SchemaChange::FieldAdded {
+                    category: s("新聞"),
+                    field: s("媒體")
+                },
+                SchemaChange::CategoryAdded("留言".to_owned()),
+            ]
+        );
+        Ok(())
+    }
+    #[test]
+    fn test_bond_target_changed() -> ForceResult<()> {
+        let from = parse("解答 {鍵結[問卦] 問題}")?;
+        let to = parse("解答 {鍵結[問卦,留言] 問題}")?;
+        assert_eq!(
+            diff(&from, &to),
+            vec![SchemaChange::BondTargetChanged {
+                category: "解答".to_owned(),
+                field: "問題".to_owned(),
+                from: Bondee::Choices(vec!["問卦".to_owned()]),
+                to: Bondee::Choices(vec!["問卦".to_owned(), "留言".to_owned()]),
+            }]
+        );
+        assert_eq!(diff(&to, &to), vec![]);
+        assert_eq!(
+            diff(&to, &parse("解答 {@key 鍵結[問卦,留言] 問題}")?),
+            vec![SchemaChange::UniqueChanged {
+                category: "解答".to_owned(),
+                field: "問題".to_owned(),
+                unique: true,
+            }]
+        );
+        Ok(())
+    }
+}
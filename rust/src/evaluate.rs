@@ -0,0 +1,130 @@
+// 計算域的求值，供使用此函式庫的程式在儲存或回傳實例前算出計算域的值
+// 算式只讀取同一實例中的數字域，以浮點數計算
+use crate::prelude::*;
+use crate::*;
+use core::fmt;
+use serde_json::Value;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum EvaluateError {
+    NotNumber(String), // 引用的域不存在或不是數字
+    DivisionByZero,
+    Overflow, // 結果超出浮點數的範圍
+}
+
+impl fmt::Display for EvaluateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EvaluateError::NotNumber(field) => write!(f, "域 {} 不是數字", field),
+            EvaluateError::DivisionByZero => write!(f, "除以零"),
+            EvaluateError::Overflow => write!(f, "結果超出範圍"),
+        }
+    }
+}
+
+impl core::error::Error for EvaluateError {}
+
+// data 為實例物件，算式中的域名對應其中的鍵
+pub fn evaluate(expr: &Expr, data: &Value) -> Result<f64, EvaluateError> {
+    let value = match expr {
+        Expr::Number(n) => *n as f64,
+        Expr::Field(name) => data
+            .get(name)
+            .and_then(Value::as_f64)
+            .ok_or_else(|| EvaluateError::NotNumber(name.clone()))?,
+        Expr::Negate(inner) => -evaluate(inner, data)?,
+        Expr::Binary { op, left, right } => {
+            let left = evaluate(left, data)?;
+            let right = evaluate(right, data)?;
+            match op {
+                BinaryOp::Add => left + right,
+                BinaryOp::Sub => left - right,
+                BinaryOp::Mul => left * right,
+                BinaryOp::Div if right == 0.0 => return Err(EvaluateError::DivisionByZero),
+                BinaryOp::Div => left / right,
+            }
+        }
+    };
+    if value.is_finite() {
+        Ok(value)
+    } else {
+        Err(EvaluateError::Overflow)
+    }
+}
+
+// 可精確表示的整數存為 JSON 整數，以便與一般的數字域一致
+fn number_value(value: f64) -> Value {
+    const EXACT: f64 = (1u64 << 53) as f64;
+    if (-EXACT..=EXACT).contains(&value) && value == value as i64 as f64 {
+        Value::from(value as i64)
+    } else {
+        Value::from(value)
+    }
+}
+
+// 將 data 中的計算域覆寫為算出的值，任一域算不出來時 data 不變
+pub fn fill_computed(force: &Force, category: &str, data: &mut Value) -> Result<(), InstanceError> {
+    let category = force
+        .categories
+        .get(category)
+        .ok_or_else(|| InstanceError::UnknownCategory(category.to_owned()))?;
+    if !data.is_object() {
+        return Err(InstanceError::NotObject);
+    }
+    let mut values = Vec::new();
+    for field in &category.fields {
+        if let Some(expr) = &field.computed {
+            let value = evaluate(expr, data).map_err(|error| InstanceError::ComputeFailed {
+                field: field.name.clone(),
+                error,
+            })?;
+            values.push((field.name.clone(), number_value(value)));
+        }
+    }
+    let object = data.as_object_mut().unwrap();
+    for (name, value) in values {
+        object.insert(name, value);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    #[test]
+    fn test_evaluate() -> ForceResult<()> {
+        let force = parse(
+            "文章 {數字 讚數 數字 噓數 計算 數字 熱度 = 讚數 - 噓數 計算 數字 比例 = 讚數 / (讚數 + 噓數)}",
+        )?;
+        let category = &force.categories["文章"];
+        let data = json!({"讚數": 3, "噓數": 1.5});
+        assert_eq!(
+            evaluate(category.fields[2].computed().unwrap(), &data),
+            Ok(1.5)
+        );
+        assert_eq!(
+            evaluate(
+                category.fields[3].computed().unwrap(),
+                &json!({"讚數": 0, "噓數": 0})
+            ),
+            Err(EvaluateError::DivisionByZero)
+        );
+
+        let mut data = json!({"讚數": 3, "噓數": 1, "熱度": "舊值"});
+        fill_computed(&force, "文章", &mut data).unwrap();
+        assert_eq!(data, json!({"讚數": 3, "噓數": 1, "熱度": 2, "比例": 0.75}));
+        assert!(validate_instance(&force, "文章", &data).is_ok());
+
+        let mut data = json!({"讚數": 3, "噓數": "一"});
+        assert_eq!(
+            fill_computed(&force, "文章", &mut data),
+            Err(InstanceError::ComputeFailed {
+                field: "熱度".to_owned(),
+                error: EvaluateError::NotNumber("噓數".to_owned()),
+            })
+        );
+        assert_eq!(data, json!({"讚數": 3, "噓數": "一"}));
+        Ok(())
+    }
+}
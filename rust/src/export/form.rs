@@ -0,0 +1,168 @@
+use crate::*;
+use serde_json::{json, Value};
+
+// 表單模型：每個域依宣告順序對應一個輸入元件，前端據此產生發文表單
+// 元件的 type 為 text、textarea、number、checkbox、datetime、select、bond、taggedBond 或 list
+impl Force {
+    fn candidates(&self, bondee: &Bondee) -> Vec<String> {
+        self.bond_targets(bondee)
+    }
+    fn widget(&self, datatype: &DataType) -> Value {
+        let mut widget = match datatype {
+            DataType::OneLine(bounds) => json!({
+                "type": "text",
+                "minLength": bounds.min,
+                "maxLength": bounds.max,
+            }),
+            DataType::Text(regex) => json!({
+                "type": "textarea",
+                "pattern": regex.as_ref().map(|regex| regex.as_str()),
+            }),
+            DataType::Number(bounds) => json!({
+                "type": "number",
+                "min": bounds.min,
+                "max": bounds.max,
+                "step": 1,
+            }),
+            DataType::Bool => json!({"type": "checkbox"}),
+            DataType::DateTime => json!({"type": "datetime"}),
+            DataType::Enum(variants) => json!({"type": "select", "options": variants}),
+            DataType::Bond(bondee, relation) => json!({
+                "type": "bond",
+                "candidates": self.candidates(bondee),
+                "exclusive": relation.cardinality == Cardinality::One,
+            }),
+            DataType::TaggedBond(bondee, tags) => {
+                let tags: Vec<&String> = tags.iter().map(|tag| &tag.name).collect();
+                json!({
+                    "type": "taggedBond",
+                    "candidates": self.candidates(bondee),
+                    "tags": tags,
+                })
+            }
+            DataType::Array { inner, length } => json!({
+                "type": "list",
+                "item": self.widget(inner),
+                "minItems": length.min,
+                "maxItems": length.max,
+            }),
+        };
+        // 省略沒有限制的鍵
+        if let Value::Object(map) = &mut widget {
+            map.retain(|_, value| !value.is_null());
+        }
+        widget
+    }
+    // 產生單一分類的表單模型，分類不存在時回傳 None
+    // 計算域的值不由使用者填寫，不產生元件
+    pub fn to_form_model(&self, category: &str) -> Option<Value> {
+        let category = self.categories.get(category)?;
+        let fields: Vec<Value> = category
+            .fields
+            .iter()
+            .filter(|field| field.computed.is_none())
+            .map(|field| {
+                let mut model = json!({
+                    "name": field.name,
+                    "label": field.name,
+                    "widget": self.widget(&field.datatype),
+                    "required": !field.optional && field.default.is_none(),
+                });
+                if let Some(doc) = &field.doc {
+                    model["help"] = json!(doc);
+                }
+                if let Some(default) = &field.default {
+                    model["default"] = default.clone();
+                }
+                if field.is_unique() {
+                    model["unique"] = json!(true);
+                }
+                if category.title_field.as_ref() == Some(&field.name) {
+                    model["title"] = json!(true);
+                }
+                model
+            })
+            .collect();
+        let mut model = json!({
+            "category": category.name,
+            "label": category.name,
+            "fields": fields,
+        });
+        if let Some(doc) = &category.doc {
+            model["help"] = json!(doc);
+        }
+        Some(model)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_to_form_model() -> ForceResult<()> {
+        let force = parse(
+            "問卦 {@title 單行(..64) 標題 /// 至少 256 字\n文本/.{256,}/ 內文} \
+             回覆 {鍵結[*] 單一 原文 數字(0..) 讚數 = 0 布林? 匿名 日期時間 時間 \
+             列舉 {挺, 噓} 立場 帶籤鍵結[問卦] {推 {}} 評價 列表<單行(1..)>(..3)? 標籤}",
+        )?;
+        assert_eq!(
+            force.to_form_model("問卦").unwrap(),
+            json!({
+                "category": "問卦",
+                "label": "問卦",
+                "fields": [
+                    {
+                        "name": "標題",
+                        "label": "標題",
+                        "widget": {"type": "text", "maxLength": 64},
+                        "required": true,
+                        "title": true,
+                    },
+                    {
+                        "name": "內文",
+                        "label": "內文",
+                        "widget": {"type": "textarea", "pattern": ".{256,}"},
+                        "required": true,
+                        "help": "至少 256 字",
+                    },
+                ],
+            })
+        );
+        let model = force.to_form_model("回覆").unwrap();
+        let widgets: Vec<&Value> = model["fields"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|field| &field["widget"])
+            .collect();
+        assert_eq!(
+            widgets,
+            vec![
+                &json!({"type": "bond", "candidates": ["問卦", "回覆"], "exclusive": true}),
+                &json!({"type": "number", "min": 0, "step": 1}),
+                &json!({"type": "checkbox"}),
+                &json!({"type": "datetime"}),
+                &json!({"type": "select", "options": ["挺", "噓"]}),
+                &json!({"type": "taggedBond", "candidates": ["問卦"], "tags": ["推"]}),
+                &json!({"type": "list", "item": {"type": "text", "minLength": 1}, "maxItems": 3}),
+            ]
+        );
+        assert_eq!(model["fields"][1]["default"], json!(0));
+        assert_eq!(model["fields"][1]["required"], json!(false));
+        assert!(force.to_form_model("留言").is_none());
+        Ok(())
+    }
+    #[test]
+    fn test_computed() -> ForceResult<()> {
+        let force = parse("文章 {數字 讚數 計算 數字 熱度 = 讚數 / 2}")?;
+        let model = force.to_form_model("文章").unwrap();
+        let names: Vec<&Value> = model["fields"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|field| &field["name"])
+            .collect();
+        assert_eq!(names, vec![&json!("讚數")]);
+        Ok(())
+    }
+}
@@ -0,0 +1,347 @@
+use crate::*;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+// GraphQL 的名稱只能是 ASCII ，其餘字元以 _u + 十六進位碼位代之
+// 改過名者以 @force(name: "原名") 記錄原名
+fn name(s: &str) -> String {
+    let mut ret = String::new();
+    for c in s.chars() {
+        if c.is_ascii_alphanumeric() || c == '_' {
+            ret.push(c);
+        } else {
+            write!(ret, "_u{:x}", c as u32).unwrap();
+        }
+    }
+    if ret.starts_with(|c: char| c.is_ascii_digit()) {
+        ret.insert(0, '_');
+    }
+    ret
+}
+
+// 同一命名空間中已用過的名稱，如 a- 與 a_u2d 會轉成相同的名稱，後者加上數字區分
+struct Names {
+    used: HashSet<String>,
+}
+
+impl Names {
+    fn new(reserved: &[&str]) -> Names {
+        Names {
+            used: reserved.iter().map(|name| name.to_string()).collect(),
+        }
+    }
+    fn claim(&mut self, s: &str) -> String {
+        let base = name(s);
+        let mut id = base.clone();
+        let mut n = 2;
+        while self.used.contains(&id) {
+            id = format!("{}_{}", base, n);
+            n += 1;
+        }
+        self.used.insert(id.clone());
+        id
+    }
+}
+
+// 內建與自訂的純量佔用型別名稱
+const BUILTIN_TYPES: &[&str] = &["String", "Int", "Float", "Boolean", "ID", LONG, "DateTime"];
+// 列舉值不能是這些名稱
+const RESERVED_VALUES: &[&str] = &["true", "false", "null"];
+
+fn description(out: &mut String, indent: &str, doc: &Option<String>) {
+    if let Some(doc) = doc {
+        writeln!(out, "{}\"\"\"", indent).unwrap();
+        for line in doc.lines() {
+            writeln!(out, "{}{}", indent, line.replace("\"\"\"", "\\\"\"\"")).unwrap();
+        }
+        writeln!(out, "{}\"\"\"", indent).unwrap();
+    }
+}
+
+// 64 位元的整數放不進 Int 時以此表示
+const LONG: &str = "Long";
+
+struct Generator<'a> {
+    force: &'a Force,
+    out: String,
+    renamed: bool,                                    // 是否用到 @force
+    scalars: Vec<&'static str>,                       // 用到的自訂純量
+    inverses: HashMap<String, Vec<(String, String)>>, // 分類 → (反向連結名, 型別)
+    types: HashMap<String, String>,                   // 分類名對應的型別名
+    used: Names,                                      // 所有型別共用的命名空間
+}
+
+impl<'a> Generator<'a> {
+    // 先為分類命名，列舉、聯集等輔助型別在產生時才命名
+    fn new(force: &'a Force) -> Generator<'a> {
+        let mut used = Names::new(BUILTIN_TYPES);
+        let types = force
+            .category_names()
+            .into_iter()
+            .map(|category| (category.to_owned(), used.claim(category)))
+            .collect();
+        Generator {
+            force,
+            out: String::new(),
+            renamed: false,
+            scalars: Vec::new(),
+            inverses: HashMap::new(),
+            types,
+            used,
+        }
+    }
+    fn type_name(&self, category: &str) -> String {
+        self.types
+            .get(category)
+            .cloned()
+            .unwrap_or_else(|| name(category))
+    }
+    // id 為 s 轉成的名稱，不同時以 @force 記錄原名
+    fn directive(&mut self, s: &str, id: &str) -> String {
+        if id == s {
+            String::new()
+        } else {
+            self.renamed = true;
+            format!(" @force(name: {:?})", s)
+        }
+    }
+    fn scalar(&mut self, scalar: &'static str) -> String {
+        if !self.scalars.contains(&scalar) {
+            self.scalars.push(scalar);
+        }
+        scalar.to_owned()
+    }
+    fn targets(&self, bondee: &Bondee) -> Vec<String> {
+        self.force.bond_targets(bondee)
+    }
+    // 鍵結對象只有一個分類時直接參照，否則產生聯集
+    fn bond_type(&mut self, bondee: &Bondee, base: &str) -> String {
+        let targets = self.targets(bondee);
+        if let [target] = targets.as_slice() {
+            return self.type_name(target);
+        }
+        let members: Vec<String> = targets
+            .iter()
+            .map(|target| self.type_name(target))
+            .collect();
+        let type_name = self.used.claim(base);
+        writeln!(self.out, "union {} = {}\n", type_name, members.join(" | ")).unwrap();
+        type_name
+    }
+    fn enum_values<'s>(&mut self, values: impl Iterator<Item = &'s String>) {
+        let mut used = Names::new(RESERVED_VALUES);
+        for value in values {
+            let id = used.claim(value);
+            let directive = self.directive(value, &id);
+            writeln!(self.out, "  {}{}", id, directive).unwrap();
+        }
+    }
+    // base 為輔助型別的名稱，即分類名加域名
+    fn graphql_type(&mut self, datatype: &DataType, base: &str) -> String {
+        match datatype {
+            DataType::OneLine(_) | DataType::Text(_) => "String".to_owned(),
+            DataType::Number(bounds) => {
+                let fits = |n: Option<i64>| {
+                    n.is_some_and(|n| (i32::MIN as i64..=i32::MAX as i64).contains(&n))
+                };
+                if fits(bounds.min) && fits(bounds.max) {
+                    "Int".to_owned()
+                } else {
+                    self.scalar(LONG)
+                }
+            }
+            DataType::Bool => "Boolean".to_owned(),
+            DataType::DateTime => self.scalar("DateTime"),
+            DataType::Enum(variants) => {
+                let type_name = self.used.claim(base);
+                writeln!(self.out, "enum {} {{", type_name).unwrap();
+                self.enum_values(variants.iter());
+                writeln!(self.out, "}}\n").unwrap();
+                type_name
+            }
+            DataType::Bond(bondee, _) => self.bond_type(bondee, base),
+            // 帶籤鍵結為一個帶有標籤與對象的物件
+            DataType::TaggedBond(bondee, tags) => {
+                let type_name = self.used.claim(base);
+                let tag_type = self.used.claim(&format!("{}Tag", type_name));
+                writeln!(self.out, "enum {} {{", tag_type).unwrap();
+                self.enum_values(tags.iter().map(|tag| &tag.name));
+                writeln!(self.out, "}}\n").unwrap();
+                let target = self.bond_type(bondee, &format!("{}Target", type_name));
+                writeln!(
+                    self.out,
+                    "type {} {{\n  tag: {}!\n  target: {}!\n}}\n",
+                    type_name, tag_type, target
+                )
+                .unwrap();
+                type_name
+            }
+            DataType::Array { inner, .. } => format!("[{}!]", self.graphql_type(inner, base)),
+        }
+    }
+    // 反向連結：多個時為列表，單一時為可為空的物件
+    fn collect_inverses(&mut self) {
+        for category_name in self.force.category_names() {
+            let category = &self.force.categories[category_name];
+            for field in &category.fields {
+                let (bondee, relation) = match (field.datatype.bondee(), field.datatype.relation())
+                {
+                    (Some(bondee), Some(relation)) => (bondee, relation),
+                    _ => continue,
+                };
+                let inverse = match &relation.inverse {
+                    Some(inverse) => inverse.clone(),
+                    None => continue,
+                };
+                let source = self.type_name(&category.name);
+                let graphql_type = match relation.cardinality {
+                    Cardinality::One => source,
+                    Cardinality::Many => format!("[{}!]!", source),
+                };
+                for target in self.targets(bondee) {
+                    self.inverses
+                        .entry(target)
+                        .or_default()
+                        .push((inverse.clone(), graphql_type.clone()));
+                }
+            }
+        }
+    }
+    // 域名、反向連結名與補上的 id 共用型別中的命名空間
+    fn gen_category(&mut self, category: &Category) {
+        let has_id = category.field("id").is_some();
+        let mut used = Names::new(if has_id { &[] } else { &["id"] });
+        let mut fields = String::new();
+        for field in &category.fields {
+            let base = format!("{}{}", category.name, field.name);
+            // 計算域的值為浮點數，尚未算出時為 null
+            let graphql_type = if field.computed.is_some() {
+                "Float".to_owned()
+            } else if field.optional {
+                self.graphql_type(&field.datatype, &base)
+            } else {
+                self.graphql_type(&field.datatype, &base) + "!"
+            };
+            description(&mut fields, "  ", &field.doc);
+            let id = used.claim(&field.name);
+            let directive = self.directive(&field.name, &id);
+            writeln!(fields, "  {}: {}{}", id, graphql_type, directive).unwrap();
+        }
+        if let Some(inverses) = self.inverses.get(&category.name).cloned() {
+            for (inverse, graphql_type) in inverses {
+                let id = used.claim(&inverse);
+                let directive = self.directive(&inverse, &id);
+                writeln!(fields, "  {}: {}{}", id, graphql_type, directive).unwrap();
+            }
+        }
+        description(&mut self.out, "", &category.doc);
+        let type_name = self.type_name(&category.name);
+        let directive = self.directive(&category.name, &type_name);
+        writeln!(self.out, "type {}{} {{", type_name, directive).unwrap();
+        if !has_id {
+            writeln!(self.out, "  id: ID!").unwrap();
+        }
+        self.out.push_str(&fields);
+        writeln!(self.out, "}}\n").unwrap();
+    }
+    fn gen(mut self) -> String {
+        self.collect_inverses();
+        for name in self.force.category_names() {
+            self.gen_category(&self.force.categories[name]);
+        }
+        let mut header = String::new();
+        if self.renamed {
+            writeln!(
+                header,
+                "directive @force(name: String!) on OBJECT | FIELD_DEFINITION | ENUM_VALUE\n"
+            )
+            .unwrap();
+        }
+        for scalar in &self.scalars {
+            writeln!(header, "scalar {}\n", scalar).unwrap();
+        }
+        header.push_str(&self.out);
+        header.truncate(header.trim_end().len());
+        header.push('\n');
+        header
+    }
+}
+
+// 產生 GraphQL SDL ，每個分類一個物件型別，另有一個 id 域
+// 鍵結以物件參照表示，鍵結對象不只一個時產生聯集，反向連結加在被鍵結的型別上
+pub fn to_graphql(force: &Force) -> String {
+    Generator::new(force).gen()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_name() {
+        assert_eq!(name("Post"), "Post");
+        assert_eq!(name("文章"), "_u6587_u7ae0");
+        assert_eq!(name("2020"), "_2020");
+    }
+    #[test]
+    fn test_to_graphql() -> ForceResult<()> {
+        let force = parse(
+            "Post {/// the title\n單行 title 數字(0..100) score 數字? views 布林 pinned \
+             日期時間 created 列舉 {draft, published} state}
+             Reply {鍵結[Post] 多個 post 反向 replies 鍵結[Post, Reply] 單一? quote 反向 quotedBy \
+             列表<帶籤鍵結[Post] {up {} down {}}> votes}",
+        )?;
+        let sdl = to_graphql(&force);
+        assert!(sdl.starts_with("scalar Long\n\nscalar DateTime\n\nenum Poststate {\n  draft\n"));
+        assert!(sdl.contains(
+            "type Post {\n  id: ID!\n  \"\"\"\n  the title\n  \"\"\"\n  title: String!\n  \
+             score: Int!\n  views: Long\n  pinned: Boolean!\n  created: DateTime!\n  \
+             state: Poststate!\n  replies: [Reply!]!\n  quotedBy: Reply\n}\n"
+        ));
+        assert!(sdl.contains("union Replyquote = Post | Reply\n"));
+        assert!(sdl.contains(
+            "enum ReplyvotesTag {\n  up\n  down\n}\n\ntype Replyvotes {\n  tag: ReplyvotesTag!\n  \
+             target: Post!\n}\n"
+        ));
+        assert!(sdl.contains(
+            "type Reply {\n  id: ID!\n  post: Post!\n  quote: Replyquote\n  \
+             votes: [Replyvotes!]!\n  quotedBy: Reply\n}\n"
+        ));
+        assert!(!sdl.contains("directive"));
+
+        let sdl = to_graphql(&parse("文章 {單行 標題}")?);
+        assert!(sdl.starts_with("directive @force(name: String!)"));
+        assert!(sdl.contains(
+            "type _u6587_u7ae0 @force(name: \"文章\") {\n  id: ID!\n  \
+             _u6a19_u984c: String! @force(name: \"標題\")\n}\n"
+        ));
+        Ok(())
+    }
+    #[test]
+    fn test_names() -> ForceResult<()> {
+        let sdl = to_graphql(&parse(
+            "`a-` {} a_u2d {} Long {} Poststate {} \
+             Post {單行 replies 列舉 {`a-`, a_u2d, true} state} \
+             Reply {鍵結[Post] 多個 post 反向 replies 鍵結[Post] 單一 origin 反向 id}",
+        )?);
+        assert!(sdl.contains("type a_u2d @force(name: \"a-\") {\n"));
+        assert!(sdl.contains("type a_u2d_2 @force(name: \"a_u2d\") {\n"));
+        assert!(sdl.contains("type Long_2 @force(name: \"Long\") {\n"));
+        assert!(sdl.contains("type Poststate {\n"));
+        assert!(sdl.contains(
+            "enum Poststate_2 {\n  a_u2d @force(name: \"a-\")\n  \
+             a_u2d_2 @force(name: \"a_u2d\")\n  true_2 @force(name: \"true\")\n}\n"
+        ));
+        assert!(sdl.contains(
+            "type Post {\n  id: ID!\n  replies: String!\n  state: Poststate_2!\n  \
+             replies_2: [Reply!]! @force(name: \"replies\")\n  \
+             id_2: Reply @force(name: \"id\")\n}\n"
+        ));
+        Ok(())
+    }
+    #[test]
+    fn test_computed() -> ForceResult<()> {
+        let sdl = to_graphql(&parse("Post {數字 likes 計算 數字 hot = likes / 2}")?);
+        assert!(sdl.contains("type Post {\n  id: ID!\n  likes: Long!\n  hot: Float\n}\n"));
+        Ok(())
+    }
+}
@@ -0,0 +1,157 @@
+use crate::*;
+use serde_json::{json, Map, Value};
+
+impl Force {
+    fn bond_schema(&self, bondee: &Bondee) -> Value {
+        let categories = self.bond_targets(bondee);
+        json!({
+            "type": "object",
+            "properties": {
+                "category": {"type": "string", "enum": categories},
+            },
+            "required": ["category"],
+        })
+    }
+    fn datatype_schema(&self, datatype: &DataType) -> Value {
+        match datatype {
+            DataType::Number(bounds) => {
+                let mut schema = json!({"type": "integer"});
+                if let Some(min) = bounds.min {
+                    schema["minimum"] = json!(min);
+                }
+                if let Some(max) = bounds.max {
+                    schema["maximum"] = json!(max);
+                }
+                schema
+            }
+            DataType::OneLine(bounds) => {
+                let mut schema = json!({"type": "string", "pattern": "^[^\\n]*$"});
+                if let Some(min) = bounds.min {
+                    schema["minLength"] = json!(min);
+                }
+                if let Some(max) = bounds.max {
+                    schema["maxLength"] = json!(max);
+                }
+                schema
+            }
+            DataType::Text(None) => json!({"type": "string"}),
+            DataType::Text(Some(regex)) => json!({"type": "string", "pattern": regex.as_str()}),
+            DataType::Enum(variants) => json!({"type": "string", "enum": variants}),
+            DataType::Bool => json!({"type": "boolean"}),
+            DataType::DateTime => json!({"type": "string", "format": "date-time"}),
+            DataType::Bond(bondee, _) => self.bond_schema(bondee),
+            DataType::TaggedBond(bondee, tags) => {
+                let mut schema = self.bond_schema(bondee);
+                let tags: Vec<&String> = tags.iter().map(|tag| &tag.name).collect();
+                schema["properties"]["tag"] = json!({"type": "string", "enum": tags});
+                schema["required"] = json!(["category", "tag"]);
+                schema
+            }
+            DataType::Array { inner, length } => {
+                let mut schema = json!({"type": "array", "items": self.datatype_schema(inner)});
+                if let Some(min) = length.min {
+                    schema["minItems"] = json!(min);
+                }
+                if let Some(max) = length.max {
+                    schema["maxItems"] = json!(max);
+                }
+                schema
+            }
+        }
+    }
+    // 產生單一分類的 JSON Schema (draft-07)，分類不存在時回傳 None
+    pub fn to_json_schema(&self, category: &str) -> Option<Value> {
+        let category = self.categories.get(category)?;
+        let mut properties = Map::new();
+        let mut required = Vec::new();
+        for field in &category.fields {
+            let mut schema = self.datatype_schema(&field.datatype);
+            if let Some(doc) = &field.doc {
+                schema["description"] = json!(doc);
+            }
+            // 計算域由儲存端填入，其值可能不是整數
+            if field.computed.is_some() {
+                schema["type"] = json!("number");
+                schema["readOnly"] = json!(true);
+            } else if let Some(default) = &field.default {
+                schema["default"] = default.clone();
+            } else if !field.optional {
+                required.push(field.name.clone());
+            }
+            properties.insert(field.name.clone(), schema);
+        }
+        let mut schema = json!({
+            "$schema": "http://json-schema.org/draft-07/schema#",
+            "title": category.name,
+            "type": "object",
+            "properties": properties,
+            "required": required,
+        });
+        if let Some(doc) = &category.doc {
+            schema["description"] = json!(doc);
+        }
+        Some(schema)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_to_json_schema() -> ForceResult<()> {
+        let force = parse(
+            "問卦 {文本/.{256,}/ 內文} 回覆 {鍵結[*] 原文 數字(0..) 讚數 = 0 列表<單行(1..8)>(..3)? 標籤}",
+        )?;
+        assert_eq!(
+            force.to_json_schema("回覆").unwrap(),
+            json!({
+                "$schema": "http://json-schema.org/draft-07/schema#",
+                "title": "回覆",
+                "type": "object",
+                "properties": {
+                    "原文": {
+                        "type": "object",
+                        "properties": {
+                            "category": {"type": "string", "enum": ["問卦", "回覆"]},
+                        },
+                        "required": ["category"],
+                    },
+                    "讚數": {"type": "integer", "minimum": 0, "default": 0},
+                    "標籤": {
+                        "type": "array",
+                        "items": {
+                            "type": "string",
+                            "pattern": "^[^\\n]*$",
+                            "minLength": 1,
+                            "maxLength": 8,
+                        },
+                        "maxItems": 3,
+                    },
+                },
+                "required": ["原文"],
+            })
+        );
+        assert_eq!(
+            force.to_json_schema("問卦").unwrap()["properties"]["內文"],
+            json!({"type": "string", "pattern": ".{256,}"})
+        );
+        assert_eq!(force.to_json_schema("留言"), None);
+
+        let force = parse("文章 {數字 讚數 計算 數字 熱度 = 讚數 / 2}")?;
+        let schema = force.to_json_schema("文章").unwrap();
+        assert_eq!(
+            schema["properties"]["熱度"],
+            json!({"type": "number", "readOnly": true})
+        );
+        assert_eq!(schema["required"], json!(["讚數"]));
+
+        let force = parse("/// 八卦問題\n問卦 {/// 問題內文\n文本 內文}")?;
+        let schema = force.to_json_schema("問卦").unwrap();
+        assert_eq!(schema["description"], json!("八卦問題"));
+        assert_eq!(
+            schema["properties"]["內文"]["description"],
+            json!("問題內文")
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,103 @@
+use crate::*;
+use std::fmt::Write;
+
+fn bondee_description(bondee: &Bondee) -> String {
+    match bondee {
+        Bondee::All => "任意分類".to_owned(),
+        Bondee::AllExcept(excluded) => format!("{}以外的任意分類", excluded.join("、")),
+        Bondee::Choices(choices) => choices.join("、"),
+    }
+}
+
+// 表格的儲存格中 | 會被當成分隔線，換行會結束該列
+fn escape_cell(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', "<br>")
+}
+
+fn category_to_markdown(category: &Category, out: &mut String) {
+    writeln!(out, "## {}", category.name).unwrap();
+    writeln!(out).unwrap();
+    if let Some(doc) = &category.doc {
+        writeln!(out, "{}", doc).unwrap();
+        writeln!(out).unwrap();
+    }
+    writeln!(out, "| 名稱 | 型別 | 必填 |").unwrap();
+    writeln!(out, "| --- | --- | --- |").unwrap();
+    for field in &category.fields {
+        // 計算域由其他域衍生，有預設值的域可省略，兩者皆無須填寫
+        let required = if field.computed.is_some() {
+            "衍生"
+        } else if field.optional || field.default.is_some() {
+            "否"
+        } else {
+            "是"
+        };
+        writeln!(
+            out,
+            "| {} | {} | {} |",
+            escape_cell(&field.name),
+            escape_cell(&field.datatype.to_string()),
+            required
+        )
+        .unwrap();
+    }
+
+    let mut relationships = Vec::new();
+    for field in &category.fields {
+        if let Some(bondee) = field.datatype.bondee() {
+            relationships.push(format!("- {} → {}", field.name, bondee_description(bondee)));
+        }
+    }
+    if !relationships.is_empty() {
+        writeln!(out).unwrap();
+        writeln!(out, "### 關係").unwrap();
+        writeln!(out).unwrap();
+        for relationship in relationships {
+            writeln!(out, "{}", relationship).unwrap();
+        }
+    }
+}
+
+// 產生以 Markdown 描述的實體關係文件，分類依名稱排序以保持輸出穩定
+pub fn to_markdown(force: &Force) -> String {
+    let mut names: Vec<&String> = force.categories.keys().collect();
+    names.sort();
+    let mut out = String::new();
+    for (i, name) in names.into_iter().enumerate() {
+        if i > 0 {
+            writeln!(out).unwrap();
+        }
+        category_to_markdown(&force.categories[name], &mut out);
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_to_markdown() -> ForceResult<()> {
+        let force = parse("留言 {鍵結[文章,留言] 本體 文本/.{1,256}/? 內文}")?;
+        let markdown = to_markdown(&force);
+        assert!(markdown.contains("## 留言\n"));
+        assert!(markdown.contains("| 本體 | 鍵結[文章,留言] | 是 |\n"));
+        assert!(markdown.contains("| 內文 | 文本/.{1,256}/ | 否 |\n"));
+        assert!(markdown.contains("- 本體 → 文章、留言\n"));
+
+        let force = parse("文章 {文本/a|b/ 內文 單行 `甲|乙`}")?;
+        let markdown = to_markdown(&force);
+        assert!(markdown.contains("| 內文 | 文本/a\\|b/ | 是 |\n"));
+        assert!(markdown.contains("| 甲\\|乙 | 單行 | 是 |\n"));
+
+        let force = parse("文章 {數字 讚數 計算 數字 熱度 = 讚數 * 2}")?;
+        let markdown = to_markdown(&force);
+        assert!(markdown.contains("| 讚數 | 數字 | 是 |\n"));
+        assert!(markdown.contains("| 熱度 | 數字 | 衍生 |\n"));
+
+        let force = parse("文章 {單行 標題 數字 讚數 = 0}")?;
+        let markdown = to_markdown(&force);
+        assert!(markdown.contains("| 標題 | 單行 | 是 |\n"));
+        assert!(markdown.contains("| 讚數 | 數字 | 否 |\n"));
+        Ok(())
+    }
+}
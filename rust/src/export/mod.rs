@@ -0,0 +1,8 @@
+// 將力語言定義匯出成其他格式
+pub mod form;
+pub mod graphql;
+pub mod json_schema;
+pub mod markdown;
+pub mod rust;
+pub mod sql;
+pub mod typescript;
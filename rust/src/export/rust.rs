@@ -0,0 +1,385 @@
+use crate::*;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+const PRELUDE: &str = "\
+use serde::{Deserialize, Serialize};
+use std::marker::PhantomData;
+
+// 指向分類 T 的鍵結，可指向多個分類時 T 為 ()
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(bound = \"\")]
+pub struct Bond<T> {
+    pub category: String,
+    pub id: String,
+    #[serde(skip)]
+    marker: PhantomData<T>,
+}
+
+impl<T> Bond<T> {
+    pub fn new(category: String, id: String) -> Bond<T> {
+        Bond {
+            category,
+            id,
+            marker: PhantomData,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(bound = \"\")]
+pub struct TaggedBond<T> {
+    pub category: String,
+    pub id: String,
+    pub tag: String,
+    #[serde(skip)]
+    marker: PhantomData<T>,
+}
+
+impl<T> TaggedBond<T> {
+    pub fn new(category: String, id: String, tag: String) -> TaggedBond<T> {
+        TaggedBond {
+            category,
+            id,
+            tag,
+            marker: PhantomData,
+        }
+    }
+}
+";
+
+// 含 2018 版保留的關鍵字，以 r# 前綴使用
+const KEYWORDS: &[&str] = &[
+    "abstract", "as", "async", "await", "become", "box", "break", "const", "continue", "do", "dyn",
+    "else", "enum", "extern", "false", "final", "fn", "for", "if", "impl", "in", "let", "loop",
+    "macro", "match", "mod", "move", "mut", "override", "priv", "pub", "ref", "return", "static",
+    "struct", "trait", "true", "try", "type", "typeof", "unsafe", "unsized", "use", "virtual",
+    "where", "while", "yield",
+];
+// 不能寫成 r# 形式者，改為加上底線
+const NON_RAW: &[&str] = &["_", "crate", "self", "Self", "super"];
+
+// 只保留合法的字元，尚未處理關鍵字
+fn sanitize(name: &str) -> String {
+    let mut ret: String = name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if ret.is_empty() || ret.starts_with(|c: char| c.is_ascii_digit()) {
+        ret.insert(0, '_');
+    }
+    ret
+}
+
+fn escape(mut id: String) -> String {
+    if NON_RAW.contains(&id.as_str()) {
+        id.push('_');
+    } else if KEYWORDS.contains(&id.as_str()) {
+        id.insert_str(0, "r#");
+    }
+    id
+}
+
+// 將力語言的識別子轉為合法的 Rust 識別子
+fn identifier(name: &str) -> String {
+    escape(sanitize(name))
+}
+
+// 同一命名空間中已用過的識別子，如 a-b 與 a_b 會轉成相同的識別子，後者加上數字區分
+#[derive(Default)]
+struct Names {
+    used: HashSet<String>,
+}
+
+impl Names {
+    fn claim(&mut self, name: &str) -> String {
+        let mut id = identifier(name);
+        let mut n = 2;
+        while self.used.contains(&id) {
+            id = escape(format!("{}_{}", sanitize(name), n));
+            n += 1;
+        }
+        self.used.insert(id.clone());
+        id
+    }
+}
+
+fn doc(out: &mut String, indent: &str, doc: &Option<String>) {
+    if let Some(doc) = doc {
+        for line in doc.lines() {
+            writeln!(out, "{}/// {}", indent, line).unwrap();
+        }
+    }
+}
+
+fn rename(out: &mut String, indent: &str, name: &str, id: &str) {
+    if id != name {
+        writeln!(out, "{}#[serde(rename = {:?})]", indent, name).unwrap();
+    }
+}
+
+// 列舉值的識別子，與 variants 一一對應
+fn variant_ids(variants: &[String]) -> Vec<String> {
+    let mut names = Names::default();
+    variants
+        .iter()
+        .map(|variant| names.claim(variant))
+        .collect()
+}
+
+fn innermost(mut datatype: &DataType) -> &DataType {
+    while let DataType::Array { inner, .. } = datatype {
+        datatype = inner;
+    }
+    datatype
+}
+
+struct Generator<'a> {
+    force: &'a Force,
+    out: String,
+    types: HashMap<String, String>,           // 分類名對應的結構名
+    enums: HashMap<(String, String), String>, // 分類名與域名對應的列舉名
+    functions: Names,                         // 預設值函式
+}
+
+impl<'a> Generator<'a> {
+    // 結構與列舉共用型別的命名空間，先為分類命名，列舉以 分類名+域名 命名
+    fn new(force: &'a Force) -> Generator<'a> {
+        let names = force.category_names();
+        let mut used = Names::default();
+        let types = names
+            .iter()
+            .map(|name| (name.to_string(), used.claim(name)))
+            .collect();
+        let mut enums = HashMap::new();
+        for name in names {
+            for field in &force.categories[name].fields {
+                if let DataType::Enum(_) = innermost(&field.datatype) {
+                    let id = used.claim(&format!("{}{}", name, field.name));
+                    enums.insert((name.to_owned(), field.name.clone()), id);
+                }
+            }
+        }
+        Generator {
+            force,
+            out: String::new(),
+            types,
+            enums,
+            functions: Names::default(),
+        }
+    }
+    fn enum_name(&self, category: &Category, field: &Field) -> String {
+        let key = (category.name.clone(), field.name.clone());
+        self.enums.get(&key).cloned().unwrap_or_default()
+    }
+    fn bond_target(&self, bondee: &Bondee) -> String {
+        match bondee {
+            Bondee::Choices(choices) if choices.len() == 1 => self
+                .types
+                .get(&choices[0])
+                .cloned()
+                .unwrap_or_else(|| identifier(&choices[0])),
+            _ => "()".to_owned(),
+        }
+    }
+    fn rust_type(&self, datatype: &DataType, enum_name: &str) -> String {
+        match datatype {
+            DataType::Number(_) => "i64".to_owned(),
+            DataType::OneLine(_) | DataType::Text(_) | DataType::DateTime => "String".to_owned(),
+            DataType::Bool => "bool".to_owned(),
+            DataType::Enum(_) => enum_name.to_owned(),
+            DataType::Bond(bondee, _) => format!("Bond<{}>", self.bond_target(bondee)),
+            DataType::TaggedBond(bondee, _) => {
+                format!("TaggedBond<{}>", self.bond_target(bondee))
+            }
+            DataType::Array { inner, .. } => format!("Vec<{}>", self.rust_type(inner, enum_name)),
+        }
+    }
+    fn literal(datatype: &DataType, value: &Value, enum_name: &str) -> String {
+        match (innermost(datatype), value) {
+            (DataType::Enum(variants), Value::String(s)) => {
+                let ids = variant_ids(variants);
+                match variants.iter().position(|variant| variant == s) {
+                    Some(i) => format!("{}::{}", enum_name, ids[i]),
+                    None => format!("{}::{}", enum_name, identifier(s)),
+                }
+            }
+            (_, Value::String(s)) => format!("{:?}.to_owned()", s),
+            (_, value) => value.to_string(),
+        }
+    }
+    fn gen_enum(&mut self, name: &str, variants: &[String]) {
+        let out = &mut self.out;
+        writeln!(
+            out,
+            "#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]"
+        )
+        .unwrap();
+        writeln!(out, "pub enum {} {{", name).unwrap();
+        for (variant, id) in variants.iter().zip(variant_ids(variants)) {
+            rename(out, "    ", variant, &id);
+            writeln!(out, "    {},", id).unwrap();
+        }
+        writeln!(out, "}}\n").unwrap();
+    }
+    fn gen_category(&mut self, category: &Category) {
+        let mut defaults = Vec::new();
+        for field in &category.fields {
+            if let DataType::Enum(variants) = innermost(&field.datatype) {
+                let enum_name = self.enum_name(category, field);
+                self.gen_enum(&enum_name, variants);
+            }
+        }
+
+        let struct_name = self.types[&category.name].clone();
+        doc(&mut self.out, "", &category.doc);
+        writeln!(
+            self.out,
+            "#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]"
+        )
+        .unwrap();
+        rename(&mut self.out, "", &category.name, &struct_name);
+        writeln!(self.out, "pub struct {} {{", struct_name).unwrap();
+        let mut fields = Names::default();
+        for field in &category.fields {
+            let id = fields.claim(&field.name);
+            let enum_name = self.enum_name(category, field);
+            let mut rust_type = self.rust_type(&field.datatype, &enum_name);
+            doc(&mut self.out, "    ", &field.doc);
+            rename(&mut self.out, "    ", &field.name, &id);
+            // 計算域以浮點數計算，由 fill_computed 填入
+            if field.computed.is_some() {
+                rust_type = "Option<f64>".to_owned();
+                writeln!(self.out, "    #[serde(default)]").unwrap();
+            } else if field.optional {
+                rust_type = format!("Option<{}>", rust_type);
+                writeln!(
+                    self.out,
+                    "    #[serde(default, skip_serializing_if = \"Option::is_none\")]"
+                )
+                .unwrap();
+            } else if let Some(default) = &field.default {
+                let function = self
+                    .functions
+                    .claim(&format!("default_{}_{}", category.name, field.name));
+                writeln!(self.out, "    #[serde(default = \"{}\")]", function).unwrap();
+                let value = Generator::literal(&field.datatype, default, &enum_name);
+                defaults.push((function, rust_type.clone(), value));
+            }
+            writeln!(self.out, "    pub {}: {},", id, rust_type).unwrap();
+        }
+        writeln!(self.out, "}}").unwrap();
+        for (function, rust_type, value) in defaults {
+            writeln!(self.out).unwrap();
+            writeln!(self.out, "fn {}() -> {} {{", function, rust_type).unwrap();
+            writeln!(self.out, "    {}", value).unwrap();
+            writeln!(self.out, "}}").unwrap();
+        }
+    }
+    fn gen(mut self) -> String {
+        self.out.push_str(PRELUDE);
+        for name in self.force.category_names() {
+            self.out.push('\n');
+            self.gen_category(&self.force.categories[name]);
+        }
+        self.out
+    }
+}
+
+// 為每個分類產生一個帶 serde derive 的 Rust 結構
+pub fn to_rust(force: &Force) -> String {
+    Generator::new(force).gen()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_identifier() {
+        assert_eq!(identifier("文章"), "文章");
+        assert_eq!(identifier("play-boy"), "play_boy");
+        assert_eq!(identifier("2020新聞"), "_2020新聞");
+        assert_eq!(identifier("type"), "r#type");
+        assert_eq!(identifier("yield"), "r#yield");
+        for name in ["crate", "self", "Self", "super"] {
+            assert_eq!(identifier(name), format!("{}_", name));
+        }
+        let mut names = Names::default();
+        assert_eq!(names.claim("a-b"), "a_b");
+        assert_eq!(names.claim("a_b"), "a_b_2");
+        assert_eq!(names.claim("self"), "self_");
+        assert_eq!(names.claim("self_"), "self__2");
+    }
+    #[test]
+    fn test_escaped_names() -> ForceResult<()> {
+        let force = parse(
+            "crate {數字 self 數字 `a-b` 數字 a_b 列舉 {`x-y`, x_y} 狀態 = \"x_y\"} \
+             `crate狀態` {鍵結[crate] 原文}",
+        )?;
+        let code = to_rust(&force);
+        assert!(code.contains(
+            "#[serde(rename = \"crate\")]\npub struct crate_ {\n    \
+             #[serde(rename = \"self\")]\n    pub self_: i64,\n    \
+             #[serde(rename = \"a-b\")]\n    pub a_b: i64,\n    \
+             #[serde(rename = \"a_b\")]\n    pub a_b_2: i64,\n"
+        ));
+        // 列舉名與分類 crate狀態 相同，改為加上數字
+        assert!(code.contains("pub struct crate狀態 {\n    pub 原文: Bond<crate_>,\n}"));
+        assert!(code.contains(
+            "pub enum crate狀態_2 {\n    #[serde(rename = \"x-y\")]\n    x_y,\n    \
+             #[serde(rename = \"x_y\")]\n    x_y_2,\n}"
+        ));
+        assert!(
+            code.contains("fn default_crate_狀態() -> crate狀態_2 {\n    crate狀態_2::x_y_2\n}")
+        );
+
+        // 原名中的引號與反斜線須跳脫
+        let force = parse(r#"文章 {數字 `a"b\\c`}"#)?;
+        assert!(to_rust(&force).contains("#[serde(rename = \"a\\\"b\\\\c\")]\n    pub a_b_c: i64,"));
+        Ok(())
+    }
+    #[test]
+    fn test_to_rust() -> ForceResult<()> {
+        let force = parse(
+            r#"/// 一篇文章
+            文章 {/// 文章的標題
+            單行 標題 數字 讚數 = 0 列舉 {草稿, 已發佈} 狀態 = "草稿"}
+            回覆 {鍵結[文章] 原文 列表<鍵結[*]>? 引用 文本 內文}"#,
+        )?;
+        let code = to_rust(&force);
+        assert!(code.contains(
+            "pub struct 回覆 {\n    pub 原文: Bond<文章>,\n    \
+             #[serde(default, skip_serializing_if = \"Option::is_none\")]\n    \
+             pub 引用: Option<Vec<Bond<()>>>,\n    pub 內文: String,\n}\n"
+        ));
+        assert!(code.contains(
+            "/// 一篇文章\n#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]\n\
+             pub struct 文章 {\n    /// 文章的標題\n    pub 標題: String,\n"
+        ));
+        assert!(code.contains("pub enum 文章狀態 {\n    草稿,\n    已發佈,\n}\n"));
+        assert!(
+            code.contains("    #[serde(default = \"default_文章_讚數\")]\n    pub 讚數: i64,\n")
+        );
+        assert!(code.contains("fn default_文章_狀態() -> 文章狀態 {\n    文章狀態::草稿\n}\n"));
+        assert!(code.contains("    pub fn new(category: String, id: String) -> Bond<T> {\n"));
+        assert!(code.contains(
+            "    pub fn new(category: String, id: String, tag: String) -> TaggedBond<T> {\n"
+        ));
+        Ok(())
+    }
+    #[test]
+    fn test_computed() -> ForceResult<()> {
+        let force = parse("文章 {數字 讚數 計算 數字 熱度 = 讚數 / 2}")?;
+        assert!(to_rust(&force).contains(
+            "pub struct 文章 {\n    pub 讚數: i64,\n    #[serde(default)]\n    pub 熱度: Option<f64>,\n}"
+        ));
+        Ok(())
+    }
+}
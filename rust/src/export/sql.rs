@@ -0,0 +1,435 @@
+// 產生 PostgreSQL 的 CREATE TABLE ，每個分類一張表，以 id 欄為主鍵
+// 鍵結為外鍵，鍵結對象不只一個時改存 分類名 與 id 兩欄；鍵結的列表另建一張關聯表
+// 外鍵在所有表建立後才以 ALTER TABLE 加上，以容許循環鍵結
+// 正則表達式以 ~ 檢查，須注意 PostgreSQL 的正則語法與 Rust 略有不同
+use crate::*;
+use serde_json::Value;
+use std::fmt::Write;
+
+type Naming = Box<dyn Fn(&str) -> String>;
+
+pub struct SqlBuilder {
+    table_name: Naming,
+    column_name: Naming,
+    id_column: String,
+}
+
+impl Default for SqlBuilder {
+    fn default() -> SqlBuilder {
+        SqlBuilder {
+            table_name: Box::new(|name| name.to_owned()),
+            column_name: Box::new(|name| name.to_owned()),
+            id_column: "id".to_owned(),
+        }
+    }
+}
+
+fn quote(identifier: &str) -> String {
+    format!("\"{}\"", identifier.replace('"', "\"\""))
+}
+
+fn literal(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "''"))
+}
+
+fn value_literal(value: &Value) -> String {
+    match value {
+        Value::String(s) => literal(s),
+        Value::Bool(true) => "TRUE".to_owned(),
+        Value::Bool(false) => "FALSE".to_owned(),
+        value => value.to_string(),
+    }
+}
+
+fn range_check(expression: &str, min: Option<i64>, max: Option<i64>, out: &mut Vec<String>) {
+    if let Some(min) = min {
+        out.push(format!("{} >= {}", expression, min));
+    }
+    if let Some(max) = max {
+        out.push(format!("{} <= {}", expression, max));
+    }
+}
+
+fn to_i64(n: Option<usize>) -> Option<i64> {
+    n.map(|n| n as i64)
+}
+
+fn literals(names: &[String]) -> String {
+    let literals: Vec<String> = names.iter().map(|name| literal(name)).collect();
+    literals.join(", ")
+}
+
+struct Table {
+    name: String,
+    columns: Vec<String>,
+}
+
+struct Generator<'a> {
+    builder: &'a SqlBuilder,
+    force: &'a Force,
+    tables: Vec<Table>,
+    foreign_keys: Vec<String>,
+}
+
+impl<'a> Generator<'a> {
+    fn table(&self, category: &str) -> String {
+        quote(&(self.builder.table_name)(category))
+    }
+    fn id(&self) -> String {
+        quote(&self.builder.id_column)
+    }
+    fn targets(&self, bondee: &Bondee) -> Vec<String> {
+        self.force.bond_targets(bondee)
+    }
+    // 單一對象的鍵結為一欄外鍵，否則為 分類名 與 id 兩欄
+    fn bond_columns(
+        &mut self,
+        table: &str,
+        column: &str,
+        bondee: &Bondee,
+        not_null: &str,
+        columns: &mut Vec<String>,
+    ) {
+        let targets = self.targets(bondee);
+        if let [target] = targets.as_slice() {
+            columns.push(format!("{} BIGINT{}", quote(column), not_null));
+            let foreign_key = format!(
+                "ALTER TABLE {} ADD FOREIGN KEY ({}) REFERENCES {} ({});",
+                table,
+                quote(column),
+                self.table(target),
+                self.id()
+            );
+            self.foreign_keys.push(foreign_key);
+        } else {
+            let category = quote(&format!("{}_category", column));
+            columns.push(format!(
+                "{} TEXT{} CHECK ({} IN ({}))",
+                category,
+                not_null,
+                category,
+                literals(&targets)
+            ));
+            columns.push(format!(
+                "{} BIGINT{}",
+                quote(&format!("{}_id", column)),
+                not_null
+            ));
+        }
+    }
+    // 列表以外的純量型別及其檢查條件
+    fn scalar(&self, datatype: &DataType, expression: &str) -> (&'static str, Vec<String>) {
+        let mut checks = Vec::new();
+        let sql_type = match datatype {
+            DataType::OneLine(bounds) => {
+                checks.push(format!("strpos({}, chr(10)) = 0", expression));
+                let length = format!("char_length({})", expression);
+                range_check(&length, to_i64(bounds.min), to_i64(bounds.max), &mut checks);
+                "TEXT"
+            }
+            DataType::Text(regex) => {
+                if let Some(regex) = regex {
+                    checks.push(format!("{} ~ {}", expression, literal(regex.as_str())));
+                }
+                "TEXT"
+            }
+            DataType::Number(bounds) => {
+                range_check(expression, bounds.min, bounds.max, &mut checks);
+                "BIGINT"
+            }
+            DataType::Enum(variants) => {
+                checks.push(format!("{} IN ({})", expression, literals(variants)));
+                "TEXT"
+            }
+            DataType::Bool => "BOOLEAN",
+            DataType::DateTime => "TIMESTAMPTZ",
+            // 巢狀列表等無法直接對應者
+            _ => "JSONB",
+        };
+        (sql_type, checks)
+    }
+    // 鍵結的列表另建一張關聯表，以 position 保留順序
+    fn join_table(&mut self, category: &Category, field: &Field, inner: &DataType) {
+        let owner = self.table(&category.name);
+        let name = format!(
+            "{}_{}",
+            (self.builder.table_name)(&category.name),
+            (self.builder.column_name)(&field.name)
+        );
+        let mut columns = vec![
+            format!(
+                "\"owner\" BIGINT NOT NULL REFERENCES {} ({}) ON DELETE CASCADE",
+                owner,
+                self.id()
+            ),
+            "\"position\" INTEGER NOT NULL".to_owned(),
+        ];
+        let table = quote(&name);
+        match inner {
+            DataType::Bond(bondee, _) => {
+                self.bond_columns(&table, "target", bondee, " NOT NULL", &mut columns)
+            }
+            DataType::TaggedBond(bondee, tags) => {
+                self.bond_columns(&table, "target", bondee, " NOT NULL", &mut columns);
+                let names: Vec<String> = tags.iter().map(|tag| tag.name.clone()).collect();
+                columns.push(format!(
+                    "\"tag\" TEXT NOT NULL CHECK (\"tag\" IN ({}))",
+                    literals(&names)
+                ));
+            }
+            _ => unreachable!(),
+        }
+        columns.push("PRIMARY KEY (\"owner\", \"position\")".to_owned());
+        self.tables.push(Table { name, columns });
+    }
+    fn field_columns(&mut self, category: &Category, field: &Field, columns: &mut Vec<String>) {
+        let table = self.table(&category.name);
+        let column = (self.builder.column_name)(&field.name);
+        let quoted = quote(&column);
+        // 計算域由資料庫算出，轉為浮點數以免整數除法
+        if let Some(expr) = &field.computed {
+            let expr = expr.render(&|leaf| match leaf {
+                Expr::Number(n) => format!("{}.0", n),
+                Expr::Field(name) => format!(
+                    "CAST({} AS DOUBLE PRECISION)",
+                    quote(&(self.builder.column_name)(name))
+                ),
+                _ => unreachable!(),
+            });
+            columns.push(format!(
+                "{} DOUBLE PRECISION GENERATED ALWAYS AS ({}) STORED",
+                quoted, expr
+            ));
+            return;
+        }
+        let not_null = if field.optional { "" } else { " NOT NULL" };
+        let mut constraints = Vec::new();
+        if field.is_unique() {
+            constraints.push(" UNIQUE".to_owned());
+        }
+        if let Some(default) = &field.default {
+            constraints.push(format!(" DEFAULT {}", value_literal(default)));
+        }
+        let (sql_type, checks) = match &field.datatype {
+            DataType::Bond(bondee, relation) => {
+                let mut bond_columns = Vec::new();
+                self.bond_columns(&table, &column, bondee, not_null, &mut bond_columns);
+                // 一對一的鍵結，同一對象只能出現一次
+                if relation.cardinality == Cardinality::One || field.is_unique() {
+                    let names: Vec<String> = match bond_columns.len() {
+                        1 => vec![quoted.clone()],
+                        _ => vec![
+                            quote(&format!("{}_category", column)),
+                            quote(&format!("{}_id", column)),
+                        ],
+                    };
+                    bond_columns.push(format!("UNIQUE ({})", names.join(", ")));
+                }
+                columns.extend(bond_columns);
+                return;
+            }
+            DataType::TaggedBond(bondee, tags) => {
+                self.bond_columns(&table, &column, bondee, not_null, columns);
+                let tag = quote(&format!("{}_tag", column));
+                let names: Vec<String> = tags.iter().map(|tag| tag.name.clone()).collect();
+                columns.push(format!(
+                    "{} TEXT{} CHECK ({} IN ({}))",
+                    tag,
+                    not_null,
+                    tag,
+                    literals(&names)
+                ));
+                return;
+            }
+            DataType::Array { inner, .. }
+                if matches!(**inner, DataType::Bond(..) | DataType::TaggedBond(..)) =>
+            {
+                self.join_table(category, field, inner);
+                return;
+            }
+            DataType::Array { inner, length } if !matches!(**inner, DataType::Array { .. }) => {
+                let (sql_type, _) = self.scalar(inner, &quoted);
+                let mut checks = Vec::new();
+                let cardinality = format!("cardinality({})", quoted);
+                range_check(
+                    &cardinality,
+                    to_i64(length.min),
+                    to_i64(length.max),
+                    &mut checks,
+                );
+                (format!("{}[]", sql_type), checks)
+            }
+            datatype => {
+                let (sql_type, checks) = self.scalar(datatype, &quoted);
+                (sql_type.to_owned(), checks)
+            }
+        };
+        let mut column = format!(
+            "{} {}{}{}",
+            quoted,
+            sql_type,
+            not_null,
+            constraints.concat()
+        );
+        if !checks.is_empty() {
+            write!(column, " CHECK ({})", checks.join(" AND ")).unwrap();
+        }
+        columns.push(column);
+    }
+    fn gen(mut self) -> String {
+        for name in self.force.category_names() {
+            let category = &self.force.categories[name];
+            let mut columns = vec![format!(
+                "{} BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY",
+                self.id()
+            )];
+            // 關聯表置於所屬的表之後
+            let at = self.tables.len();
+            for field in &category.fields {
+                self.field_columns(category, field, &mut columns);
+            }
+            let table = Table {
+                name: (self.builder.table_name)(name),
+                columns,
+            };
+            self.tables.insert(at, table);
+        }
+        let mut out = String::new();
+        for table in &self.tables {
+            writeln!(out, "CREATE TABLE {} (", quote(&table.name)).unwrap();
+            writeln!(out, "    {}", table.columns.join(",\n    ")).unwrap();
+            writeln!(out, ");\n").unwrap();
+        }
+        for foreign_key in &self.foreign_keys {
+            writeln!(out, "{}", foreign_key).unwrap();
+        }
+        out.truncate(out.trim_end().len());
+        out.push('\n');
+        out
+    }
+}
+
+impl SqlBuilder {
+    pub fn new() -> SqlBuilder {
+        SqlBuilder::default()
+    }
+    // 由分類名產生表名
+    pub fn table_name(mut self, naming: impl Fn(&str) -> String + 'static) -> SqlBuilder {
+        self.table_name = Box::new(naming);
+        self
+    }
+    // 由域名產生欄名
+    pub fn column_name(mut self, naming: impl Fn(&str) -> String + 'static) -> SqlBuilder {
+        self.column_name = Box::new(naming);
+        self
+    }
+    // 主鍵欄的名稱，預設為 id
+    pub fn id_column(mut self, name: &str) -> SqlBuilder {
+        self.id_column = name.to_owned();
+        self
+    }
+    pub fn build(&self, force: &Force) -> String {
+        Generator {
+            builder: self,
+            force,
+            tables: Vec::new(),
+            foreign_keys: Vec::new(),
+        }
+        .gen()
+    }
+}
+
+// 以預設的命名方式產生
+pub fn to_sql(force: &Force) -> String {
+    SqlBuilder::new().build(force)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_to_sql() -> ForceResult<()> {
+        let force = parse(
+            "文章 {@key 單行(1..64) 標題 數字(0..) 讚數 = 0 文本 /^[a-z]+$/ 代號 \
+             列舉 {草稿, 已發佈} 狀態 = \"草稿\" 布林? 置頂 日期時間 發文時間 列表<單行>(..5) 標籤}
+             回覆 {鍵結[文章] 原文 鍵結[文章,回覆] 單一? 引用 列表<鍵結[文章]> 參考 \
+             帶籤鍵結[文章] {挺 {} 噓 {}} 評價}",
+        )?;
+        let sql = to_sql(&force);
+        assert!(sql.contains(
+            "CREATE TABLE \"回覆\" (
+    \"id\" BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    \"原文\" BIGINT NOT NULL,
+    \"引用_category\" TEXT CHECK (\"引用_category\" IN ('文章', '回覆')),
+    \"引用_id\" BIGINT,
+    UNIQUE (\"引用_category\", \"引用_id\"),
+    \"評價\" BIGINT NOT NULL,
+    \"評價_tag\" TEXT NOT NULL CHECK (\"評價_tag\" IN ('挺', '噓'))
+);
+
+CREATE TABLE \"回覆_參考\" (
+    \"owner\" BIGINT NOT NULL REFERENCES \"回覆\" (\"id\") ON DELETE CASCADE,
+    \"position\" INTEGER NOT NULL,
+    \"target\" BIGINT NOT NULL,
+    PRIMARY KEY (\"owner\", \"position\")
+);
+
+CREATE TABLE \"文章\" ("
+        ));
+        assert!(sql.contains(
+            "    \"標題\" TEXT NOT NULL UNIQUE CHECK (strpos(\"標題\", chr(10)) = 0 AND \
+             char_length(\"標題\") >= 1 AND char_length(\"標題\") <= 64),
+    \"讚數\" BIGINT NOT NULL DEFAULT 0 CHECK (\"讚數\" >= 0),
+    \"代號\" TEXT NOT NULL CHECK (\"代號\" ~ '^[a-z]+$'),
+    \"狀態\" TEXT NOT NULL DEFAULT '草稿' CHECK (\"狀態\" IN ('草稿', '已發佈')),
+    \"置頂\" BOOLEAN,
+    \"發文時間\" TIMESTAMPTZ NOT NULL,
+    \"標籤\" TEXT[] NOT NULL CHECK (cardinality(\"標籤\") <= 5)
+);
+"
+        ));
+        assert!(sql.ends_with(
+            "ALTER TABLE \"回覆\" ADD FOREIGN KEY (\"原文\") REFERENCES \"文章\" (\"id\");
+ALTER TABLE \"回覆_參考\" ADD FOREIGN KEY (\"target\") REFERENCES \"文章\" (\"id\");
+ALTER TABLE \"回覆\" ADD FOREIGN KEY (\"評價\") REFERENCES \"文章\" (\"id\");
+"
+        ));
+        Ok(())
+    }
+    #[test]
+    fn test_builder() -> ForceResult<()> {
+        let force = parse("Post {鍵結[Post] parent}")?;
+        let sql = SqlBuilder::new()
+            .table_name(|name| format!("app_{}", name.to_lowercase()))
+            .column_name(|name| format!("{}_ref", name))
+            .id_column("pk")
+            .build(&force);
+        assert_eq!(
+            sql,
+            "CREATE TABLE \"app_post\" (
+    \"pk\" BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    \"parent_ref\" BIGINT NOT NULL
+);
+
+ALTER TABLE \"app_post\" ADD FOREIGN KEY (\"parent_ref\") REFERENCES \"app_post\" (\"pk\");
+"
+        );
+        Ok(())
+    }
+    #[test]
+    fn test_computed() -> ForceResult<()> {
+        let force = parse("文章 {數字 讚數 數字 噓數 計算 數字 熱度 = (讚數 - 噓數) / 2}")?;
+        assert_eq!(
+            to_sql(&force),
+            "CREATE TABLE \"文章\" (
+    \"id\" BIGINT GENERATED BY DEFAULT AS IDENTITY PRIMARY KEY,
+    \"讚數\" BIGINT NOT NULL,
+    \"噓數\" BIGINT NOT NULL,
+    \"熱度\" DOUBLE PRECISION GENERATED ALWAYS AS ((CAST(\"讚數\" AS DOUBLE PRECISION) - \
+             CAST(\"噓數\" AS DOUBLE PRECISION)) / 2.0) STORED
+);
+"
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,284 @@
+use crate::*;
+use std::collections::HashSet;
+use std::fmt::Write;
+
+pub struct TypeScriptOptions {
+    pub id_type: String, // 鍵結中 id 的型別
+}
+
+impl Default for TypeScriptOptions {
+    fn default() -> TypeScriptOptions {
+        TypeScriptOptions {
+            id_type: "string".to_owned(),
+        }
+    }
+}
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(c) if c.is_alphabetic() || c == '_' || c == '$' => {
+            chars.all(|c| c.is_alphanumeric() || c == '_' || c == '$')
+        }
+        _ => false,
+    }
+}
+
+// 不能作為介面名稱者，含保留字與內建型別
+const RESERVED: &[&str] = &[
+    "any",
+    "bigint",
+    "boolean",
+    "break",
+    "case",
+    "catch",
+    "class",
+    "const",
+    "continue",
+    "debugger",
+    "default",
+    "delete",
+    "do",
+    "else",
+    "enum",
+    "export",
+    "extends",
+    "false",
+    "finally",
+    "for",
+    "function",
+    "if",
+    "implements",
+    "import",
+    "in",
+    "instanceof",
+    "interface",
+    "let",
+    "never",
+    "new",
+    "null",
+    "number",
+    "object",
+    "package",
+    "private",
+    "protected",
+    "public",
+    "return",
+    "static",
+    "string",
+    "super",
+    "switch",
+    "symbol",
+    "this",
+    "throw",
+    "true",
+    "try",
+    "typeof",
+    "undefined",
+    "unknown",
+    "var",
+    "void",
+    "while",
+    "with",
+    "yield",
+];
+
+// 介面名稱必須是合法的識別子
+fn type_name(name: &str) -> String {
+    let ret: String = name
+        .chars()
+        .map(|c| {
+            if c.is_alphanumeric() || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    if !is_identifier(&ret) {
+        format!("_{}", ret)
+    } else if RESERVED.contains(&ret.as_str()) {
+        format!("{}_", ret)
+    } else {
+        ret
+    }
+}
+
+// 已用過的介面名稱，如 a-b 與 a_b 會轉成相同的名稱，後者加上數字區分
+struct Names {
+    used: HashSet<String>,
+}
+
+impl Names {
+    // 開頭輸出的 Bond 與 TaggedBond 也佔用名稱
+    fn new() -> Names {
+        Names {
+            used: ["Bond", "TaggedBond"]
+                .iter()
+                .map(|name| name.to_string())
+                .collect(),
+        }
+    }
+    fn claim(&mut self, name: &str) -> String {
+        let base = type_name(name);
+        let mut id = base.clone();
+        let mut n = 2;
+        while self.used.contains(&id) {
+            id = format!("{}_{}", base, n);
+            n += 1;
+        }
+        self.used.insert(id.clone());
+        id
+    }
+}
+
+fn property_name(name: &str) -> String {
+    if is_identifier(name) {
+        name.to_owned()
+    } else {
+        format!("{:?}", name)
+    }
+}
+
+fn doc(out: &mut String, indent: &str, doc: &Option<String>) {
+    if let Some(doc) = doc {
+        writeln!(out, "{}/**", indent).unwrap();
+        for line in doc.lines() {
+            writeln!(out, "{} * {}", indent, line).unwrap();
+        }
+        writeln!(out, "{} */", indent).unwrap();
+    }
+}
+
+fn union(names: &[String]) -> String {
+    let literals: Vec<String> = names.iter().map(|name| format!("{:?}", name)).collect();
+    literals.join(" | ")
+}
+
+fn bond_target(bondee: &Bondee) -> String {
+    match bondee {
+        Bondee::All | Bondee::AllExcept(_) => "string".to_owned(),
+        Bondee::Choices(choices) => union(choices),
+    }
+}
+
+fn ts_type(datatype: &DataType) -> String {
+    match datatype {
+        DataType::Number(_) => "number".to_owned(),
+        DataType::OneLine(_) | DataType::Text(_) | DataType::DateTime => "string".to_owned(),
+        DataType::Bool => "boolean".to_owned(),
+        DataType::Enum(variants) => union(variants),
+        DataType::Bond(bondee, _) => format!("Bond<{}>", bond_target(bondee)),
+        DataType::TaggedBond(bondee, tags) => {
+            let names: Vec<String> = tags.iter().map(|tag| tag.name.clone()).collect();
+            format!("TaggedBond<{}, {}>", bond_target(bondee), union(&names))
+        }
+        DataType::Array { inner, .. } => match **inner {
+            DataType::Enum(_) | DataType::TaggedBond(..) => format!("({})[]", ts_type(inner)),
+            _ => format!("{}[]", ts_type(inner)),
+        },
+    }
+}
+
+// 為每個分類產生一個 TypeScript 介面，輸出可直接存為 .d.ts
+pub fn to_typescript(force: &Force, options: &TypeScriptOptions) -> String {
+    let mut out = String::new();
+    writeln!(
+        out,
+        "export interface Bond<C extends string = string> {{\n    category: C;\n    id: {};\n}}\n",
+        options.id_type
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "export interface TaggedBond<C extends string = string, T extends string = string> \
+         extends Bond<C> {{\n    tag: T;\n}}"
+    )
+    .unwrap();
+    let mut names: Vec<&String> = force.categories.keys().collect();
+    names.sort();
+    let mut used = Names::new();
+    for name in names {
+        let category = &force.categories[name];
+        writeln!(out).unwrap();
+        doc(&mut out, "", &category.doc);
+        writeln!(out, "export interface {} {{", used.claim(&category.name)).unwrap();
+        for field in &category.fields {
+            doc(&mut out, "    ", &field.doc);
+            // 計算域由 fill_computed 填入，填入前可能不存在
+            if field.computed.is_some() {
+                let name = property_name(&field.name);
+                writeln!(out, "    readonly {}?: number;", name).unwrap();
+                continue;
+            }
+            // 有預設值的域可省略
+            let optional = if field.optional || field.default.is_some() {
+                "?"
+            } else {
+                ""
+            };
+            writeln!(
+                out,
+                "    {}{}: {};",
+                property_name(&field.name),
+                optional,
+                ts_type(&field.datatype)
+            )
+            .unwrap();
+        }
+        writeln!(out, "}}").unwrap();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_to_typescript() -> ForceResult<()> {
+        let force = parse(
+            "回覆 {鍵結[文章,留言] 原文 數字? 讚數 列表<列舉{挺,噓}> 推文 單行 `play-boy` 文本/.+/ 內文}",
+        )?;
+        let options = TypeScriptOptions {
+            id_type: "number".to_owned(),
+        };
+        let code = to_typescript(&force, &options);
+        assert!(code.contains("category: C;\n    id: number;\n"));
+        assert!(code.contains(
+            "export interface 回覆 {\n    原文: Bond<\"文章\" | \"留言\">;\n    讚數?: number;\n    \
+             推文: (\"挺\" | \"噓\")[];\n    \"play-boy\": string;\n    內文: string;\n}\n"
+        ));
+        assert!(to_typescript(&force, &TypeScriptOptions::default()).contains("id: string;"));
+
+        let force = parse("/// 回覆文章\n回覆 {/// 原文\n鍵結[*] 原文}")?;
+        assert!(to_typescript(&force, &options).contains(
+            "/**\n * 回覆文章\n */\nexport interface 回覆 {\n    /**\n     * 原文\n     */\n"
+        ));
+        Ok(())
+    }
+    #[test]
+    fn test_computed() -> ForceResult<()> {
+        let force = parse("文章 {數字 讚數 計算 數字 熱度 = 讚數 / 2}")?;
+        let code = to_typescript(&force, &TypeScriptOptions::default());
+        assert!(code.contains(
+            "export interface 文章 {\n    讚數: number;\n    readonly 熱度?: number;\n}\n"
+        ));
+        Ok(())
+    }
+    #[test]
+    fn test_names() -> ForceResult<()> {
+        let force = parse(
+            "`a-b` {} a_b {} class {} Bond {} `1a` {} 文章 {單行 標題 = \"未命名\" 數字 讚數 = 0}",
+        )?;
+        let code = to_typescript(&force, &TypeScriptOptions::default());
+        assert!(code.contains("export interface _1a {"));
+        assert!(code.contains("export interface Bond_2 {"));
+        assert!(code.contains("export interface a_b {"));
+        assert!(code.contains("export interface a_b_2 {"));
+        assert!(code.contains("export interface class_ {"));
+        assert!(
+            code.contains("export interface 文章 {\n    標題?: string;\n    讚數?: number;\n}\n")
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,390 @@
+// 將力轉回標準格式的原始碼
+use crate::*;
+use lexer::quote_identifier;
+use logos::Logos;
+use serde_json::Value;
+use std::fmt::Write;
+
+const INDENT: &str = "    ";
+
+fn format_string(s: &str) -> String {
+    let mut ret = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => ret.push_str("\\\""),
+            '\\' => ret.push_str("\\\\"),
+            '\n' => ret.push_str("\\n"),
+            c => ret.push(c),
+        }
+    }
+    ret.push('"');
+    ret
+}
+
+fn format_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => format_string(s),
+        Value::Bool(true) => "真".to_owned(),
+        Value::Bool(false) => "假".to_owned(),
+        value => value.to_string(),
+    }
+}
+
+// 屬性的引數能寫成識別子時不加引號
+fn format_argument(argument: &str) -> String {
+    let mut lexer = lexer::Token::lexer(argument);
+    match (lexer.next(), lexer.next()) {
+        (Some(lexer::Token::Identifier(s)), None) if s == argument => s.into_owned(),
+        _ => format_string(argument),
+    }
+}
+
+// 分類與域的屬性皆以空白結尾
+fn format_attributes(attributes: &[Attribute], out: &mut String) {
+    for attribute in attributes {
+        write!(out, "@{}", attribute.name).unwrap();
+        if !attribute.arguments.is_empty() {
+            let arguments: Vec<String> = attribute
+                .arguments
+                .iter()
+                .map(|argument| format_argument(argument))
+                .collect();
+            write!(out, "({})", arguments.join(", ")).unwrap();
+        }
+        out.push(' ');
+    }
+}
+
+fn format_tags(tags: &[Tag], depth: usize, out: &mut String) {
+    let indent = INDENT.repeat(depth);
+    out.push_str(" {\n");
+    for tag in tags {
+        if tag.transfuse.is_empty() {
+            writeln!(
+                out,
+                "{}{}{} {{}}",
+                indent,
+                INDENT,
+                quote_identifier(&tag.name)
+            )
+            .unwrap();
+        } else {
+            let transfuse: Vec<String> = tag.transfuse.iter().map(|n| n.to_string()).collect();
+            writeln!(
+                out,
+                "{}{}{} {{",
+                indent,
+                INDENT,
+                quote_identifier(&tag.name)
+            )
+            .unwrap();
+            writeln!(
+                out,
+                "{}{}{}輸能: [{}]",
+                indent,
+                INDENT,
+                INDENT,
+                transfuse.join(", ")
+            )
+            .unwrap();
+            writeln!(out, "{}{}}}", indent, INDENT).unwrap();
+        }
+    }
+    write!(out, "{}}}", indent).unwrap();
+}
+
+// 與某個模式相同的正則表達式以模式名輸出
+fn pattern_name<'a>(patterns: &'a Patterns, regex: &str) -> Option<&'a String> {
+    patterns
+        .iter()
+        .filter(|(_, pattern)| pattern.as_str() == regex)
+        .map(|(name, _)| name)
+        .min()
+}
+
+// depth 為域所在的縮排層數，帶籤鍵結的標籤依此縮排
+fn format_datatype(datatype: &DataType, depth: usize, patterns: &Patterns, out: &mut String) {
+    match datatype {
+        DataType::Text(Some(regex)) if pattern_name(patterns, regex.as_str()).is_some() => write!(
+            out,
+            "文本 {}",
+            quote_identifier(pattern_name(patterns, regex.as_str()).unwrap())
+        )
+        .unwrap(),
+        DataType::TaggedBond(bondee, tags) => {
+            write!(out, "帶籤鍵結{}", bondee).unwrap();
+            format_tags(tags, depth, out);
+        }
+        DataType::Enum(variants) => {
+            let variants: Vec<String> = variants.iter().map(|v| quote_identifier(v)).collect();
+            write!(out, "列舉 {{ {} }}", variants.join(", ")).unwrap()
+        }
+        DataType::Array { inner, length } => {
+            out.push_str("列表<");
+            format_datatype(inner, depth, patterns, out);
+            write!(out, ">{}", length).unwrap();
+        }
+        datatype => write!(out, "{}", datatype).unwrap(),
+    }
+}
+
+fn format_doc(doc: &Option<String>, indent: &str, out: &mut String) {
+    if let Some(doc) = doc {
+        for line in doc.lines() {
+            writeln!(out, "{}/// {}", indent, line).unwrap();
+        }
+    }
+}
+
+fn format_field(category: &Category, field: &Field, patterns: &Patterns, out: &mut String) {
+    format_doc(&field.doc, INDENT, out);
+    out.push_str(INDENT);
+    if category.title_field.as_ref() == Some(&field.name) {
+        out.push_str("@title ");
+    }
+    if field.key {
+        out.push_str("@key ");
+    }
+    if field.unique {
+        out.push_str("@unique ");
+    }
+    format_attributes(&field.attributes, out);
+    if field.computed.is_some() {
+        out.push_str("計算 ");
+    }
+    format_datatype(&field.datatype, 1, patterns, out);
+    if field.optional {
+        out.push('?');
+    }
+    write!(out, " {}", quote_identifier(&field.name)).unwrap();
+    if let Some(inverse) = field.datatype.relation().and_then(|r| r.inverse.as_ref()) {
+        write!(out, " 反向 {}", quote_identifier(inverse)).unwrap();
+    }
+    if let Some(default) = &field.default {
+        write!(out, " = {}", format_value(default)).unwrap();
+    }
+    if let Some(computed) = &field.computed {
+        write!(out, " = {}", computed).unwrap();
+    }
+    out.push('\n');
+}
+
+fn format_fields<'a>(
+    category: &Category,
+    fields: impl Iterator<Item = &'a Field>,
+    patterns: &Patterns,
+) -> String {
+    let mut out = String::new();
+    format_doc(&category.doc, "", &mut out);
+    format_attributes(&category.attributes, &mut out);
+    let name = quote_identifier(&category.name);
+    if category.parents.is_empty() {
+        writeln!(out, "{} {{", name).unwrap();
+    } else {
+        let parents: Vec<String> = category
+            .parents
+            .iter()
+            .map(|p| quote_identifier(p))
+            .collect();
+        writeln!(out, "{} : {} {{", name, parents.join(", ")).unwrap();
+    }
+    for field in fields {
+        format_field(category, field, patterns, &mut out);
+    }
+    out.push_str("}\n");
+    out
+}
+
+// 輸出所有已展開的域
+pub fn format_category(category: &Category) -> String {
+    format_fields(category, category.fields.iter(), &Patterns::new())
+}
+
+// 省略與父分類相同的繼承域
+fn format_child(force: &Force, category: &Category) -> String {
+    let mut inherited: Vec<&Field> = Vec::new();
+    let mut inherited_title = None;
+    for parent in category
+        .parents
+        .iter()
+        .filter_map(|p| force.categories.get(p))
+    {
+        for field in &parent.fields {
+            inherited.retain(|f| f.name != field.name);
+            inherited.push(field);
+        }
+        inherited_title = parent.title_field.as_ref().or(inherited_title);
+    }
+    let fields = category.fields.iter().filter(|field| {
+        let is_new_title = category.title_field.as_ref() == Some(&field.name)
+            && inherited_title != category.title_field.as_ref();
+        is_new_title || !inherited.contains(field)
+    });
+    format_fields(category, fields, &force.patterns)
+}
+
+// 模式與分類皆依名稱排序，域維持原本順序
+pub fn format_force(force: &Force) -> String {
+    let mut out = String::new();
+    let mut patterns: Vec<(&String, &String)> = force.patterns.iter().collect();
+    patterns.sort();
+    for (name, regex) in patterns {
+        writeln!(out, "模式 {} = /{}/", quote_identifier(name), regex).unwrap();
+    }
+    let mut names: Vec<&String> = force.categories.keys().collect();
+    names.sort();
+    for name in names {
+        out.push_str(&format_child(force, &force.categories[name]));
+    }
+    out
+}
+
+pub fn format_source(source: &str) -> ForceResult<String> {
+    Ok(format_force(&parse(source)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_format_source() -> ForceResult<()> {
+        let source = r#"問卦{文本/.{256,}/ 內文}
+            新聞 {  ///顯示於列表
+            @title 單行 標題
+        數字(0..)? 讚數=0 列舉{草稿,已發佈} 狀態 = "草稿"
+        列表<鍵結[問卦,新聞]>(1..) 相關 單行 引言 = "他說\"好\""}
+        /// 回覆文章
+        回覆 {帶籤鍵結[*] {挺 {輸能: [1]} 回 {}} 原文}"#;
+        let expected = r#"問卦 {
+    文本/.{256,}/ 內文
+}
+/// 回覆文章
+回覆 {
+    帶籤鍵結[*] {
+        挺 {
+            輸能: [1]
+        }
+        回 {}
+    } 原文
+}
+新聞 {
+    /// 顯示於列表
+    @title 單行 標題
+    數字(0..)? 讚數 = 0
+    列舉 { 草稿, 已發佈 } 狀態 = "草稿"
+    列表<鍵結[問卦,新聞]>(1..) 相關
+    單行 引言 = "他說\"好\""
+}
+"#;
+        assert_eq!(format_source(source)?, expected);
+        assert_eq!(format_source(expected)?, expected);
+        assert_eq!(parse(expected)?.categories.len(), 3);
+        Ok(())
+    }
+    #[test]
+    fn test_format_inheritance() -> ForceResult<()> {
+        let source = "元資料 {單行 作者} 文章 : 元資料 {@title 單行 標題} 回覆 : 文章 {文本 作者}";
+        let expected = "元資料 {
+    單行 作者
+}
+回覆 : 文章 {
+    文本 作者
+}
+文章 : 元資料 {
+    @title 單行 標題
+}
+";
+        assert_eq!(format_source(source)?, expected);
+        assert_eq!(format_source(expected)?, expected);
+        Ok(())
+    }
+    #[test]
+    fn test_format_pattern() -> ForceResult<()> {
+        let source = "會員 {文本 信箱 聯絡 列表<文本 信箱> 其他} 模式 信箱 = /.+@.+/";
+        let expected = "模式 信箱 = /.+@.+/
+會員 {
+    文本 信箱 聯絡
+    列表<文本 信箱> 其他
+}
+";
+        assert!(format_source(source).is_err());
+        let source = "模式 信箱 = /.+@.+/ 會員 {文本 信箱 聯絡 列表<文本 信箱> 其他}";
+        assert_eq!(format_source(source)?, expected);
+        assert_eq!(format_source(expected)?, expected);
+        Ok(())
+    }
+    #[test]
+    fn test_format_namespace() -> ForceResult<()> {
+        let source = "命名空間 論壇 { 文章 {單行 標題} 回覆 : 文章 {鍵結[文章] 原文} }";
+        let expected = "論壇.回覆 : 論壇.文章 {
+    鍵結[論壇.文章] 原文
+}
+論壇.文章 {
+    單行 標題
+}
+";
+        assert_eq!(format_source(source)?, expected);
+        assert_eq!(format_source(expected)?, expected);
+        Ok(())
+    }
+    #[test]
+    fn test_format_relation() -> ForceResult<()> {
+        let source =
+            "回覆 {鍵結[文章] 多個 原文 反向 回覆 列表<鍵結[文章] 單一>? 引用 反向 被引用}";
+        let expected = "回覆 {
+    鍵結[文章] 原文 反向 回覆
+    列表<鍵結[文章] 單一>? 引用 反向 被引用
+}
+";
+        assert_eq!(format_source(source)?, expected);
+        assert_eq!(format_source(expected)?, expected);
+        Ok(())
+    }
+    #[test]
+    fn test_format_attributes() -> ForceResult<()> {
+        let source = "/// 站務\n@隱藏 @限定( 管理員,\"版 主\" ) 公告 {單行 標題}";
+        let expected = "/// 站務
+@隱藏 @限定(管理員, \"版 主\") 公告 {
+    單行 標題
+}
+";
+        assert_eq!(format_source(source)?, expected);
+        assert_eq!(format_source(expected)?, expected);
+        let force = parse("會員 {@索引 @key 單行 帳號}")?;
+        assert_eq!(
+            format_category(&force.categories["會員"]),
+            "會員 {\n    @key @索引 單行 帳號\n}\n"
+        );
+        Ok(())
+    }
+    #[test]
+    fn test_format_computed() -> ForceResult<()> {
+        let source =
+            "文章 {數字 讚數 數字 噓數 計算 數字(0..)? 熱度=(讚數-噓數)*2/ (讚數+噓數 -1) \
+                      計算 數字 倒數 = -(讚數 - 1) - -2 + - 3}";
+        let expected = "文章 {
+    數字 讚數
+    數字 噓數
+    計算 數字(0..)? 熱度 = (讚數 - 噓數) * 2 / (讚數 + 噓數 - 1)
+    計算 數字 倒數 = -(讚數 - 1) - -2 + -3
+}
+";
+        assert_eq!(format_source(source)?, expected);
+        assert_eq!(format_source(expected)?, expected);
+        Ok(())
+    }
+    #[test]
+    fn test_format_quoted_identifier() -> ForceResult<()> {
+        let source =
+            "`會員 資料` {單行 `發布 日期` 列舉{`單行`, 一般} 狀態 鍵結[`會員 資料`] 推薦人}";
+        let expected = "`會員 資料` {
+    單行 `發布 日期`
+    列舉 { `單行`, 一般 } 狀態
+    鍵結[`會員 資料`] 推薦人
+}
+";
+        assert_eq!(format_source(source)?, expected);
+        assert_eq!(format_source(expected)?, expected);
+        Ok(())
+    }
+}
@@ -0,0 +1,163 @@
+// 以 匯入 "路徑" 將力拆成多個檔案，檔案如何讀取交由載入器決定，以便在 WASM 中使用
+use crate::parser::Parser;
+use crate::prelude::*;
+use crate::semantic::link;
+use crate::*;
+
+pub trait Loader {
+    // path 已相對於匯入者所在的目錄展開
+    fn load(&self, path: &str) -> Result<String, String>;
+}
+
+// 從檔案系統讀取
+#[cfg(feature = "std")]
+pub struct FileLoader;
+
+#[cfg(feature = "std")]
+impl Loader for FileLoader {
+    fn load(&self, path: &str) -> Result<String, String> {
+        std::fs::read_to_string(path).map_err(|e| e.to_string())
+    }
+}
+
+// 從記憶體中的 路徑 → 原始碼 讀取
+#[derive(Debug, Default)]
+pub struct MemoryLoader {
+    pub files: Map<String, String>,
+}
+
+impl MemoryLoader {
+    pub fn new() -> MemoryLoader {
+        MemoryLoader::default()
+    }
+    pub fn insert(&mut self, path: &str, source: &str) {
+        self.files.insert(path.to_owned(), source.to_owned());
+    }
+}
+
+impl Loader for MemoryLoader {
+    fn load(&self, path: &str) -> Result<String, String> {
+        self.files
+            .get(path)
+            .cloned()
+            .ok_or_else(|| "檔案不存在".to_owned())
+    }
+}
+
+// 將 path 視為相對於 from 所在目錄的路徑，並消去 . 與 ..
+pub(crate) fn join_path(from: &str, path: &str) -> String {
+    let mut parts: Vec<&str> = if path.starts_with('/') {
+        vec![""]
+    } else {
+        let mut parts: Vec<&str> = from.split('/').collect();
+        parts.pop();
+        parts
+    };
+    for part in path.split('/') {
+        match part {
+            "." | "" => {}
+            ".." => {
+                parts.pop();
+            }
+            part => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+// 解析 path 以及其匯入的所有檔案
+pub fn parse_file(path: &str, loader: &dyn Loader) -> ForceResult<Force> {
+    let source = loader
+        .load(path)
+        .map_err(|message| ForceError::LoadFailed {
+            path: path.to_owned(),
+            message,
+            span: 0..0,
+        })?;
+    let mut parser = Parser::with_loader(&source, path, loader);
+    let mut force = parser.parse_unflattened()?;
+    link(&mut force.categories)?;
+    Ok(force)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_join_path() {
+        assert_eq!(join_path("main.force", "base.force"), "base.force");
+        assert_eq!(
+            join_path("a/main.force", "./b/base.force"),
+            "a/b/base.force"
+        );
+        assert_eq!(join_path("a/b/main.force", "../base.force"), "a/base.force");
+        assert_eq!(join_path("a/main.force", "/base.force"), "/base.force");
+    }
+    #[test]
+    fn test_import() -> ForceResult<()> {
+        let mut loader = MemoryLoader::new();
+        loader.insert(
+            "main.force",
+            "匯入 \"base/文章.force\"\n回覆 : 文章 {鍵結[文章] 原文}",
+        );
+        loader.insert(
+            "base/文章.force",
+            "匯入 \"模式.force\" 文章 {文本 信箱 作者}",
+        );
+        loader.insert("base/模式.force", "模式 信箱 = /.+@.+/");
+        let force = parse_file("main.force", &loader)?;
+        let names: Vec<&str> = force.categories["回覆"]
+            .fields
+            .iter()
+            .map(|f| f.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["作者", "原文"]);
+        assert_eq!(force.patterns["信箱"], ".+@.+");
+        Ok(())
+    }
+    #[test]
+    fn test_import_error() {
+        let mut loader = MemoryLoader::new();
+        loader.insert("a.force", "匯入 \"b.force\" 甲 {單行 名}");
+        loader.insert("b.force", "匯入 \"a.force\"");
+        match parse_file("a.force", &loader) {
+            Err(ForceError::ImportError { path, error, span }) => {
+                assert_eq!(path, "b.force");
+                assert_eq!(span, 0..16);
+                assert!(matches!(*error, ForceError::ImportCycle { .. }));
+            }
+            other => panic!("預期 ImportError ，但得到 {:?}", other),
+        }
+
+        loader.insert("b.force", "甲 {單行 名}");
+        assert!(matches!(
+            parse_file("a.force", &loader),
+            Err(ForceError::DuplicateCategory { .. })
+        ));
+        loader.insert("b.force", "乙 {單行 名");
+        assert!(matches!(
+            parse_file("a.force", &loader),
+            Err(ForceError::ImportError { .. })
+        ));
+        assert!(matches!(
+            parse_file("c.force", &loader),
+            Err(ForceError::LoadFailed { .. })
+        ));
+        assert!(matches!(
+            parse("匯入 \"b.force\""),
+            Err(ForceError::LoadFailed { .. })
+        ));
+    }
+    #[test]
+    fn test_diamond_import() -> ForceResult<()> {
+        let mut loader = MemoryLoader::new();
+        loader.insert("main.force", "匯入 \"a.force\" 匯入 \"b.force\"");
+        loader.insert("a.force", "匯入 \"base.force\" 甲 : 基礎 {}");
+        loader.insert("b.force", "匯入 \"base.force\" 乙 : 基礎 {}");
+        loader.insert("base.force", "基礎 {單行 名}");
+        let force = parse_file("main.force", &loader)?;
+        assert_eq!(force.categories.len(), 3);
+        assert_eq!(force.categories["乙"].fields[0].name, "名");
+        Ok(())
+    }
+}
@@ -0,0 +1,392 @@
+// 增量解析，供編輯器在每次按鍵後更新解析結果
+// 原始碼被切成一個個頂層項目（分類或模式），編輯時只重新切分、解析受影響的項目
+// 從受影響項目的前一個項目結尾開始重新切分 token ，直到某個 token 恰好落在未受影響的項目開頭為止
+// 詞法分析沒有狀態，因此該處之後的 token 必定與編輯前相同
+// 模式會影響其後的分類，動到模式時整份重新解析；含有命名空間、匯入等其他項目時一律整份重新解析
+// 結果與 parse_all_errors 相同
+use crate::lexer::Token;
+use crate::parser::Parser;
+use crate::semantic::link;
+use crate::*;
+use logos::{Logos, Span};
+use regex::Regex;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ItemKind {
+    Doc, // 目前只有文件註解，尚不知種類
+    Category,
+    Pattern,
+    Other,
+}
+
+// 切分出的項目，尚未解析
+struct Raw {
+    span: Span,
+    kind: ItemKind,
+    depth: i32,    // 分類的大括號深度
+    tokens: usize, // 模式已讀入的 token 數
+}
+
+#[derive(Default)]
+struct Splitter {
+    items: Vec<Raw>,
+    current: Option<Raw>,
+}
+
+impl Splitter {
+    fn is_idle(&self) -> bool {
+        self.current.is_none()
+    }
+    fn push(&mut self, token: &Token, span: Span) {
+        let mut raw = self.current.take().unwrap_or(Raw {
+            span: span.clone(),
+            kind: ItemKind::Doc,
+            depth: 0,
+            tokens: 0,
+        });
+        raw.span.end = span.end;
+        if raw.kind == ItemKind::Doc {
+            raw.kind = match token {
+                Token::DocComment(_) => ItemKind::Doc,
+                Token::Identifier(_) | Token::At => ItemKind::Category,
+                Token::Pattern => ItemKind::Pattern,
+                _ => ItemKind::Other,
+            };
+        }
+        let done = match raw.kind {
+            ItemKind::Doc => false,
+            ItemKind::Category => {
+                match token {
+                    Token::LeftCurlyBrace => raw.depth += 1,
+                    Token::RightCurlyBrace => raw.depth -= 1,
+                    _ => {}
+                }
+                // 未進入大括號就遇到 } ，交由整份解析處理
+                if raw.depth < 0 {
+                    raw.kind = ItemKind::Other;
+                }
+                raw.depth <= 0 && *token == Token::RightCurlyBrace
+            }
+            // 模式 名稱 = /正則表達式/ ，不合此形式者視為其他項目
+            ItemKind::Pattern => {
+                let fits = matches!(
+                    (raw.tokens, token),
+                    (0, Token::Pattern)
+                        | (1, Token::Identifier(_))
+                        | (2, Token::Equal)
+                        | (3, Token::Regex(_))
+                );
+                raw.tokens += 1;
+                if !fits {
+                    raw.kind = ItemKind::Other;
+                }
+                !fits || raw.tokens == 4
+            }
+            ItemKind::Other => true,
+        };
+        if done {
+            self.items.push(raw);
+        } else {
+            self.current = Some(raw);
+        }
+    }
+    // 結尾只有文件註解時忽略之
+    // 未完成的項目延伸到原始碼結尾，解析時才會在同樣的位置遇到檔案結尾
+    fn finish(mut self, end: usize) -> Vec<Raw> {
+        if let Some(mut raw) = self.current.take() {
+            if raw.kind != ItemKind::Doc {
+                raw.span.end = end;
+                self.items.push(raw);
+            }
+        }
+        self.items
+    }
+}
+
+struct Item {
+    span: Span,
+    kind: ItemKind,
+    name_span: Span, // 分類名稱開頭的 token ，重複的分類以此回報
+    category: Option<Category>,
+    pattern: Option<(String, Regex)>,
+    lex_errors: Vec<ForceError>,
+    errors: Vec<ForceError>,
+}
+
+impl Item {
+    fn shift(&mut self, delta: isize) {
+        let shift = |span: &mut Span| {
+            span.start = (span.start as isize + delta) as usize;
+            span.end = (span.end as isize + delta) as usize;
+        };
+        shift(&mut self.span);
+        shift(&mut self.name_span);
+        for error in self.lex_errors.iter_mut().chain(self.errors.iter_mut()) {
+            if let Some(span) = error.span_mut() {
+                shift(span);
+            }
+        }
+    }
+}
+
+fn parse_item(source: &str, raw: Raw, patterns: &HashMap<String, Regex>) -> Item {
+    let start = raw.span.start;
+    let mut parser = Parser::with_patterns(&source[raw.span.clone()], patterns.clone());
+    let (categories, lex_errors, errors) = parser.parse_unlinked_all_errors();
+    let category = categories.into_iter().next().map(|(_, category)| category);
+    let mut tokens = Token::lexer(&source[raw.span.clone()]).spanned();
+    let name_span = tokens
+        .find(|(token, _)| !matches!(token, Token::DocComment(_)))
+        .map_or(0..0, |(_, span)| span);
+    let pattern = match (raw.kind, tokens.next()) {
+        (ItemKind::Pattern, Some((Token::Identifier(name), _))) => {
+            let patterns = parser.into_patterns();
+            patterns
+                .get(name.as_ref())
+                .map(|regex| (name.into_owned(), regex.clone()))
+        }
+        _ => None,
+    };
+    let mut item = Item {
+        span: 0..raw.span.len(),
+        kind: raw.kind,
+        name_span,
+        category,
+        pattern,
+        lex_errors,
+        errors,
+    };
+    item.shift(start as isize);
+    item
+}
+
+pub struct IncrementalParser {
+    source: String,
+    items: Option<Vec<Item>>, // 無法切分成項目時為 None
+    force: Force,
+    errors: Vec<ForceError>,
+    reparsed: usize,
+}
+
+impl IncrementalParser {
+    pub fn new(source: &str) -> IncrementalParser {
+        let mut parser = IncrementalParser {
+            source: source.to_owned(),
+            items: None,
+            force: Force::default(),
+            errors: Vec::new(),
+            reparsed: 0,
+        };
+        parser.parse_whole();
+        parser
+    }
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+    pub fn force(&self) -> &Force {
+        &self.force
+    }
+    pub fn errors(&self) -> &[ForceError] {
+        &self.errors
+    }
+    // 上次更新時重新解析的項目數，整份重新解析時為所有項目
+    pub fn reparsed(&self) -> usize {
+        self.reparsed
+    }
+    // 以 text 取代原始碼中 range 的部分，range 以位元組偏移表示
+    pub fn edit(&mut self, range: Span, text: &str) {
+        self.source.replace_range(range.clone(), text);
+        let mut items = match self.items.take() {
+            Some(items) => items,
+            None => return self.parse_whole(),
+        };
+        let delta = text.len() as isize - range.len() as isize;
+        let edited_end = range.start + text.len();
+        // 與編輯範圍相鄰的項目也算受影響，其 token 可能與新輸入的字元相連
+        let prefix = items.iter().take_while(|item| item.span.end < range.start);
+        let prefix = prefix.count();
+        let tail_start = items
+            .iter()
+            .position(|item| item.span.start > range.end)
+            .unwrap_or(items.len());
+        let mut tail = items.split_off(tail_start.max(prefix));
+        for item in tail.iter_mut() {
+            item.shift(delta);
+        }
+        let removed = items.split_off(prefix);
+        let region_start = items.last().map_or(0, |item| item.span.end);
+
+        let mut splitter = Splitter::default();
+        let mut synced = tail.len();
+        let lexer = Token::lexer(&self.source[region_start..]).spanned();
+        for (token, span) in lexer {
+            let span = span.start + region_start..span.end + region_start;
+            if splitter.is_idle() && span.start >= edited_end {
+                let next = tail.iter().position(|item| item.span.start >= span.start);
+                if let Some(i) = next.filter(|&i| tail[i].span.start == span.start) {
+                    synced = i;
+                    break;
+                }
+            }
+            splitter.push(&token, span);
+        }
+        let raws = splitter.finish(self.source.len());
+        let dropped = removed.iter().chain(&tail[..synced]);
+        let touches_pattern = dropped
+            .map(|item| item.kind)
+            .chain(raws.iter().map(|raw| raw.kind))
+            .any(|kind| kind != ItemKind::Category);
+        if touches_pattern {
+            return self.parse_whole();
+        }
+        let patterns = collect_patterns(&items);
+        self.reparsed = raws.len();
+        for raw in raws {
+            items.push(parse_item(&self.source, raw, &patterns));
+        }
+        items.extend(tail.drain(synced..));
+        self.assemble(items);
+    }
+    fn parse_whole(&mut self) {
+        let mut splitter = Splitter::default();
+        for (token, span) in Token::lexer(&self.source).spanned() {
+            splitter.push(&token, span);
+        }
+        let raws = splitter.finish(self.source.len());
+        self.reparsed = raws.len();
+        if raws.iter().all(|raw| raw.kind != ItemKind::Other) {
+            let mut items = Vec::new();
+            let mut patterns = HashMap::new();
+            for raw in raws {
+                let item = parse_item(&self.source, raw, &patterns);
+                if let Some((name, regex)) = &item.pattern {
+                    patterns.insert(name.clone(), regex.clone());
+                }
+                items.push(item);
+            }
+            // 有錯誤的模式會影響錯誤恢復的方式，交由 parse_all_errors 處理
+            let valid = items
+                .iter()
+                .all(|item| item.kind != ItemKind::Pattern || item.pattern.is_some());
+            if valid {
+                return self.assemble(items);
+            }
+        }
+        let (force, errors) = parse_all_errors(&self.source);
+        self.items = None;
+        self.force = force;
+        self.errors = errors;
+    }
+    // 依序合併各項目的結果，與 parse_all_errors 的順序相同
+    fn assemble(&mut self, items: Vec<Item>) {
+        let mut categories = Categories::new();
+        let mut lex_errors = Vec::new();
+        let mut errors = Vec::new();
+        for item in &items {
+            lex_errors.extend(item.lex_errors.iter().cloned());
+            errors.extend(item.errors.iter().cloned());
+            if let Some(category) = &item.category {
+                if categories.contains_key(&category.name) {
+                    errors.push(ForceError::DuplicateCategory {
+                        category: category.name.clone(),
+                        span: item.name_span.clone(),
+                    });
+                } else {
+                    categories.insert(category.name.clone(), category.clone());
+                }
+            }
+        }
+        if let Err(error) = link(&mut categories) {
+            errors.push(error);
+        }
+        lex_errors.append(&mut errors);
+        let patterns = collect_patterns(&items)
+            .into_iter()
+            .map(|(name, regex)| (name, regex.as_str().to_owned()))
+            .collect();
+        self.force = Force {
+            categories,
+            patterns,
+        };
+        self.errors = lex_errors;
+        self.items = Some(items);
+    }
+}
+
+fn collect_patterns(items: &[Item]) -> HashMap<String, Regex> {
+    items
+        .iter()
+        .filter_map(|item| item.pattern.clone())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    fn assert_same(parser: &IncrementalParser) {
+        let (force, errors) = parse_all_errors(parser.source());
+        assert_eq!(parser.force().categories, force.categories);
+        assert_eq!(parser.force().patterns, force.patterns);
+        let describe = |errors: &[ForceError]| -> Vec<String> {
+            errors
+                .iter()
+                .map(|error| format!("{} {:?}", error, error.span()))
+                .collect()
+        };
+        assert_eq!(describe(parser.errors()), describe(&errors));
+    }
+    fn edit(parser: &mut IncrementalParser, old: &str, new: &str) {
+        let start = parser.source().find(old).unwrap();
+        parser.edit(start..start + old.len(), new);
+        assert_same(parser);
+    }
+    #[test]
+    fn test_incremental() {
+        let mut parser = IncrementalParser::new(
+            "模式 信箱 = /.+@.+/\n/// 文章\n文章 {單行 標題}\n留言 : 文章 {文本 信箱 聯絡}\n問卦 {數字 讚數}",
+        );
+        assert_same(&parser);
+        assert_eq!(parser.reparsed(), 4);
+
+        edit(&mut parser, "讚數", "噓數");
+        assert_eq!(parser.reparsed(), 1);
+        edit(&mut parser, "單行 標題", "單行 標題 數字 點閱");
+        assert_eq!(parser.reparsed(), 1);
+        assert_eq!(parser.force().categories["留言"].fields.len(), 3);
+
+        // 打字途中的錯誤只影響該分類
+        edit(&mut parser, "數字 噓數", "數字 噓");
+        edit(&mut parser, "數字 噓", "數字");
+        assert_eq!(parser.reparsed(), 1);
+        assert_eq!(parser.errors().len(), 1);
+        assert!(!parser.force().categories.contains_key("問卦"));
+        edit(&mut parser, "{數字}", "{數字 噓數}");
+
+        // 合併兩個分類、拆開一個分類
+        edit(&mut parser, "}\n問卦 {", "");
+        edit(&mut parser, "數字 噓數", "} 問卦 { 數字 噓數");
+        assert_eq!(parser.errors().len(), 0);
+
+        // 未閉合的區塊註解吞掉其後所有項目
+        edit(&mut parser, "/// 文章\n", "/* ");
+        assert_eq!(parser.force().categories.len(), 0);
+        edit(&mut parser, "/* ", "");
+
+        edit(&mut parser, "問卦", "文章");
+        assert_eq!(parser.errors().len(), 1);
+        edit(&mut parser, "信箱 = /.+@.+/", "信箱 = /.+/");
+        assert_eq!(parser.reparsed(), 4);
+    }
+    #[test]
+    fn test_fallback() {
+        let mut parser = IncrementalParser::new("命名空間 論壇 { 文章 {單行 標題} }");
+        assert_same(&parser);
+        edit(&mut parser, "標題", "內文");
+        assert!(parser.force().categories.contains_key("論壇.文章"));
+        edit(&mut parser, "命名空間 論壇 { ", "");
+        edit(&mut parser, "} }", "}");
+        edit(&mut parser, "內文", "標題");
+        assert_eq!(parser.reparsed(), 1);
+    }
+}
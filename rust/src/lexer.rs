@@ -1,14 +1,24 @@
+use crate::prelude::*;
 use logos::{Lexer, Logos, Span};
 
 // 先藉助 logos 函式庫自動生成 LogoToken ，再將之轉成自定義的 Token
+// 帶值的 token 借用原始碼中的片段，只有含跳脫的字串與識別子須另外配置，建出 AST 時才轉成 String
 
 // logos 詞法分析庫的 token
 #[derive(Logos, Debug, PartialEq, Clone)]
-pub enum Token {
+pub enum Token<'src> {
     #[regex(r"[ \t\n]+", logos::skip)]
+    // 註解：// 至行尾，或 /* ... */ ，不可巢狀
+    #[regex(r"//[^\n]*", logos::skip)]
+    #[regex(r"/\*([^*]|\*+[^*/])*\*+/", logos::skip, priority = 10)]
     #[error]
     Error,
 
+    // 文件註解：/// 至行尾，附加在其後的分類或域上
+    // 四條以上的斜線是一般註解，由較長的 // 註解比對
+    #[regex(r"///([^/\n][^\n]*)?", extract_doc, priority = 10)]
+    DocComment(Cow<'src, str>),
+
     // 特殊符號
     #[token("{")]
     LeftCurlyBrace,
@@ -24,6 +34,35 @@ pub enum Token {
     Sharp,
     #[token(":")]
     Colon,
+    #[token("@")]
+    At,
+    #[token("<")]
+    LessThan,
+    #[token(">")]
+    GreaterThan,
+    #[token("(")]
+    LeftParenthesis,
+    #[token(")")]
+    RightParenthesis,
+    #[token("..")]
+    DotDot,
+    #[token(".")]
+    Dot,
+    #[token("?")]
+    Question,
+    #[token("=")]
+    Equal,
+    // 計算域的運算子， * 與鍵結共用 Star
+    #[token("+")]
+    Plus,
+    #[token("-")]
+    Minus,
+    #[token("/")]
+    Slash,
+
+    // 整數
+    #[regex("-?[0-9]+", |lex| lex.slice().parse(), priority = 2)]
+    Integer(i64),
 
     // 域型別
     #[token("單行")]
@@ -36,37 +75,272 @@ pub enum Token {
     Bond,
     #[token("帶籤鍵結")]
     TaggedBond,
+    #[token("列表")]
+    Array,
+    #[token("列舉")]
+    Enum,
+    #[token("布林")]
+    Bool,
+    #[token("日期時間")]
+    DateTime,
+
+    // 布林值
+    #[token("真")]
+    True,
+    #[token("假")]
+    False,
+
+    // 具名的正則表達式
+    #[token("模式")]
+    Pattern,
+    #[token("匯入")]
+    Import,
+    #[token("命名空間")]
+    Namespace,
 
-    // 正則表達式
-    #[regex("/[^/]+/", extract_regex)]
-    Regex(String),
+    // 正則表達式，不可以空白開頭，以便與除號區分，如 讚數 / 2
+    #[regex(r"/[^/\s][^/]*/", extract_regex)]
+    Regex(Cow<'src, str>),
+
+    // 字串，支援 \" \\ \n 三種跳脫
+    #[regex(r#""([^"\\]|\\.)*""#, extract_string)]
+    StringLiteral(Cow<'src, str>),
 
     // 鍵結的符號
     #[token("*")]
     Star,
+    #[token("except")]
+    Except,
     #[token("輸能")]
     Transfuse,
+    #[token("單一")]
+    One,
+    #[token("多個")]
+    Many,
+    #[token("反向")]
+    Inverse,
+    #[token("計算")]
+    Computed,
 
-    // 識別子，只能是中文、英文、數字、底線
-    // TODO: 增強識別子的限制
-    #[regex("[^\\s/\\[\\]\\}\\{,#:]+", get_string)]
-    Identifier(String),
+    // 識別子，依 UAX#31 ，以 XID_Start 開頭、其後為 XID_Continue
+    // 另外允許以底線或數字開頭，如 2020新聞
+    // 以 ` 括起者可含任意字元，支援 \` \\ 兩種跳脫
+    #[regex(r"[\p{XID_Start}_0-9]\p{XID_Continue}*", |lex| Cow::Borrowed(lex.slice()))]
+    #[regex(r"`([^`\\\n]|\\.)+`", extract_quoted)]
+    Identifier(Cow<'src, str>),
 
     End,
 }
 
-fn get_string(lex: &mut Lexer<Token>) -> String {
-    lex.slice().to_string()
+impl<'src> Token<'src> {
+    // 錯誤訊息中的寫法，符號與關鍵字以「」括起，帶值的 token 只描述其種類
+    pub fn describe(&self) -> String {
+        let text = match self {
+            Token::Error => return "無法辨識的字元".to_owned(),
+            Token::DocComment(_) => return "文件註解".to_owned(),
+            Token::Integer(_) => return "整數".to_owned(),
+            Token::Regex(_) => return "正則表達式".to_owned(),
+            Token::StringLiteral(_) => return "字串".to_owned(),
+            Token::Identifier(_) => return "識別子".to_owned(),
+            Token::End => return "檔案結尾".to_owned(),
+            Token::LeftCurlyBrace => "{",
+            Token::RightCurlyBrace => "}",
+            Token::LeftSquareBracket => "[",
+            Token::RightSquareBracket => "]",
+            Token::Comma => ",",
+            Token::Sharp => "#",
+            Token::Colon => ":",
+            Token::At => "@",
+            Token::LessThan => "<",
+            Token::GreaterThan => ">",
+            Token::LeftParenthesis => "(",
+            Token::RightParenthesis => ")",
+            Token::DotDot => "..",
+            Token::Dot => ".",
+            Token::Question => "?",
+            Token::Equal => "=",
+            Token::Star => "*",
+            Token::Plus => "+",
+            Token::Minus => "-",
+            Token::Slash => "/",
+            keyword => return format!("「{}」", keyword.keyword().unwrap()),
+        };
+        format!("「{}」", text)
+    }
+    // 關鍵字的原文，非關鍵字為 None
+    pub fn keyword(&self) -> Option<&'static str> {
+        let keyword = match self {
+            Token::OneLine => "單行",
+            Token::Text => "文本",
+            Token::Number => "數字",
+            Token::Bond => "鍵結",
+            Token::TaggedBond => "帶籤鍵結",
+            Token::Array => "列表",
+            Token::Enum => "列舉",
+            Token::Bool => "布林",
+            Token::DateTime => "日期時間",
+            Token::True => "真",
+            Token::False => "假",
+            Token::Pattern => "模式",
+            Token::Import => "匯入",
+            Token::Namespace => "命名空間",
+            Token::Transfuse => "輸能",
+            Token::One => "單一",
+            Token::Many => "多個",
+            Token::Inverse => "反向",
+            Token::Computed => "計算",
+            Token::Except => "except",
+            _ => return None,
+        };
+        Some(keyword)
+    }
+    // 不再借用原始碼，供錯誤與具體語法樹保存
+    pub fn into_owned(self) -> Token<'static> {
+        match self {
+            Token::DocComment(s) => Token::DocComment(Cow::Owned(s.into_owned())),
+            Token::Regex(s) => Token::Regex(Cow::Owned(s.into_owned())),
+            Token::StringLiteral(s) => Token::StringLiteral(Cow::Owned(s.into_owned())),
+            Token::Identifier(s) => Token::Identifier(Cow::Owned(s.into_owned())),
+            Token::Integer(n) => Token::Integer(n),
+            Token::Error => Token::Error,
+            Token::LeftCurlyBrace => Token::LeftCurlyBrace,
+            Token::RightCurlyBrace => Token::RightCurlyBrace,
+            Token::LeftSquareBracket => Token::LeftSquareBracket,
+            Token::RightSquareBracket => Token::RightSquareBracket,
+            Token::Comma => Token::Comma,
+            Token::Sharp => Token::Sharp,
+            Token::Colon => Token::Colon,
+            Token::At => Token::At,
+            Token::LessThan => Token::LessThan,
+            Token::GreaterThan => Token::GreaterThan,
+            Token::LeftParenthesis => Token::LeftParenthesis,
+            Token::RightParenthesis => Token::RightParenthesis,
+            Token::DotDot => Token::DotDot,
+            Token::Dot => Token::Dot,
+            Token::Question => Token::Question,
+            Token::Equal => Token::Equal,
+            Token::Plus => Token::Plus,
+            Token::Minus => Token::Minus,
+            Token::Slash => Token::Slash,
+            Token::OneLine => Token::OneLine,
+            Token::Text => Token::Text,
+            Token::Number => Token::Number,
+            Token::Bond => Token::Bond,
+            Token::TaggedBond => Token::TaggedBond,
+            Token::Array => Token::Array,
+            Token::Enum => Token::Enum,
+            Token::Bool => Token::Bool,
+            Token::DateTime => Token::DateTime,
+            Token::True => Token::True,
+            Token::False => Token::False,
+            Token::Pattern => Token::Pattern,
+            Token::Import => Token::Import,
+            Token::Namespace => Token::Namespace,
+            Token::Star => Token::Star,
+            Token::Except => Token::Except,
+            Token::Transfuse => Token::Transfuse,
+            Token::One => Token::One,
+            Token::Many => Token::Many,
+            Token::Inverse => Token::Inverse,
+            Token::Computed => Token::Computed,
+            Token::End => Token::End,
+        }
+    }
 }
 
-fn extract_regex(lex: &mut Lexer<Token>) -> String {
+fn extract_regex<'s>(lex: &mut Lexer<'s, Token<'s>>) -> Cow<'s, str> {
     let s = lex.slice();
-    s[1..(s.len() - 1)].to_string()
+    Cow::Borrowed(&s[1..(s.len() - 1)])
+}
+
+fn extract_doc<'s>(lex: &mut Lexer<'s, Token<'s>>) -> Cow<'s, str> {
+    Cow::Borrowed(lex.slice()[3..].trim())
+}
+
+// 去掉頭尾的引號並處理跳脫，沒有跳脫時直接借用原始碼
+fn unescape<'s>(s: &'s str, escape: impl Fn(char) -> Option<char>) -> Option<Cow<'s, str>> {
+    let s = &s[1..(s.len() - 1)];
+    if !s.contains('\\') {
+        return Some(Cow::Borrowed(s));
+    }
+    let mut ret = String::new();
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        if c == '\\' {
+            ret.push(escape(chars.next()?)?);
+        } else {
+            ret.push(c);
+        }
+    }
+    Some(Cow::Owned(ret))
+}
+
+fn extract_quoted<'s>(lex: &mut Lexer<'s, Token<'s>>) -> Option<Cow<'s, str>> {
+    unescape(lex.slice(), |c| match c {
+        '`' | '\\' => Some(c),
+        _ => None,
+    })
+}
+
+fn extract_string<'s>(lex: &mut Lexer<'s, Token<'s>>) -> Option<Cow<'s, str>> {
+    unescape(lex.slice(), |c| match c {
+        'n' => Some('\n'),
+        '"' | '\\' => Some(c),
+        _ => None,
+    })
+}
+
+// 原始碼中某個位元組偏移所在的行列，皆從 1 起算，列以字元計
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Location {
+    pub offset: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Location {
+    pub fn new(source: &str, offset: usize) -> Location {
+        let before = &source[..offset];
+        let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+        Location {
+            offset,
+            line: before.matches('\n').count() + 1,
+            column: before[line_start..].chars().count() + 1,
+        }
+    }
+}
+
+// 能否不加 ` 直接寫成識別子，命名空間中的完整名稱逐段檢查
+pub fn is_plain_identifier(name: &str) -> bool {
+    name.split('.').all(|segment| {
+        let mut lexer = Token::lexer(segment);
+        match (lexer.next(), lexer.next()) {
+            (Some(Token::Identifier(s)), None) => s == segment,
+            _ => false,
+        }
+    })
+}
+
+// 格式化輸出用，必要時以 ` 括起
+pub fn quote_identifier(name: &str) -> String {
+    if is_plain_identifier(name) {
+        return name.to_owned();
+    }
+    let mut ret = String::from("`");
+    for c in name.chars() {
+        match c {
+            '`' => ret.push_str("\\`"),
+            '\\' => ret.push_str("\\\\"),
+            c => ret.push(c),
+        }
+    }
+    ret.push('`');
+    ret
 }
 
-pub fn lexer(s: &str) -> Vec<(Token, Span)> {
+pub fn lexer(s: &str) -> Vec<(Token<'_>, Span)> {
     let lex = Token::lexer(s);
-    let mut ret: Vec<(Token, Span)> = lex.spanned().collect();
+    let mut ret: Vec<(Token<'_>, Span)> = lex.spanned().collect();
     ret.push((Token::End, s.len()..s.len()));
     ret
 }
@@ -78,7 +352,7 @@ mod tests {
 
     #[test]
     fn test_special_character() {
-        let mut lexer = Token::lexer("{}[],#:");
+        let mut lexer = Token::lexer("{}[],#:@<>()..?=.");
         assert_eq!(lexer.next(), Some(Token::LeftCurlyBrace));
         assert_eq!(lexer.next(), Some(Token::RightCurlyBrace));
         assert_eq!(lexer.next(), Some(Token::LeftSquareBracket));
@@ -86,17 +360,41 @@ mod tests {
         assert_eq!(lexer.next(), Some(Token::Comma));
         assert_eq!(lexer.next(), Some(Token::Sharp));
         assert_eq!(lexer.next(), Some(Token::Colon));
+        assert_eq!(lexer.next(), Some(Token::At));
+        assert_eq!(lexer.next(), Some(Token::LessThan));
+        assert_eq!(lexer.next(), Some(Token::GreaterThan));
+        assert_eq!(lexer.next(), Some(Token::LeftParenthesis));
+        assert_eq!(lexer.next(), Some(Token::RightParenthesis));
+        assert_eq!(lexer.next(), Some(Token::DotDot));
+        assert_eq!(lexer.next(), Some(Token::Question));
+        assert_eq!(lexer.next(), Some(Token::Equal));
+        assert_eq!(lexer.next(), Some(Token::Dot));
         assert_eq!(lexer.next(), None);
     }
     #[test]
     fn test_keyword() {
-        let mut lexer = Token::lexer("單行 文本 數字 鍵結 帶籤鍵結 輸能");
+        let mut lexer = Token::lexer(
+            "單行 文本 數字 鍵結 帶籤鍵結 列表 列舉 布林 日期時間 真 假 輸能 單一 多個 反向 計算 模式 匯入 命名空間",
+        );
         assert_eq!(lexer.next(), Some(Token::OneLine));
         assert_eq!(lexer.next(), Some(Token::Text));
         assert_eq!(lexer.next(), Some(Token::Number));
         assert_eq!(lexer.next(), Some(Token::Bond));
         assert_eq!(lexer.next(), Some(Token::TaggedBond));
+        assert_eq!(lexer.next(), Some(Token::Array));
+        assert_eq!(lexer.next(), Some(Token::Enum));
+        assert_eq!(lexer.next(), Some(Token::Bool));
+        assert_eq!(lexer.next(), Some(Token::DateTime));
+        assert_eq!(lexer.next(), Some(Token::True));
+        assert_eq!(lexer.next(), Some(Token::False));
         assert_eq!(lexer.next(), Some(Token::Transfuse));
+        assert_eq!(lexer.next(), Some(Token::One));
+        assert_eq!(lexer.next(), Some(Token::Many));
+        assert_eq!(lexer.next(), Some(Token::Inverse));
+        assert_eq!(lexer.next(), Some(Token::Computed));
+        assert_eq!(lexer.next(), Some(Token::Pattern));
+        assert_eq!(lexer.next(), Some(Token::Import));
+        assert_eq!(lexer.next(), Some(Token::Namespace));
         assert_eq!(lexer.next(), None);
     }
     #[test]
@@ -104,20 +402,133 @@ mod tests {
         let mut lexer = Token::lexer("單行文本數字鍵結帶籤鍵結輸能");
         assert_eq!(
             lexer.next(),
-            Some(Token::Identifier("單行文本數字鍵結帶籤鍵結輸能".to_owned()))
+            Some(Token::Identifier("單行文本數字鍵結帶籤鍵結輸能".into()))
         );
         lexer = Token::lexer("Gossip");
-        assert_eq!(lexer.next(), Some(Token::Identifier("Gossip".to_owned())));
+        assert_eq!(lexer.next(), Some(Token::Identifier("Gossip".into())));
         lexer = Token::lexer("八卦");
-        assert_eq!(lexer.next(), Some(Token::Identifier("八卦".to_owned())));
+        assert_eq!(lexer.next(), Some(Token::Identifier("八卦".into())));
         lexer = Token::lexer("play_boy");
-        assert_eq!(lexer.next(), Some(Token::Identifier("play_boy".to_owned())));
+        assert_eq!(lexer.next(), Some(Token::Identifier("play_boy".into())));
         lexer = Token::lexer("花花公子");
-        assert_eq!(lexer.next(), Some(Token::Identifier("花花公子".to_owned())));
+        assert_eq!(lexer.next(), Some(Token::Identifier("花花公子".into())));
+    }
+    #[test]
+    fn test_unicode_identifier() {
+        let mut lexer = Token::lexer("iPhone手機 _草稿2 Café 版本ⅱ 😀 play-boy");
+        assert_eq!(lexer.next(), Some(Token::Identifier("iPhone手機".into())));
+        assert_eq!(lexer.next(), Some(Token::Identifier("_草稿2".into())));
+        assert_eq!(lexer.next(), Some(Token::Identifier("Café".into())));
+        assert_eq!(lexer.next(), Some(Token::Identifier("版本ⅱ".into())));
+        assert_eq!(lexer.next(), Some(Token::Error));
+        assert_eq!(lexer.next(), Some(Token::Identifier("play".into())));
+        assert_eq!(lexer.next(), Some(Token::Minus));
+        assert_eq!(lexer.next(), Some(Token::Identifier("boy".into())));
+
+        let mut lexer = Token::lexer(r"`發布 日期` `單行` `a\`b\\` `\q`");
+        assert_eq!(lexer.next(), Some(Token::Identifier("發布 日期".into())));
+        assert_eq!(lexer.next(), Some(Token::Identifier("單行".into())));
+        assert_eq!(lexer.next(), Some(Token::Identifier("a`b\\".into())));
+        assert_eq!(lexer.next(), Some(Token::Error));
+        assert_eq!(Token::lexer("``").next(), Some(Token::Error));
+
+        assert!(is_plain_identifier("論壇.文章"));
+        assert_eq!(quote_identifier("論壇.文章"), "論壇.文章");
+        assert_eq!(quote_identifier("單行"), "`單行`");
+        assert_eq!(quote_identifier("發布 日期"), "`發布 日期`");
+        assert_eq!(quote_identifier("a`b"), "`a\\`b`");
+    }
+    #[test]
+    fn test_borrowed() {
+        let source = r#"新聞 "記者" "說\"好\"" /[a-z]+/ `發布 日期` `a\`b`"#;
+        let borrowed: Vec<bool> = Token::lexer(source)
+            .map(|token| match token {
+                Token::Identifier(s) | Token::StringLiteral(s) | Token::Regex(s) => {
+                    matches!(s, Cow::Borrowed(_))
+                }
+                _ => panic!("{:?}", token),
+            })
+            .collect();
+        // 只有含跳脫者須另外配置
+        assert_eq!(borrowed, vec![true, true, false, true, true, false]);
+        let token = Token::lexer(source).next().unwrap().into_owned();
+        assert_eq!(token, Token::Identifier("新聞".into()));
+    }
+    #[test]
+    fn test_location() {
+        let source = "新聞 {\n    單行 記者\n}";
+        let offset = source.find("記者").unwrap();
+        assert_eq!(
+            Location::new(source, offset),
+            Location {
+                offset,
+                line: 2,
+                column: 8
+            }
+        );
+        assert_eq!(Location::new(source, 0).line, 1);
+    }
+    #[test]
+    fn test_integer() {
+        let mut lexer = Token::lexer("(1..-20) 2020新聞");
+        assert_eq!(lexer.next(), Some(Token::LeftParenthesis));
+        assert_eq!(lexer.next(), Some(Token::Integer(1)));
+        assert_eq!(lexer.next(), Some(Token::DotDot));
+        assert_eq!(lexer.next(), Some(Token::Integer(-20)));
+        assert_eq!(lexer.next(), Some(Token::RightParenthesis));
+        assert_eq!(lexer.next(), Some(Token::Identifier("2020新聞".into())));
     }
     #[test]
     fn test_regex() {
         let mut lexer = Token::lexer("/[ab]+d?/");
-        assert_eq!(lexer.next(), Some(Token::Regex("[ab]+d?".to_owned())));
+        assert_eq!(lexer.next(), Some(Token::Regex("[ab]+d?".into())));
+        // 以空白開頭者為除號
+        let mut lexer = Token::lexer("讚數 / 2 / 噓數");
+        lexer.next();
+        assert_eq!(lexer.next(), Some(Token::Slash));
+        assert_eq!(lexer.next(), Some(Token::Integer(2)));
+        assert_eq!(lexer.next(), Some(Token::Slash));
+    }
+    #[test]
+    fn test_string() {
+        let mut lexer = Token::lexer(r#""未命名" "說\"好\"\n" "\q""#);
+        assert_eq!(lexer.next(), Some(Token::StringLiteral("未命名".into())));
+        assert_eq!(
+            lexer.next(),
+            Some(Token::StringLiteral("說\"好\"\n".into()))
+        );
+        assert_eq!(lexer.next(), Some(Token::Error));
+    }
+    #[test]
+    fn test_comment() {
+        let mut lexer = Token::lexer("// 新聞分類\n單行 /* 記者\n* 姓名 */ 記者 // 結尾");
+        assert_eq!(lexer.next(), Some(Token::OneLine));
+        assert_eq!(lexer.next(), Some(Token::Identifier("記者".into())));
+        assert_eq!(lexer.next(), None);
+        let mut lexer = Token::lexer("文本/a*/ /* 註解 */ 內文");
+        assert_eq!(lexer.next(), Some(Token::Text));
+        assert_eq!(lexer.next(), Some(Token::Regex("a*".into())));
+        assert_eq!(lexer.next(), Some(Token::Identifier("內文".into())));
+        assert_eq!(lexer.next(), None);
+    }
+    #[test]
+    fn test_doc_comment() {
+        let mut lexer = Token::lexer("/// 新聞分類\n新聞 //// 四條斜線\n///\n////");
+        assert_eq!(lexer.next(), Some(Token::DocComment("新聞分類".into())));
+        assert_eq!(lexer.next(), Some(Token::Identifier("新聞".into())));
+        assert_eq!(lexer.next(), Some(Token::DocComment("".into())));
+        assert_eq!(lexer.next(), None);
+    }
+    #[test]
+    fn test_describe() {
+        for keyword in &["單行", "帶籤鍵結", "日期時間", "真", "命名空間", "反向"]
+        {
+            let token = Token::lexer(keyword).next().unwrap();
+            assert_eq!(token.keyword(), Some(*keyword));
+            assert_eq!(token.describe(), format!("「{}」", keyword));
+        }
+        assert_eq!(Token::DotDot.describe(), "「..」");
+        assert_eq!(Token::Identifier("新聞".into()).describe(), "識別子");
+        assert_eq!(Token::Identifier("新聞".into()).keyword(), None);
     }
 }
@@ -1,7 +1,75 @@
+// 關閉 std 時只剩詞法分析、語法分析與實例驗證，其餘模組皆需要 std
+#![cfg_attr(not(feature = "std"), no_std)]
+
+#[cfg(not(feature = "std"))]
+#[macro_use]
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std as alloc;
+
+#[cfg(feature = "std")]
+pub mod analysis;
+#[cfg(feature = "arbitrary")]
+pub mod arbitrary;
+#[cfg(feature = "std")]
+pub mod ast;
+#[cfg(feature = "std")]
+pub mod binary;
+#[cfg(feature = "std")]
+pub mod compat;
+#[cfg(feature = "std")]
+pub mod compiled;
+pub mod cst;
 pub mod defs;
+#[cfg(feature = "std")]
+pub mod diagnostics;
+#[cfg(feature = "std")]
+pub mod diff;
+pub mod evaluate;
+#[cfg(feature = "std")]
+pub mod export;
+#[cfg(feature = "std")]
+pub mod format;
+pub mod import;
+#[cfg(feature = "std")]
+pub mod incremental;
 pub mod lexer;
+#[cfg(feature = "std")]
+pub mod lint;
+#[cfg(feature = "lsp")]
+pub mod lsp;
+pub mod merge;
 pub mod parser;
+#[cfg(feature = "std")]
+pub mod repl;
+pub mod semantic;
 pub mod validate;
+pub mod visit;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+// std 的 prelude 中由 alloc 提供的名稱，no_std 時須自行引入
+// 無 std 時以 BTreeMap 代替 HashMap
+mod prelude {
+    pub use alloc::borrow::{Cow, ToOwned};
+    pub use alloc::boxed::Box;
+    #[cfg(not(feature = "std"))]
+    pub use alloc::collections::BTreeMap as Map;
+    pub use alloc::string::{String, ToString};
+    pub use alloc::vec::Vec;
+    #[cfg(feature = "std")]
+    pub use std::collections::HashMap as Map;
+}
 
+#[cfg(feature = "std")]
+pub use crate::compiled::CompiledForce;
 pub use crate::defs::*;
-pub use crate::parser::{parse, parse_category};
+pub use crate::evaluate::{evaluate, fill_computed, EvaluateError};
+#[cfg(feature = "std")]
+pub use crate::import::FileLoader;
+pub use crate::import::{parse_file, Loader, MemoryLoader};
+pub use crate::merge::MergeStrategy;
+pub use crate::parser::{parse, parse_all_errors, parse_category, parse_partial, PartialForce};
+pub use crate::validate::{
+    fill_defaults, validate_collection, validate_instance, CollectionError, InstanceError,
+};
@@ -0,0 +1,419 @@
+// 綱要的風格檢查：合法但可疑的寫法，以警告而非錯誤回報
+// 規則實作 Rule 並註冊到 Linter ，使用此函式庫的程式可加入自訂規則
+use crate::analysis::Analysis;
+use crate::*;
+use logos::Span;
+use regex::Regex;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Warning {
+    pub rule: &'static str,
+    pub category: String,
+    pub field: Option<String>,
+    pub message: String,
+    pub span: Option<Span>, // 分類名或域名的位置，僅 lint_source 會填入
+}
+
+impl Warning {
+    pub fn new(
+        rule: &'static str,
+        category: &str,
+        field: Option<&str>,
+        message: String,
+    ) -> Warning {
+        Warning {
+            rule,
+            category: category.to_owned(),
+            field: field.map(|field| field.to_owned()),
+            message,
+            span: None,
+        }
+    }
+}
+
+pub trait Rule {
+    // 規則名，供 Linter::disable 使用
+    fn name(&self) -> &'static str;
+    fn check(&self, force: &Force) -> Vec<Warning>;
+}
+
+// 依名稱排序的分類及其域
+fn each_field(force: &Force) -> Vec<(&Category, &Field)> {
+    let mut ret = Vec::new();
+    for name in force.category_names() {
+        let category = &force.categories[name];
+        ret.extend(category.fields.iter().map(|field| (category, field)));
+    }
+    ret
+}
+
+pub struct EmptyCategory;
+
+impl Rule for EmptyCategory {
+    fn name(&self) -> &'static str {
+        "empty-category"
+    }
+    fn check(&self, force: &Force) -> Vec<Warning> {
+        force
+            .category_names()
+            .into_iter()
+            .filter(|name| force.categories[*name].fields.is_empty())
+            .map(|name| Warning::new(self.name(), name, None, format!("分類 {} 沒有任何域", name)))
+            .collect()
+    }
+}
+
+// 鍵結到的分類沒有任何域鍵結回來，且未宣告反向連結
+pub struct OneWayBond;
+
+impl Rule for OneWayBond {
+    fn name(&self) -> &'static str {
+        "one-way-bond"
+    }
+    fn check(&self, force: &Force) -> Vec<Warning> {
+        let mut warnings = Vec::new();
+        for (category, field) in each_field(force) {
+            let has_inverse = field
+                .datatype
+                .relation()
+                .is_some_and(|relation| relation.inverse.is_some());
+            let choices = match field.datatype.bondee() {
+                Some(Bondee::Choices(choices)) if !has_inverse => choices,
+                _ => continue,
+            };
+            for target in choices.iter().filter(|target| **target != category.name) {
+                let links_back = force.categories.get(target).is_none_or(|target| {
+                    target.fields.iter().any(|f| {
+                        f.datatype
+                            .bondee()
+                            .is_some_and(|bondee| bondee.accepts(&category.name))
+                    })
+                });
+                if !links_back {
+                    let message = format!(
+                        "域 {}.{} 鍵結到 {}，但 {} 沒有鍵結回來，可考慮宣告反向連結",
+                        category.name, field.name, target, target
+                    );
+                    warnings.push(Warning::new(
+                        self.name(),
+                        &category.name,
+                        Some(&field.name),
+                        message,
+                    ));
+                }
+            }
+        }
+        warnings
+    }
+}
+
+// 與其他分類沒有任何鍵結或繼承關係的分類
+pub struct IsolatedCategory;
+
+impl Rule for IsolatedCategory {
+    fn name(&self) -> &'static str {
+        "isolated-category"
+    }
+    fn check(&self, force: &Force) -> Vec<Warning> {
+        if force.categories.len() < 2 {
+            return Vec::new();
+        }
+        let connected = |a: &Category, b: &Category| {
+            a.parents.contains(&b.name)
+                || a.fields.iter().any(|field| {
+                    field
+                        .datatype
+                        .bondee()
+                        .is_some_and(|bondee| bondee.accepts(&b.name))
+                })
+        };
+        force
+            .category_names()
+            .into_iter()
+            .filter(|name| {
+                let category = &force.categories[*name];
+                force.categories.values().all(|other| {
+                    other.name == category.name
+                        || !(connected(category, other) || connected(other, category))
+                })
+            })
+            .map(|name| {
+                let message = format!("分類 {} 與其他分類沒有任何鍵結或繼承關係", name);
+                Warning::new(self.name(), name, None, message)
+            })
+            .collect()
+    }
+}
+
+pub struct BroadBond;
+
+impl Rule for BroadBond {
+    fn name(&self) -> &'static str {
+        "broad-bond"
+    }
+    fn check(&self, force: &Force) -> Vec<Warning> {
+        each_field(force)
+            .into_iter()
+            .filter(|(_, field)| field.datatype.bondee() == Some(&Bondee::All))
+            .map(|(category, field)| {
+                let message = format!(
+                    "域 {}.{} 可鍵結到任意分類，可考慮列出對象或以 except 排除",
+                    category.name, field.name
+                );
+                Warning::new(self.name(), &category.name, Some(&field.name), message)
+            })
+            .collect()
+    }
+}
+
+// 是否有內含無上限量詞的群組本身又被無上限地重複，如 (a+)+ 、 (.*)*
+// 此類寫法在回溯式的引擎（如 JavaScript）中可能耗費指數時間
+pub fn has_nested_quantifier(regex: &str) -> bool {
+    let chars: Vec<char> = regex.chars().collect();
+    let unbounded = |i: usize| match chars.get(i) {
+        Some('*') | Some('+') => true,
+        Some('{') => {
+            let end = chars[i..].iter().position(|c| *c == '}');
+            end.is_some_and(|end| chars[i..i + end].ends_with(&[',']))
+        }
+        _ => false,
+    };
+    let mut groups: Vec<bool> = Vec::new(); // 各層尚未結束的群組內是否有無上限量詞
+                                            // 跳脫字元與字元類別各算一個單位，與一般字元相同
+    let mark = |groups: &mut Vec<bool>, i: usize| {
+        if unbounded(i + 1) {
+            if let Some(group) = groups.last_mut() {
+                *group = true;
+            }
+        }
+    };
+    let mut in_class = false;
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => {
+                i += 1;
+                if !in_class {
+                    mark(&mut groups, i);
+                }
+            }
+            ']' if in_class => {
+                in_class = false;
+                mark(&mut groups, i);
+            }
+            _ if in_class => {}
+            '[' => in_class = true,
+            '(' => groups.push(false),
+            ')' => {
+                let inner = groups.pop().unwrap_or(false);
+                if inner && unbounded(i + 1) {
+                    return true;
+                }
+                if let Some(outer) = groups.last_mut() {
+                    *outer |= inner || unbounded(i + 1);
+                }
+            }
+            _ => mark(&mut groups, i),
+        }
+        i += 1;
+    }
+    false
+}
+
+fn field_regex(datatype: &DataType) -> Option<&Regex> {
+    match datatype {
+        DataType::Text(regex) => regex.as_ref(),
+        DataType::Array { inner, .. } => field_regex(inner),
+        _ => None,
+    }
+}
+
+pub struct CatastrophicRegex;
+
+impl Rule for CatastrophicRegex {
+    fn name(&self) -> &'static str {
+        "catastrophic-regex"
+    }
+    fn check(&self, force: &Force) -> Vec<Warning> {
+        each_field(force)
+            .into_iter()
+            .filter_map(|(category, field)| {
+                let regex = field_regex(&field.datatype)?;
+                if !has_nested_quantifier(regex.as_str()) {
+                    return None;
+                }
+                let message = format!(
+                    "域 {}.{} 的正則表達式 /{}/ 有巢狀的重複，在其他語言中驗證時可能極慢",
+                    category.name,
+                    field.name,
+                    regex.as_str()
+                );
+                Some(Warning::new(
+                    self.name(),
+                    &category.name,
+                    Some(&field.name),
+                    message,
+                ))
+            })
+            .collect()
+    }
+}
+
+pub struct Linter {
+    rules: Vec<Box<dyn Rule>>,
+    disabled: Vec<String>,
+}
+
+impl Default for Linter {
+    fn default() -> Linter {
+        Linter::new()
+    }
+}
+
+impl Linter {
+    // 包含所有內建規則
+    pub fn new() -> Linter {
+        let mut linter = Linter::empty();
+        linter
+            .register(EmptyCategory)
+            .register(OneWayBond)
+            .register(IsolatedCategory)
+            .register(BroadBond)
+            .register(CatastrophicRegex);
+        linter
+    }
+    pub fn empty() -> Linter {
+        Linter {
+            rules: Vec::new(),
+            disabled: Vec::new(),
+        }
+    }
+    pub fn register(&mut self, rule: impl Rule + 'static) -> &mut Linter {
+        self.rules.push(Box::new(rule));
+        self
+    }
+    pub fn disable(&mut self, rule: &str) -> &mut Linter {
+        self.disabled.push(rule.to_owned());
+        self
+    }
+    // 依註冊順序，不含已停用的規則
+    pub fn rule_names(&self) -> Vec<&'static str> {
+        self.rules
+            .iter()
+            .map(|rule| rule.name())
+            .filter(|name| !self.disabled.iter().any(|disabled| disabled == name))
+            .collect()
+    }
+    // 依規則的註冊順序回傳，同一規則內依分類名排序
+    pub fn lint(&self, force: &Force) -> Vec<Warning> {
+        self.rules
+            .iter()
+            .filter(|rule| !self.disabled.iter().any(|name| name == rule.name()))
+            .flat_map(|rule| rule.check(force))
+            .collect()
+    }
+    // 解析原始碼並檢查，警告附上分類名或域名的位置
+    pub fn lint_source(&self, source: &str) -> ForceResult<Vec<Warning>> {
+        let force = parse(source)?;
+        let analysis = Analysis::new(source);
+        let mut warnings = self.lint(&force);
+        for warning in &mut warnings {
+            warning.span = analysis.name_span(&warning.category, warning.field.as_deref());
+        }
+        Ok(warnings)
+    }
+}
+
+pub fn lint(force: &Force) -> Vec<Warning> {
+    Linter::new().lint(force)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rules(warnings: &[Warning]) -> Vec<(&str, &str)> {
+        warnings
+            .iter()
+            .map(|warning| (warning.rule, warning.category.as_str()))
+            .collect()
+    }
+
+    #[test]
+    fn test_builtin_rules() -> ForceResult<()> {
+        let source = "草稿 {} 文章 {@title 單行 標題 文本/(a+)+b/ 內文} \
+                      回覆 {鍵結[文章] 原文 鍵結[*] 轉貼} 引用 {鍵結[文章] 來源 反向 引用}";
+        let warnings = Linter::new().lint_source(source)?;
+        assert_eq!(
+            rules(&warnings),
+            vec![
+                ("empty-category", "草稿"),
+                ("one-way-bond", "回覆"),
+                ("broad-bond", "回覆"),
+                ("catastrophic-regex", "文章"),
+            ]
+        );
+        assert_eq!(warnings[1].field.as_deref(), Some("原文"));
+        assert_eq!(&source[warnings[0].span.clone().unwrap()], "草稿");
+        assert_eq!(&source[warnings[1].span.clone().unwrap()], "原文");
+
+        let force = parse("文章 {單行 標題} 設定 {布林 公開}")?;
+        let warnings = lint(&force);
+        assert_eq!(
+            rules(&warnings),
+            vec![("isolated-category", "文章"), ("isolated-category", "設定")]
+        );
+        assert!(Linter::new()
+            .disable("isolated-category")
+            .lint(&force)
+            .is_empty());
+        Ok(())
+    }
+    #[test]
+    fn test_nested_quantifier() {
+        for regex in [
+            "(a+)+",
+            "(.*)*b",
+            "((ab)*c)+",
+            "(x{2,})+",
+            "(a|b+){3,}",
+            "(\\d+)+",
+            "([a-z]+)+",
+            "(\\w+\\s?)*$",
+        ] {
+            assert!(has_nested_quantifier(regex), "{}", regex);
+        }
+        for regex in [
+            "a+b*",
+            "(ab)+",
+            "(a+){3}",
+            "[(a+)]+",
+            "\\(a+\\)+",
+            ".{256,}",
+        ] {
+            assert!(!has_nested_quantifier(regex), "{}", regex);
+        }
+    }
+    #[test]
+    fn test_custom_rule() -> ForceResult<()> {
+        struct NoTitle;
+        impl Rule for NoTitle {
+            fn name(&self) -> &'static str {
+                "no-title"
+            }
+            fn check(&self, force: &Force) -> Vec<Warning> {
+                let names = force.category_names().into_iter();
+                names
+                    .filter(|name| force.categories[*name].title_field.is_none())
+                    .map(|name| Warning::new(self.name(), name, None, "沒有標題".to_owned()))
+                    .collect()
+            }
+        }
+        let mut linter = Linter::empty();
+        linter.register(NoTitle);
+        assert_eq!(linter.rule_names(), vec!["no-title"]);
+        let warnings = linter.lint(&parse("文章 {@title 單行 標題} 回覆 {單行 內文}")?);
+        assert_eq!(rules(&warnings), vec![("no-title", "回覆")]);
+        Ok(())
+    }
+}
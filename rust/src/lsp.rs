@@ -0,0 +1,299 @@
+// 語言伺服器協定的部分，分析本身見 analysis 模組
+// 每次文件變動即重新分析整份文件並發布診斷
+use crate::analysis::{Analysis, CompletionKind};
+use lsp_server::{Connection, Message, Notification, Request, RequestId, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+    Notification as NotificationTrait, PublishDiagnostics,
+};
+use lsp_types::request::{Completion, GotoDefinition, HoverRequest, Request as RequestTrait};
+use lsp_types::*;
+use std::collections::HashMap;
+use std::error::Error;
+
+// LSP 的行列從 0 起算，列以 UTF-16 編碼單位計
+fn to_position(source: &str, offset: usize) -> Position {
+    let before = &source[..offset];
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    Position {
+        line: before.matches('\n').count() as u32,
+        character: before[line_start..].encode_utf16().count() as u32,
+    }
+}
+
+fn to_offset(source: &str, position: Position) -> usize {
+    let mut line_start = 0;
+    for _ in 0..position.line {
+        match source[line_start..].find('\n') {
+            Some(i) => line_start += i + 1,
+            None => return source.len(),
+        }
+    }
+    let mut units = 0;
+    for (i, c) in source[line_start..].char_indices() {
+        if units >= position.character as usize || c == '\n' {
+            return line_start + i;
+        }
+        units += c.len_utf16();
+    }
+    source.len()
+}
+
+fn to_range(source: &str, span: &logos::Span) -> Range {
+    Range {
+        start: to_position(source, span.start),
+        end: to_position(source, span.end),
+    }
+}
+
+struct Document {
+    source: String,
+    analysis: Analysis,
+}
+
+impl Document {
+    fn new(source: String) -> Document {
+        Document {
+            analysis: Analysis::new(&source),
+            source,
+        }
+    }
+    fn diagnostics(&self) -> Vec<lsp_types::Diagnostic> {
+        self.analysis
+            .diagnostics()
+            .into_iter()
+            .map(|diagnostic| lsp_types::Diagnostic {
+                range: to_range(&self.source, &diagnostic.span),
+                severity: Some(DiagnosticSeverity::ERROR),
+                source: Some("force".to_owned()),
+                message: match diagnostic.help {
+                    Some(help) => format!("{}\n提示：{}", diagnostic.message, help),
+                    None => diagnostic.message,
+                },
+                ..Default::default()
+            })
+            .collect()
+    }
+}
+
+struct Server {
+    connection: Connection,
+    documents: HashMap<Url, Document>,
+}
+
+impl Server {
+    fn send_notification<N: NotificationTrait>(
+        &self,
+        params: N::Params,
+    ) -> Result<(), Box<dyn Error>> {
+        let notification = Notification::new(N::METHOD.to_owned(), params);
+        self.connection
+            .sender
+            .send(Message::Notification(notification))?;
+        Ok(())
+    }
+    fn publish(
+        &self,
+        uri: Url,
+        diagnostics: Vec<lsp_types::Diagnostic>,
+    ) -> Result<(), Box<dyn Error>> {
+        self.send_notification::<PublishDiagnostics>(PublishDiagnosticsParams {
+            uri,
+            diagnostics,
+            version: None,
+        })
+    }
+    fn update(&mut self, uri: Url, source: String) -> Result<(), Box<dyn Error>> {
+        let document = Document::new(source);
+        let diagnostics = document.diagnostics();
+        self.documents.insert(uri.clone(), document);
+        self.publish(uri, diagnostics)
+    }
+    fn handle_notification(&mut self, notification: Notification) -> Result<(), Box<dyn Error>> {
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params: DidOpenTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                self.update(params.text_document.uri, params.text_document.text)?;
+            }
+            // 只支援全量同步，最後一筆變動即為完整內容
+            DidChangeTextDocument::METHOD => {
+                let params: DidChangeTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                if let Some(change) = params.content_changes.into_iter().last() {
+                    self.update(params.text_document.uri, change.text)?;
+                }
+            }
+            DidCloseTextDocument::METHOD => {
+                let params: DidCloseTextDocumentParams =
+                    serde_json::from_value(notification.params)?;
+                self.documents.remove(&params.text_document.uri);
+                self.publish(params.text_document.uri, Vec::new())?;
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+    fn document(&self, params: &TextDocumentPositionParams) -> Option<(&Document, usize)> {
+        let document = self.documents.get(&params.text_document.uri)?;
+        let offset = to_offset(&document.source, params.position);
+        Some((document, offset))
+    }
+    fn hover(&self, params: HoverParams) -> Option<Hover> {
+        let (document, offset) = self.document(&params.text_document_position_params)?;
+        let text = document.analysis.hover(offset)?;
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::PlainText,
+                value: text,
+            }),
+            range: None,
+        })
+    }
+    fn definition(&self, params: GotoDefinitionParams) -> Option<GotoDefinitionResponse> {
+        let position = &params.text_document_position_params;
+        let (document, offset) = self.document(position)?;
+        let span = document.analysis.definition(offset)?;
+        Some(GotoDefinitionResponse::Scalar(Location {
+            uri: position.text_document.uri.clone(),
+            range: to_range(&document.source, &span),
+        }))
+    }
+    fn completion(&self, params: CompletionParams) -> Option<CompletionResponse> {
+        let (document, offset) = self.document(&params.text_document_position)?;
+        let items = document
+            .analysis
+            .completions(offset)
+            .into_iter()
+            .map(|completion| CompletionItem {
+                label: completion.label,
+                kind: Some(match completion.kind {
+                    CompletionKind::DataType => CompletionItemKind::KEYWORD,
+                    CompletionKind::Category => CompletionItemKind::CLASS,
+                    CompletionKind::Pattern => CompletionItemKind::CONSTANT,
+                }),
+                ..Default::default()
+            })
+            .collect();
+        Some(CompletionResponse::Array(items))
+    }
+    fn handle_request(&self, request: Request) -> Result<(), Box<dyn Error>> {
+        let id: RequestId = request.id.clone();
+        let result = match request.method.as_str() {
+            HoverRequest::METHOD => {
+                let params = serde_json::from_value(request.params)?;
+                serde_json::to_value(self.hover(params))?
+            }
+            GotoDefinition::METHOD => {
+                let params = serde_json::from_value(request.params)?;
+                serde_json::to_value(self.definition(params))?
+            }
+            Completion::METHOD => {
+                let params = serde_json::from_value(request.params)?;
+                serde_json::to_value(self.completion(params))?
+            }
+            _ => {
+                let response = Response::new_err(
+                    id,
+                    lsp_server::ErrorCode::MethodNotFound as i32,
+                    format!("不支援的請求 {}", request.method),
+                );
+                self.connection.sender.send(Message::Response(response))?;
+                return Ok(());
+            }
+        };
+        let response = Response::new_ok(id, result);
+        self.connection.sender.send(Message::Response(response))?;
+        Ok(())
+    }
+    fn main_loop(&mut self) -> Result<(), Box<dyn Error>> {
+        while let Ok(message) = self.connection.receiver.recv() {
+            match message {
+                Message::Request(request) => {
+                    if self.connection.handle_shutdown(&request)? {
+                        return Ok(());
+                    }
+                    self.handle_request(request)?;
+                }
+                Message::Notification(notification) => self.handle_notification(notification)?,
+                Message::Response(_) => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+fn capabilities() -> ServerCapabilities {
+    ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        definition_provider: Some(OneOf::Left(true)),
+        completion_provider: Some(CompletionOptions {
+            trigger_characters: Some(vec!["[".to_owned(), ",".to_owned(), ":".to_owned()]),
+            ..Default::default()
+        }),
+        ..Default::default()
+    }
+}
+
+// 以標準輸入輸出執行語言伺服器，直到客戶端要求關閉
+pub fn run() -> Result<(), Box<dyn Error>> {
+    let (connection, io_threads) = Connection::stdio();
+    connection.initialize(serde_json::to_value(capabilities())?)?;
+    let mut server = Server {
+        connection,
+        documents: HashMap::new(),
+    };
+    server.main_loop()?;
+    drop(server);
+    io_threads.join()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_position() {
+        let source = "新聞 {\n    單行 記者\n}";
+        let offset = source.find("記者").unwrap();
+        let position = to_position(source, offset);
+        assert_eq!(
+            position,
+            Position {
+                line: 1,
+                character: 7
+            }
+        );
+        assert_eq!(to_offset(source, position), offset);
+        assert_eq!(to_offset(source, Position::new(9, 0)), source.len());
+    }
+    #[test]
+    fn test_memory_connection() {
+        let (client, server) = Connection::memory();
+        let mut server = Server {
+            connection: server,
+            documents: HashMap::new(),
+        };
+        let uri = Url::parse("file:///a.force").unwrap();
+        let params = DidOpenTextDocumentParams {
+            text_document: TextDocumentItem::new(
+                uri,
+                "force".to_owned(),
+                0,
+                "新聞 {單行}".to_owned(),
+            ),
+        };
+        let notification = Notification::new(DidOpenTextDocument::METHOD.to_owned(), params);
+        server.handle_notification(notification).unwrap();
+        match client.receiver.recv().unwrap() {
+            Message::Notification(notification) => {
+                let params: PublishDiagnosticsParams =
+                    serde_json::from_value(notification.params).unwrap();
+                assert_eq!(params.diagnostics.len(), 1);
+                assert_eq!(params.diagnostics[0].range.start, Position::new(0, 6));
+            }
+            other => panic!("預期診斷，但得到 {:?}", other),
+        }
+    }
+}
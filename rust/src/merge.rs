@@ -0,0 +1,194 @@
+// 合併兩個力，如以共用的基本綱要加上各看板的擴充綱要
+use crate::prelude::*;
+use crate::visit::{walk_force_mut, VisitorMut};
+use crate::*;
+
+// 兩個力中有同名而定義不同的分類或模式時的處理方式，定義相同者視為同一個
+#[derive(Debug, Clone, PartialEq)]
+pub enum MergeStrategy {
+    Error,                    // 回報 MergeConflict
+    PreferLeft,               // 保留 self 的定義
+    RenameWithPrefix(String), // 為 other 中的加上前綴，並改寫 other 中對它們的鍵結與繼承
+}
+
+// 除原始碼外皆相同
+fn same_category(a: &Category, b: &Category) -> bool {
+    let a = Category {
+        source: b.source.clone(),
+        ..a.clone()
+    };
+    a == *b
+}
+
+fn conflicts(left: &Force, right: &Force) -> (Vec<String>, Vec<String>) {
+    let mut categories: Vec<String> = right
+        .categories
+        .iter()
+        .filter(|(name, category)| {
+            left.categories
+                .get(*name)
+                .is_some_and(|other| !same_category(other, category))
+        })
+        .map(|(name, _)| name.clone())
+        .collect();
+    categories.sort();
+    let mut patterns: Vec<String> = right
+        .patterns
+        .iter()
+        .filter(|(name, regex)| {
+            left.patterns
+                .get(*name)
+                .is_some_and(|other| other != *regex)
+        })
+        .map(|(name, _)| name.clone())
+        .collect();
+    patterns.sort();
+    (categories, patterns)
+}
+
+struct Renamer<'a> {
+    renamed: &'a Map<String, String>,
+}
+
+impl<'a> VisitorMut for Renamer<'a> {
+    fn visit_bondee_mut(&mut self, bondee: &mut Bondee) {
+        for name in bondee.names_mut() {
+            if let Some(renamed) = self.renamed.get(name) {
+                *name = renamed.clone();
+            }
+        }
+    }
+}
+
+fn rename(force: &mut Force, categories: &[String], patterns: &[String], prefix: &str) {
+    let renamed: Map<String, String> = categories
+        .iter()
+        .map(|name| (name.clone(), format!("{}{}", prefix, name)))
+        .collect();
+    for (name, new_name) in &renamed {
+        let mut category = force.categories.remove(name).unwrap();
+        category.name = new_name.clone();
+        force.categories.insert(new_name.clone(), category);
+    }
+    for category in force.categories.values_mut() {
+        for parent in &mut category.parents {
+            if let Some(renamed) = renamed.get(parent) {
+                *parent = renamed.clone();
+            }
+        }
+    }
+    walk_force_mut(&mut Renamer { renamed: &renamed }, force);
+    // 域的正則表達式已在解析時展開，只須改寫模式名
+    for name in patterns {
+        let regex = force.patterns.remove(name).unwrap();
+        force.patterns.insert(format!("{}{}", prefix, name), regex);
+    }
+}
+
+impl Force {
+    // 合併後重新做語意檢查，確保所有鍵結對象都存在；有衝突時只回報衝突
+    // 錯誤皆依名稱排序
+    pub fn merge(
+        mut self,
+        mut other: Force,
+        strategy: MergeStrategy,
+    ) -> Result<Force, Vec<ForceError>> {
+        if let MergeStrategy::RenameWithPrefix(prefix) = &strategy {
+            let (categories, patterns) = conflicts(&self, &other);
+            rename(&mut other, &categories, &patterns, prefix);
+        }
+        // 加上前綴後仍可能與 self 中的名稱衝突
+        if strategy != MergeStrategy::PreferLeft {
+            let (categories, patterns) = conflicts(&self, &other);
+            let mut errors: Vec<ForceError> = categories
+                .into_iter()
+                .chain(patterns)
+                .map(|name| ForceError::MergeConflict { name })
+                .collect();
+            if !errors.is_empty() {
+                errors.sort_by_key(|error| error.to_string());
+                return Err(errors);
+            }
+        }
+        for (name, category) in other.categories {
+            self.categories.entry(name).or_insert(category);
+        }
+        for (name, regex) in other.patterns {
+            self.patterns.entry(name).or_insert(regex);
+        }
+        self.validate()?;
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE: &str =
+        "模式 代號 = /[a-z]+/ 會員 {文本 代號 帳號} 文章 {鍵結[會員] 作者 單行 標題}";
+
+    #[test]
+    fn test_merge() -> ForceResult<()> {
+        let extension =
+            "模式 代號 = /[a-z]+/ 會員 {文本 代號 帳號} 投票 {鍵結[文章] 原文 數字 票數}";
+        let force = parse(BASE)?
+            .merge(parse(extension)?, MergeStrategy::Error)
+            .unwrap();
+        assert_eq!(force.category_names(), vec!["投票", "文章", "會員"]);
+
+        let extension = "模式 代號 = /[0-9]+/ 文章 {單行 標題 文本 內文} 精華 {鍵結[文章] 原文}";
+        match parse(BASE)?.merge(parse(extension)?, MergeStrategy::Error) {
+            Err(errors) => {
+                let messages: Vec<String> = errors.iter().map(|e| e.to_string()).collect();
+                assert_eq!(
+                    messages,
+                    vec![
+                        "合併的兩個力中 代號 的定義不同",
+                        "合併的兩個力中 文章 的定義不同"
+                    ]
+                );
+            }
+            Ok(_) => panic!("預期 MergeConflict"),
+        }
+        let force = parse(BASE)?
+            .merge(parse(extension)?, MergeStrategy::PreferLeft)
+            .unwrap();
+        assert_eq!(force.categories["文章"].fields.len(), 2);
+        assert_eq!(force.patterns["代號"], "[a-z]+");
+
+        let strategy = MergeStrategy::RenameWithPrefix("八卦版.".to_owned());
+        let force = parse(BASE)?.merge(parse(extension)?, strategy).unwrap();
+        assert_eq!(
+            force.category_names(),
+            vec!["八卦版.文章", "文章", "會員", "精華"]
+        );
+        let renamed = Bondee::Choices(vec!["八卦版.文章".to_owned()]);
+        assert_eq!(
+            force.categories["精華"].fields[0].datatype.bondee(),
+            Some(&renamed)
+        );
+        assert_eq!(force.patterns["八卦版.代號"], "[0-9]+");
+        Ok(())
+    }
+    #[test]
+    fn test_merge_revalidate() -> ForceResult<()> {
+        let extension = "投票 {鍵結[留言] 原文}";
+        let errors = parse(BASE)?
+            .merge(parse(extension)?, MergeStrategy::Error)
+            .unwrap_err();
+        assert!(matches!(
+            &errors[..],
+            [ForceError::UnknownBondee { bondee, .. }] if bondee == "留言"
+        ));
+        let strategy = MergeStrategy::RenameWithPrefix("八卦".to_owned());
+        let errors = parse("文章 {} 八卦文章 {單行 標題}")?
+            .merge(parse("文章 {布林 置頂}")?, strategy)
+            .unwrap_err();
+        assert!(matches!(
+            &errors[..],
+            [ForceError::MergeConflict { name }] if name == "八卦文章"
+        ));
+        Ok(())
+    }
+}
@@ -1,125 +1,634 @@
+use crate::cst::{Builder, NodeKind, SyntaxNode};
 use crate::defs::*;
-use crate::lexer::{lexer, Token};
-use logos::Span;
+use crate::import::{join_path, Loader};
+use crate::lexer::Token;
+use crate::prelude::*;
+use crate::semantic::link;
+use crate::validate::ValidatorTrait;
+use core::iter::Peekable;
+use core::mem;
+use logos::{Logos, Span, SpannedIter};
+#[cfg(feature = "std")]
 use regex::Regex;
-use std::collections::HashMap;
-
-pub struct Parser {
-    tokens: Vec<(Token, Span)>,
-    count: usize,
-    cur: Token,
-    source: String,
-}
-
-impl Parser {
-    pub fn new(source: &str) -> Parser {
-        let tokens = lexer(source);
-        return Parser {
-            count: 0,
-            cur: tokens[0].0.clone(),
-            tokens: tokens,
-            source: source.to_owned(),
+use serde_json::Value;
+
+// 檢查預設值用，鍵結無法有預設值
+struct DefaultValidator {}
+impl ValidatorTrait for DefaultValidator {
+    fn validate_bond(&self, _bondee: &Bondee, _data: &Value) -> bool {
+        false
+    }
+}
+
+// 域前的 @ 屬性，記錄位置以便回報錯誤
+#[derive(Default)]
+struct FieldAttributes {
+    title: Option<Span>,
+    key: Option<Span>,
+    unique: bool,
+    others: Vec<Attribute>,
+}
+
+// 容錯解析的結果，供語法突顯與大綱等在使用者輸入途中仍需運作的功能使用
+#[derive(Debug)]
+pub struct PartialForce {
+    pub force: Force,           // 與 parse_all_errors 相同，只含完整的分類
+    pub partial: Vec<Category>, // 出錯的分類，只含錯誤之前的域，繼承未展開
+    pub errors: Vec<ForceError>,
+}
+
+impl PartialForce {
+    pub fn is_complete(&self) -> bool {
+        self.errors.is_empty()
+    }
+    // 完整與不完整的分類，依名稱排序，同名者完整的在前
+    pub fn categories(&self) -> Vec<&Category> {
+        let mut categories: Vec<&Category> = self.force.categories.values().collect();
+        categories.extend(&self.partial);
+        categories.sort_by(|a, b| a.name.cmp(&b.name));
+        categories
+    }
+}
+
+// 在 UAX#31 之外另外限制識別子，供專案統一命名
+#[derive(Debug, Clone, Default)]
+pub enum IdentifierPolicy {
+    #[default]
+    Unicode, // 不另設限制
+    Ascii,          // 只能有 ASCII 字元
+    Pattern(Regex), // 須符合此正則表達式，通常以 ^ 與 $ 錨定，如 ^[\p{Han}_]+$
+}
+
+impl IdentifierPolicy {
+    pub fn allows(&self, name: &str) -> bool {
+        match self {
+            IdentifierPolicy::Unicode => true,
+            IdentifierPolicy::Ascii => name.is_ascii(),
+            IdentifierPolicy::Pattern(regex) => regex.is_match(name),
+        }
+    }
+}
+
+// 解析時的寬鬆程度與資源上限，預設為最嚴格
+#[derive(Debug, Clone)]
+pub struct ParserOptions {
+    pub trailing_commas: bool,      // 允許逗號分隔的列表以逗號結尾
+    pub unknown_attributes: bool,   // 保留未知的域屬性，而非回報錯誤
+    pub overwrite_duplicates: bool, // 同名分類以後者覆蓋前者，而非回報錯誤
+    pub max_depth: Option<usize>,   // 列表、命名空間與算式各自的巢狀層數上限，None 時可能耗盡堆疊
+    pub max_size: Option<usize>,    // 原始碼的位元組數上限
+    pub identifiers: IdentifierPolicy,
+}
+
+// 預設的巢狀層數上限，遠低於遞迴下降耗盡堆疊所需的層數
+pub const DEFAULT_MAX_DEPTH: usize = 64;
+
+impl Default for ParserOptions {
+    fn default() -> ParserOptions {
+        ParserOptions::strict()
+    }
+}
+
+impl ParserOptions {
+    // 適合持續整合中的檢查
+    pub fn strict() -> ParserOptions {
+        ParserOptions {
+            trailing_commas: false,
+            unknown_attributes: false,
+            overwrite_duplicates: false,
+            max_depth: Some(DEFAULT_MAX_DEPTH),
+            max_size: None,
+            identifiers: IdentifierPolicy::Unicode,
+        }
+    }
+    // 適合互動式編輯器，盡量接受編輯到一半的原始碼
+    pub fn lenient() -> ParserOptions {
+        ParserOptions {
+            trailing_commas: true,
+            unknown_attributes: true,
+            overwrite_duplicates: true,
+            ..ParserOptions::strict()
+        }
+    }
+}
+
+// 邊解析邊向詞法分析器取 token ，不預先切分整份原始碼
+pub struct Parser<'s> {
+    tokens: Peekable<SpannedIter<'s, Token<'s>>>,
+    cur: Token<'s>,
+    span: Span,
+    prev_end: usize, // 前一個 token 的結尾
+    depth: usize,    // 已消耗的大括號深度，供錯誤恢復用
+    source: &'s str,
+    lex_errors: Vec<ForceError>,
+    patterns: Map<String, Regex>, // 已宣告的模式
+    regexes: Map<String, Regex>,  // 已編譯的正則表達式
+    cst: Option<Builder>,         // 僅在需要具體語法樹時建立
+    loader: Option<&'s dyn Loader>,
+    path: String,                              // 目前檔案的路徑，匯入的相對路徑以此為準
+    importing: Vec<String>,                    // 正在解析中的檔案，用以偵測循環匯入
+    loaded: Map<String, Vec<(String, Regex)>>, // 已匯入過的檔案及其模式，重複匯入時只併入模式
+    namespace: Vec<String>,                    // 目前所在的命名空間，由外而內
+    options: ParserOptions,
+    list_depth: usize,       // 目前所在的列表型別層數
+    partials: Vec<Category>, // 解析到一半出錯的分類
+}
+
+impl<'s> Parser<'s> {
+    pub fn new(source: &'s str) -> Parser<'s> {
+        let mut parser = Parser {
+            tokens: Token::lexer(source).spanned().peekable(),
+            cur: Token::End,
+            span: 0..0,
+            prev_end: 0,
+            depth: 0,
+            source,
+            lex_errors: Vec::new(),
+            patterns: Map::new(),
+            regexes: Map::new(),
+            cst: None,
+            loader: None,
+            path: String::new(),
+            importing: Vec::new(),
+            loaded: Map::new(),
+            namespace: Vec::new(),
+            options: ParserOptions::default(),
+            list_depth: 0,
+            partials: Vec::new(),
         };
+        parser.advance();
+        parser.prev_end = 0;
+        parser
+    }
+    pub fn with_options(source: &'s str, options: ParserOptions) -> Parser<'s> {
+        let mut parser = Parser::new(source);
+        parser.set_options(options);
+        parser
+    }
+    // 第一個 token 已在 new 中取得，須依新的命名規則重新檢查
+    fn set_options(&mut self, options: ParserOptions) {
+        self.options = options;
+        let (cur, span) = (mem::replace(&mut self.cur, Token::End), self.cur_span());
+        self.cur = self.check_identifier(cur, &span);
+    }
+    pub(crate) fn with_loader(source: &'s str, path: &str, loader: &'s dyn Loader) -> Parser<'s> {
+        let mut parser = Parser::new(source);
+        parser.loader = Some(loader);
+        parser.path = path.to_owned();
+        parser.importing = vec![path.to_owned()];
+        parser
+    }
+    // 預先宣告模式，供只解析原始碼中一段的呼叫者使用
+    #[cfg(feature = "std")]
+    pub(crate) fn with_patterns(source: &'s str, patterns: Map<String, Regex>) -> Parser<'s> {
+        let mut parser = Parser::new(source);
+        parser.patterns = patterns;
+        parser
+    }
+    #[cfg(feature = "std")]
+    pub(crate) fn into_patterns(self) -> Map<String, Regex> {
+        self.patterns
+    }
+    // 詞法錯誤必然導致解析失敗，此時回報最先遇到的詞法錯誤
+    fn check_lexer<T>(&mut self, result: ForceResult<T>) -> ForceResult<T> {
+        match result {
+            Err(_) if !self.lex_errors.is_empty() => Err(self.lex_errors.remove(0)),
+            result => result,
+        }
+    }
+    fn cur_span(&self) -> Span {
+        self.span.clone()
+    }
+    // 前進一個 token ，回傳原本的 token
+    fn advance(&mut self) -> Token<'s> {
+        if let (Some(cst), false) = (&mut self.cst, self.cur == Token::End) {
+            cst.token(self.source, self.cur.clone(), self.span.clone());
+        }
+        match self.cur {
+            Token::LeftCurlyBrace => self.depth += 1,
+            Token::RightCurlyBrace => self.depth = self.depth.saturating_sub(1),
+            _ => {}
+        }
+        let (next, span) = self.tokens.next().unwrap_or_else(|| {
+            let len = self.source.len();
+            (Token::End, len..len)
+        });
+        if next == Token::Error {
+            self.lex_errors.push(ForceError::LexError {
+                text: self.source[span.clone()].to_owned(),
+                span: span.clone(),
+            });
+        }
+        let next = self.check_identifier(next, &span);
+        self.prev_end = self.span.end;
+        self.span = span;
+        mem::replace(&mut self.cur, next)
+    }
+    // 不符合命名規則的識別子視為詞法錯誤
+    fn check_identifier(&mut self, token: Token<'s>, span: &Span) -> Token<'s> {
+        match &token {
+            Token::Identifier(name) if !self.options.identifiers.allows(name) => {
+                self.lex_errors.push(ForceError::InvalidIdentifier {
+                    name: name.to_string(),
+                    span: span.clone(),
+                });
+                Token::Error
+            }
+            _ => token,
+        }
+    }
+    pub(crate) fn enable_cst(&mut self) {
+        self.cst = Some(Builder::new());
+    }
+    pub(crate) fn take_cst(&mut self) -> Option<SyntaxNode> {
+        let source = self.source;
+        self.cst.take().map(|cst| cst.finish(source))
+    }
+    fn checkpoint(&mut self) -> usize {
+        let source = self.source;
+        let end = self.span.start;
+        self.cst
+            .as_mut()
+            .map_or(0, |cst| cst.checkpoint(source, end))
+    }
+    fn start_node_at(&mut self, checkpoint: usize, kind: NodeKind) {
+        if let Some(cst) = &mut self.cst {
+            cst.start_node_at(checkpoint, kind);
+        }
+    }
+    fn cst_depth(&self) -> usize {
+        self.cst.as_ref().map_or(0, Builder::depth)
+    }
+    fn finish_nodes(&mut self, depth: usize) {
+        if let Some(cst) = &mut self.cst {
+            cst.finish_to(depth);
+        }
+    }
+    // 前進一個 token ，取出其所帶的字串，仍借用原始碼
+    fn advance_str(&mut self) -> Cow<'s, str> {
+        match self.advance() {
+            Token::Identifier(s)
+            | Token::DocComment(s)
+            | Token::StringLiteral(s)
+            | Token::Regex(s) => s,
+            _ => Cow::Borrowed(""),
+        }
+    }
+    fn advance_string(&mut self) -> String {
+        self.advance_str().into_owned()
+    }
+    fn peek(&mut self) -> Option<&Token<'s>> {
+        self.tokens.peek().map(|(token, _)| token)
+    }
+    // 目前的 token 不在 expected 之中，原始碼已結束時回報 UnexpectedEof
+    fn unexpected(&self, expected: &[Token]) -> ForceError {
+        let expected = expected.iter().map(|token| token.clone().into_owned());
+        if self.cur == Token::End {
+            return ForceError::UnexpectedEof {
+                expected: expected.collect(),
+                span: self.cur_span(),
+            };
+        }
+        ForceError::Unexpected {
+            expected: expected.collect(),
+            fact: self.cur.clone().into_owned(),
+            span: self.cur_span(),
+        }
     }
-    fn advance(&mut self) {
-        self.count += 1;
-        self.cur = self.tokens[self.count].clone().0
+    // 已吃掉逗號而目前為列表結尾時，依選項決定是否接受
+    fn trailing_comma(&self, close: &Token) -> bool {
+        self.options.trailing_commas && &self.cur == close
+    }
+    // 相同的正則表達式只編譯一次，各域共用編譯結果
+    fn compile_regex(&mut self, s: &str, span: Span) -> ForceResult<Regex> {
+        if let Some(regex) = self.regexes.get(s) {
+            return Ok(regex.clone());
+        }
+        match Regex::new(s) {
+            Ok(regex) => {
+                self.regexes.insert(s.to_owned(), regex.clone());
+                Ok(regex)
+            }
+            Err(error) => {
+                // 語法錯誤的最後一行為錯誤原因，前面是標示位置的圖示
+                let message = error.to_string();
+                let message = message.lines().last().unwrap_or_default();
+                Err(ForceError::InvalidRegex {
+                    regex: s.to_owned(),
+                    message: message.trim_start_matches("error: ").to_owned(),
+                    span,
+                })
+            }
+        }
+    }
+    fn check_size(&self) -> ForceResult<()> {
+        match self.options.max_size {
+            Some(limit) if self.source.len() > limit => Err(ForceError::TooLarge {
+                limit,
+                span: limit..self.source.len(),
+            }),
+            _ => Ok(()),
+        }
+    }
+    fn check_depth(&self, depth: usize, span: Span) -> ForceResult<()> {
+        match self.options.max_depth {
+            Some(limit) if depth > limit => Err(ForceError::TooDeep { limit, span }),
+            _ => Ok(()),
+        }
     }
     fn eat(&mut self, expect: Token) -> ForceResult<()> {
         if self.cur == expect {
             self.advance();
             Ok(())
         } else {
-            Err(ForceError::NonExpect {
-                expect,
-                fact: self.cur.clone(),
-            })
+            Err(self.unexpected(&[expect]))
         }
     }
     fn get_identifier(&mut self) -> ForceResult<String> {
-        let ret = if let Token::Identifier(id) = &self.cur {
-            Ok(id.clone())
+        self.get_identifier_str().map(Cow::into_owned)
+    }
+    // 只用於比對或查詢時不必配置字串
+    fn get_identifier_str(&mut self) -> ForceResult<Cow<'s, str>> {
+        if let Token::Identifier(_) = self.cur {
+            Ok(self.advance_str())
         } else {
-            Err(ForceError::NonExpect {
-                expect: Token::Identifier("某個識別子".to_owned()),
-                fact: self.cur.clone(),
-            })
-        };
-        if let Ok(_) = ret {
+            Err(self.unexpected(&[Token::Identifier("".into())]))
+        }
+    }
+    // 舊版的識別子可含 - 等符號，如 play-boy 現在會被拆成 play - boy
+    // 名稱之後緊接著舊版允許的字元時，回報舊版的完整識別子
+    fn check_legacy(&self, start: usize) -> ForceResult<()> {
+        let is_delimiter = |c: char| c.is_whitespace() || "/[]}{,#:@<>().?=\"`".contains(c);
+        if self.source[start..].starts_with('`') {
+            return Ok(());
+        }
+        match self.source[self.prev_end..].chars().next() {
+            Some(c) if !is_delimiter(c) => {
+                let end = self.source[start..]
+                    .find(is_delimiter)
+                    .map_or(self.source.len(), |i| start + i);
+                Err(ForceError::LegacyIdentifier {
+                    name: self.source[start..end].to_owned(),
+                    span: start..end,
+                })
+            }
+            _ => Ok(()),
+        }
+    }
+    // 可帶命名空間的名稱，如 論壇.文章
+    fn parse_name(&mut self) -> ForceResult<String> {
+        let start = self.span.start;
+        let mut name = self.get_identifier()?;
+        self.check_legacy(start)?;
+        while let Token::Dot = self.cur {
+            self.advance();
+            name.push('.');
+            let start = self.span.start;
+            name.push_str(&self.get_identifier()?);
+            self.check_legacy(start)?;
+        }
+        Ok(name)
+    }
+    fn parse_integers(&mut self) -> ForceResult<Vec<i64>> {
+        let mut integers = Vec::new();
+        self.eat(Token::LeftSquareBracket)?;
+        while let Token::Integer(n) = self.cur {
+            integers.push(n);
             self.advance();
+            if let Token::Comma = self.cur {
+                self.advance();
+                if !self.trailing_comma(&Token::RightSquareBracket) {
+                    if let Token::Integer(_) = self.cur {
+                        continue;
+                    }
+                    return Err(self.unexpected(&[Token::Integer(0)]));
+                }
+            } else {
+                break;
+            }
         }
-        ret
+        self.eat(Token::RightSquareBracket)?;
+        Ok(integers)
+    }
+    fn parse_tag(&mut self) -> ForceResult<Tag> {
+        let name = self.get_identifier()?;
+        let mut transfuse = Vec::new();
+        self.eat(Token::LeftCurlyBrace)?;
+        while self.cur != Token::RightCurlyBrace {
+            match self.cur {
+                Token::Transfuse => {
+                    self.advance();
+                    self.eat(Token::Colon)?;
+                    transfuse = self.parse_integers()?;
+                }
+                _ => return Err(self.unexpected(&[Token::Transfuse, Token::RightCurlyBrace])),
+            }
+        }
+        self.eat(Token::RightCurlyBrace)?;
+        Ok(Tag { name, transfuse })
     }
     fn parse_tags(&mut self) -> ForceResult<Vec<Tag>> {
-        let mut tags = Vec::new();
+        let mut tags: Vec<Tag> = Vec::new();
         self.eat(Token::LeftCurlyBrace)?;
         loop {
             if let Token::RightCurlyBrace = self.cur {
                 self.advance();
                 break;
             } else {
-                let tag = self.get_identifier()?;
-                tags.push(Tag { name: tag });
-                self.eat(Token::LeftCurlyBrace)?;
-                while self.cur != Token::RightCurlyBrace {
-                    // TODO: 解析真實內容
-                    self.advance();
+                let span = self.cur_span();
+                let tag = self.parse_tag()?;
+                if tags.iter().any(|t| t.name == tag.name) {
+                    return Err(ForceError::DuplicateTag {
+                        tag: tag.name,
+                        span,
+                    });
                 }
-                self.eat(Token::RightCurlyBrace)?;
+                tags.push(tag);
             }
         }
         Ok(tags)
     }
     fn parse_bondee(&mut self) -> ForceResult<Bondee> {
         self.eat(Token::LeftSquareBracket)?;
-        match self.cur.clone() {
+        match self.cur {
             Token::Star => {
                 self.advance();
-                self.eat(Token::RightSquareBracket)?;
-                Ok(Bondee::All)
+                match self.cur {
+                    Token::RightSquareBracket => {
+                        self.advance();
+                        Ok(Bondee::All)
+                    }
+                    Token::Except => {
+                        self.advance();
+                        Ok(Bondee::AllExcept(self.parse_bondee_names()?))
+                    }
+                    _ => Err(self.unexpected(&[Token::Except, Token::RightSquareBracket])),
+                }
             }
-            Token::Identifier(name) => {
-                let mut choices = vec![name.to_string()];
-                self.advance();
-                loop {
-                    match self.cur {
-                        Token::RightSquareBracket => {
-                            break;
-                        }
-                        _ => {
-                            self.eat(Token::Comma)?;
-                            let name = self.get_identifier()?;
-                            choices.push(name);
-                        }
+            Token::Identifier(_) => Ok(Bondee::Choices(self.parse_bondee_names()?)),
+            _ => Err(self.unexpected(&[Token::Star, Token::Identifier("".into())])),
+        }
+    }
+    // 以逗號分隔的分類名，直到 ] 為止
+    fn parse_bondee_names(&mut self) -> ForceResult<Vec<String>> {
+        let mut choices = vec![self.parse_name()?];
+        loop {
+            match self.cur {
+                Token::RightSquareBracket => {
+                    break;
+                }
+                Token::Comma => {
+                    self.advance();
+                    if !self.trailing_comma(&Token::RightSquareBracket) {
+                        choices.push(self.parse_name()?);
                     }
                 }
-                self.eat(Token::RightSquareBracket)?;
-                Ok(Bondee::Choices(choices))
+                _ => return Err(self.unexpected(&[Token::Comma, Token::RightSquareBracket])),
             }
-            _ => Err(ForceError::NoMeet {
-                expect: "* 或識別子".to_owned(),
-                fact: self.cur.clone(),
-            }),
         }
+        self.eat(Token::RightSquareBracket)?;
+        Ok(choices)
+    }
+    fn parse_variants(&mut self) -> ForceResult<Vec<String>> {
+        let mut variants: Vec<String> = Vec::new();
+        self.eat(Token::LeftCurlyBrace)?;
+        loop {
+            let span = self.cur_span();
+            let variant = self.get_identifier()?;
+            if variants.contains(&variant) {
+                return Err(ForceError::DuplicateVariant { variant, span });
+            }
+            variants.push(variant);
+            match self.cur {
+                Token::RightCurlyBrace => break,
+                Token::Comma => {
+                    self.advance();
+                    if self.trailing_comma(&Token::RightCurlyBrace) {
+                        break;
+                    }
+                }
+                _ => return Err(self.unexpected(&[Token::Comma, Token::RightCurlyBrace])),
+            };
+        }
+        self.eat(Token::RightCurlyBrace)?;
+        Ok(variants)
+    }
+    fn parse_bound(&mut self) -> Option<i64> {
+        if let Token::Integer(n) = self.cur {
+            self.advance();
+            Some(n)
+        } else {
+            None
+        }
+    }
+    // 解析 (下限..上限) 或 (min=下限, max=上限) ，上下限皆可省略
+    fn parse_range(&mut self) -> ForceResult<Bounds<i64>> {
+        let start = self.cur_span().start;
+        self.eat(Token::LeftParenthesis)?;
+        let bounds = if let Token::Identifier(_) = self.cur {
+            self.parse_named_bounds()?
+        } else {
+            let min = self.parse_bound();
+            self.eat(Token::DotDot)?;
+            let max = self.parse_bound();
+            Bounds { min, max }
+        };
+        let end = self.cur_span().end;
+        self.eat(Token::RightParenthesis)?;
+        match (bounds.min, bounds.max) {
+            (Some(min), Some(max)) if min > max => {
+                Err(ForceError::InvalidRange { span: start..end })
+            }
+            _ => Ok(bounds),
+        }
+    }
+    // min 與 max 各至多一個，以逗號分隔
+    fn parse_named_bounds(&mut self) -> ForceResult<Bounds<i64>> {
+        let mut bounds = Bounds::default();
+        loop {
+            let span = self.cur_span();
+            let bound = match &*self.get_identifier_str()? {
+                "min" => &mut bounds.min,
+                "max" => &mut bounds.max,
+                _ => return Err(ForceError::InvalidRange { span }),
+            };
+            if bound.is_some() {
+                return Err(ForceError::InvalidRange { span });
+            }
+            self.eat(Token::Equal)?;
+            *bound = match self.parse_bound() {
+                Some(n) => Some(n),
+                None => return Err(self.unexpected(&[Token::Integer(0)])),
+            };
+            match self.cur {
+                Token::RightParenthesis => break,
+                Token::Comma => {
+                    self.advance();
+                    if self.trailing_comma(&Token::RightParenthesis) {
+                        break;
+                    }
+                }
+                _ => return Err(self.unexpected(&[Token::Comma, Token::RightParenthesis])),
+            }
+        }
+        Ok(bounds)
+    }
+    fn parse_length(&mut self) -> ForceResult<Bounds<usize>> {
+        let start = self.cur_span().start;
+        let bounds = self.parse_range()?;
+        let span = start..self.prev_end;
+        let to_length = |n: Option<i64>| match n {
+            Some(n) if n < 0 => Err(ForceError::InvalidRange { span: span.clone() }),
+            n => Ok(n.map(|n| n as usize)),
+        };
+        Ok(Bounds {
+            min: to_length(bounds.min)?,
+            max: to_length(bounds.max)?,
+        })
     }
     fn parse_datatype(&mut self) -> ForceResult<DataType> {
         match self.cur {
             Token::Number => {
                 self.advance();
-                Ok(DataType::Number)
+                let bounds = if let Token::LeftParenthesis = self.cur {
+                    self.parse_range()?
+                } else {
+                    Bounds::default()
+                };
+                Ok(DataType::Number(bounds))
             }
             Token::OneLine => {
                 self.advance();
-                Ok(DataType::OneLine)
+                let bounds = if let Token::LeftParenthesis = self.cur {
+                    self.parse_length()?
+                } else {
+                    Bounds::default()
+                };
+                Ok(DataType::OneLine(bounds))
             }
             Token::Text => {
                 self.advance();
-                match self.cur.clone() {
-                    Token::Regex(s) => {
-                        self.advance();
-                        let regex =
-                            Regex::new(&s).map_err(|_e| ForceError::InvalidRegex { regex: s })?;
-                        Ok(DataType::Text(Some(regex)))
+                // 識別子之後若還有域名、? 或 > ，則此識別子為模式名
+                let is_pattern = matches!(
+                    self.peek(),
+                    Some(Token::Identifier(_) | Token::Question | Token::GreaterThan)
+                );
+                match self.cur {
+                    Token::Regex(_) => {
+                        let span = self.cur_span();
+                        let s = self.advance_str();
+                        Ok(DataType::Text(Some(self.compile_regex(&s, span)?)))
+                    }
+                    Token::Identifier(_) if is_pattern => {
+                        let span = self.cur_span();
+                        let pattern = self.get_identifier_str()?;
+                        match self.patterns.get(pattern.as_ref()) {
+                            Some(regex) => Ok(DataType::Text(Some(regex.clone()))),
+                            None => Err(ForceError::UnknownPattern {
+                                pattern: pattern.into_owned(),
+                                span,
+                            }),
+                        }
                     }
                     _ => Ok(DataType::Text(None)),
                 }
@@ -127,7 +636,18 @@ impl Parser {
             Token::Bond => {
                 self.advance();
                 let bondee = self.parse_bondee()?;
-                Ok(DataType::Bond(bondee))
+                let mut relation = Relation::default();
+                match self.cur {
+                    Token::One => {
+                        self.advance();
+                        relation.cardinality = Cardinality::One;
+                    }
+                    Token::Many => {
+                        self.advance();
+                    }
+                    _ => {}
+                }
+                Ok(DataType::Bond(bondee, relation))
             }
             Token::TaggedBond => {
                 self.advance();
@@ -135,63 +655,787 @@ impl Parser {
                 let tags = self.parse_tags()?;
                 Ok(DataType::TaggedBond(bondee, tags))
             }
-            _ => Err(ForceError::NoMeet {
-                expect: "型別".to_owned(),
-                fact: self.cur.clone(),
-            }),
+            Token::Enum => {
+                self.advance();
+                Ok(DataType::Enum(self.parse_variants()?))
+            }
+            Token::Bool => {
+                self.advance();
+                Ok(DataType::Bool)
+            }
+            Token::DateTime => {
+                self.advance();
+                Ok(DataType::DateTime)
+            }
+            Token::Array => {
+                let span = self.cur_span();
+                self.advance();
+                self.eat(Token::LessThan)?;
+                self.list_depth += 1;
+                let inner = self
+                    .check_depth(self.list_depth, span)
+                    .and_then(|_| self.parse_datatype());
+                self.list_depth -= 1;
+                let inner = Box::new(inner?);
+                self.eat(Token::GreaterThan)?;
+                let length = if let Token::LeftParenthesis = self.cur {
+                    self.parse_length()?
+                } else {
+                    Bounds::default()
+                };
+                Ok(DataType::Array { inner, length })
+            }
+            _ => Err(self.unexpected(&[
+                Token::OneLine,
+                Token::Text,
+                Token::Number,
+                Token::Bond,
+                Token::TaggedBond,
+                Token::Array,
+                Token::Enum,
+                Token::Bool,
+                Token::DateTime,
+            ])),
         }
     }
-    pub fn parse_category(&mut self) -> ForceResult<Category> {
-        let start = self.tokens[self.count].1.start;
+    // 解析屬性名之後的 (引數, ...) ，引數為識別子或字串，沒有括號時為空
+    fn parse_attribute_arguments(&mut self) -> ForceResult<Vec<String>> {
+        let mut arguments = Vec::new();
+        if self.cur != Token::LeftParenthesis {
+            return Ok(arguments);
+        }
+        self.advance();
+        while self.cur != Token::RightParenthesis {
+            match self.cur {
+                Token::Identifier(_) | Token::StringLiteral(_) => {
+                    arguments.push(self.advance_string())
+                }
+                _ => {
+                    return Err(self.unexpected(&[
+                        Token::Identifier("".into()),
+                        Token::StringLiteral("".into()),
+                        Token::RightParenthesis,
+                    ]))
+                }
+            }
+            match self.cur {
+                Token::Comma => {
+                    self.advance();
+                    if self.cur == Token::RightParenthesis && !self.options.trailing_commas {
+                        return Err(self.unexpected(&[
+                            Token::Identifier("".into()),
+                            Token::StringLiteral("".into()),
+                        ]));
+                    }
+                }
+                Token::RightParenthesis => {}
+                _ => return Err(self.unexpected(&[Token::Comma, Token::RightParenthesis])),
+            }
+        }
+        self.advance();
+        Ok(arguments)
+    }
+    // 解析分類前的屬性 @名稱 或 @名稱(引數, ...)
+    fn parse_category_attributes(&mut self) -> ForceResult<Vec<Attribute>> {
+        let mut attributes: Vec<Attribute> = Vec::new();
+        while let Token::At = self.cur {
+            let start = self.cur_span().start;
+            self.advance();
+            let end = self.cur_span().end;
+            let name = self.get_identifier()?;
+            if attributes.iter().any(|attribute| attribute.name == name) {
+                return Err(ForceError::DuplicateAttribute {
+                    attribute: name,
+                    span: start..end,
+                });
+            }
+            let arguments = self.parse_attribute_arguments()?;
+            attributes.push(Attribute { name, arguments });
+        }
+        Ok(attributes)
+    }
+    // 解析域前的屬性，嚴格模式下拒絕 FIELD_ATTRIBUTES 以外的屬性
+    fn parse_field_attributes(&mut self) -> ForceResult<FieldAttributes> {
+        let mut attributes = FieldAttributes::default();
+        while let Token::At = self.cur {
+            let start = self.cur_span().start;
+            self.advance();
+            let end = self.cur_span().end;
+            let name = self.get_identifier_str()?;
+            let repeated = match name.as_ref() {
+                "title" => attributes.title.is_some(),
+                "unique" => attributes.unique,
+                "key" => attributes.key.is_some(),
+                _ => false,
+            };
+            if repeated {
+                return Err(ForceError::DuplicateAttribute {
+                    attribute: name.into_owned(),
+                    span: start..end,
+                });
+            }
+            match name.as_ref() {
+                "title" => attributes.title = Some(start..end),
+                "unique" => attributes.unique = true,
+                "key" => attributes.key = Some(start..end),
+                _ if !self.options.unknown_attributes
+                    && !FIELD_ATTRIBUTES.contains(&name.as_ref()) =>
+                {
+                    return Err(ForceError::UnknownAttribute {
+                        attribute: name.into_owned(),
+                        span: start..end,
+                    })
+                }
+                _ => {
+                    let name = name.into_owned();
+                    if attributes
+                        .others
+                        .iter()
+                        .any(|attribute| attribute.name == name)
+                    {
+                        return Err(ForceError::DuplicateAttribute {
+                            attribute: name,
+                            span: start..end,
+                        });
+                    }
+                    let arguments = self.parse_attribute_arguments()?;
+                    attributes.others.push(Attribute { name, arguments });
+                }
+            }
+        }
+        Ok(attributes)
+    }
+    fn parse_default(&mut self, field: &str, datatype: &DataType) -> ForceResult<Option<Value>> {
+        if self.cur != Token::Equal {
+            return Ok(None);
+        }
+        self.advance();
+        let span = self.cur_span();
+        let value = match self.cur {
+            Token::Integer(n) => {
+                self.advance();
+                Value::from(n)
+            }
+            Token::StringLiteral(_) => Value::from(self.advance_string()),
+            Token::True | Token::False => Value::from(self.advance() == Token::True),
+            _ => {
+                return Err(self.unexpected(&[
+                    Token::Integer(0),
+                    Token::StringLiteral("".into()),
+                    Token::True,
+                    Token::False,
+                ]))
+            }
+        };
+        if (DefaultValidator {}).validate_datatype(datatype, &value) {
+            Ok(Some(value))
+        } else {
+            Err(ForceError::InvalidDefault {
+                field: field.to_owned(),
+                span,
+            })
+        }
+    }
+    // 計算域的算式，加減低於乘除，皆為左結合
+    // 括號、負號與每個串接的運算子都使算式樹加深一層，受 max_depth 限制
+    fn parse_expr(&mut self, mut depth: usize) -> ForceResult<Expr> {
+        let mut left = self.parse_term(depth)?;
+        loop {
+            let op = match self.cur {
+                Token::Plus => BinaryOp::Add,
+                Token::Minus => BinaryOp::Sub,
+                // 讚數 -1 中的 -1 是一個整數
+                // i64::MIN 無法取負，改為加上負數
+                Token::Integer(n) if n < 0 => {
+                    depth += 1;
+                    self.check_depth(depth, self.cur_span())?;
+                    self.advance();
+                    let (op, right) = match n.checked_neg() {
+                        Some(n) => (BinaryOp::Sub, Expr::Number(n)),
+                        None => (BinaryOp::Add, Expr::Number(n)),
+                    };
+                    let right = self.parse_term_rest(right, depth)?;
+                    left = Expr::Binary {
+                        op,
+                        left: Box::new(left),
+                        right: Box::new(right),
+                    };
+                    continue;
+                }
+                _ => return Ok(left),
+            };
+            depth += 1;
+            self.check_depth(depth, self.cur_span())?;
+            self.advance();
+            let right = self.parse_term(depth)?;
+            left = Expr::Binary {
+                op,
+                left: Box::new(left),
+                right: Box::new(right),
+            };
+        }
+    }
+    fn parse_term(&mut self, depth: usize) -> ForceResult<Expr> {
+        let first = self.parse_factor(depth)?;
+        self.parse_term_rest(first, depth)
+    }
+    fn parse_term_rest(&mut self, mut left: Expr, mut depth: usize) -> ForceResult<Expr> {
+        loop {
+            let op = match self.cur {
+                Token::Star => BinaryOp::Mul,
+                Token::Slash => BinaryOp::Div,
+                _ => return Ok(left),
+            };
+            depth += 1;
+            self.check_depth(depth, self.cur_span())?;
+            self.advance();
+            let right = self.parse_factor(depth)?;
+            left = Expr::Binary {
+                op,
+                left: Box::new(left),
+                right: Box::new(right),
+            };
+        }
+    }
+    fn parse_factor(&mut self, depth: usize) -> ForceResult<Expr> {
+        match self.cur {
+            Token::Integer(n) => {
+                self.advance();
+                Ok(Expr::Number(n))
+            }
+            Token::Identifier(_) => Ok(Expr::Field(self.get_identifier()?)),
+            Token::LeftParenthesis => {
+                self.check_depth(depth + 1, self.cur_span())?;
+                self.advance();
+                let expr = self.parse_expr(depth + 1)?;
+                self.eat(Token::RightParenthesis)?;
+                Ok(expr)
+            }
+            // - 1 與 -1 相同，-(1) 則保留負號
+            Token::Minus => {
+                self.check_depth(depth + 1, self.cur_span())?;
+                self.advance();
+                if let Token::Integer(n) = self.cur {
+                    if let Some(n) = n.checked_neg() {
+                        self.advance();
+                        return Ok(Expr::Number(n));
+                    }
+                }
+                Ok(Expr::Negate(Box::new(self.parse_factor(depth + 1)?)))
+            }
+            _ => Err(self.unexpected(&[
+                Token::Integer(0),
+                Token::Identifier("".into()),
+                Token::LeftParenthesis,
+                Token::Minus,
+            ])),
+        }
+    }
+    // 連續的文件註解以換行串接
+    fn parse_docs(&mut self) -> Option<String> {
+        let mut docs = Vec::new();
+        while let Token::DocComment(_) = self.cur {
+            docs.push(self.advance_string());
+        }
+        if docs.is_empty() {
+            None
+        } else {
+            Some(docs.join("\n"))
+        }
+    }
+    fn parse_field(
+        &mut self,
+        doc: Option<String>,
+        category: &str,
+        fields: &[Field],
+        title_field: &mut Option<String>,
+    ) -> ForceResult<Field> {
+        let attributes = self.parse_field_attributes()?;
+        if let (Some(span), Token::RightCurlyBrace) = (&attributes.title, &self.cur) {
+            return Err(ForceError::TitleWithoutField {
+                category: category.to_owned(),
+                span: span.clone(),
+            });
+        }
+        let computed = self.cur == Token::Computed;
+        if computed {
+            self.advance();
+            if self.cur != Token::Number {
+                return Err(self.unexpected(&[Token::Number]));
+            }
+        }
+        let datatype = self.parse_datatype()?;
+        let optional = self.cur == Token::Question;
+        if optional {
+            self.advance();
+        }
+        let span = self.cur_span();
         let name = self.get_identifier()?;
-        let mut fields = Vec::new();
+        self.check_legacy(span.start)?;
+        if fields.iter().any(|field| field.name == name) {
+            return Err(ForceError::DuplicateField {
+                category: category.to_owned(),
+                field: name,
+                span,
+            });
+        }
+        let mut datatype = datatype;
+        if let Token::Inverse = self.cur {
+            let span = self.cur_span();
+            self.advance();
+            let inverse = self.get_identifier()?;
+            match datatype.relation_mut() {
+                Some(relation) => relation.inverse = Some(inverse),
+                None => {
+                    return Err(ForceError::NoMeet {
+                        expect: "鍵結".to_owned(),
+                        fact: Token::Inverse,
+                        span,
+                    })
+                }
+            }
+        }
+        let (default, computed) = if computed {
+            self.eat(Token::Equal)?;
+            (None, Some(self.parse_expr(0)?))
+        } else {
+            (self.parse_default(&name, &datatype)?, None)
+        };
+        if let Some(span) = attributes.key.clone() {
+            if fields.iter().any(|field| field.key) {
+                return Err(ForceError::DuplicateKey {
+                    category: category.to_owned(),
+                    span: Some(span),
+                });
+            }
+        }
+        if let Some(span) = attributes.title {
+            if title_field.is_some() {
+                return Err(ForceError::DuplicateTitle {
+                    category: category.to_owned(),
+                    span,
+                });
+            }
+            *title_field = Some(name.clone());
+        }
+        Ok(Field {
+            datatype,
+            name,
+            optional,
+            default,
+            doc,
+            unique: attributes.unique,
+            key: attributes.key.is_some(),
+            attributes: attributes.others,
+            computed,
+        })
+    }
+    pub fn parse_category(&mut self) -> ForceResult<Category> {
+        self.check_size()?;
+        let doc = self.parse_docs();
+        let result = self.parse_category_body(doc);
+        self.check_lexer(result)
+    }
+    // 分類的文件註解已由呼叫者解析
+    // 在分類名之後出錯時，已解析的部分記入 partials
+    fn parse_category_body(&mut self, doc: Option<String>) -> ForceResult<Category> {
+        let start = self.span.start;
+        let attributes = self.parse_category_attributes()?;
+        let mut name = self.parse_name()?;
+        if !self.namespace.is_empty() {
+            name = format!("{}.{}", self.namespace.join("."), name);
+        }
+        let mut category = Category {
+            name,
+            fields: Vec::new(),
+            title_field: None,
+            doc,
+            parents: Vec::new(),
+            attributes,
+            source: String::new(),
+        };
+        match self.parse_category_rest(&mut category) {
+            Ok(end) => {
+                category.source = self.source[start..end].to_string();
+                Ok(category)
+            }
+            Err(error) => {
+                category.source = self.source[start..self.prev_end].to_string();
+                self.partials.push(category);
+                Err(error)
+            }
+        }
+    }
+    // 父分類與大括號內的域，回傳 } 的結尾
+    fn parse_category_rest(&mut self, category: &mut Category) -> ForceResult<usize> {
+        if let Token::Colon = self.cur {
+            self.advance();
+            category.parents.push(self.parse_name()?);
+            while let Token::Comma = self.cur {
+                self.advance();
+                if self.trailing_comma(&Token::LeftCurlyBrace) {
+                    break;
+                }
+                category.parents.push(self.parse_name()?);
+            }
+        }
         self.eat(Token::LeftCurlyBrace)?;
         loop {
-            if let Token::RightCurlyBrace = self.cur {
-                break;
-            } else {
-                let datatype = self.parse_datatype()?;
-                let name = self.get_identifier()?;
-                fields.push(Field { datatype, name });
+            // 分類結尾前的文件註解不屬於任何域，直接忽略
+            let depth = self.cst_depth();
+            let checkpoint = self.checkpoint();
+            let doc = self.parse_docs();
+            match self.cur {
+                Token::RightCurlyBrace => break,
+                Token::End => return Err(self.unexpected(&[Token::RightCurlyBrace])),
+                _ => {}
             }
+            self.start_node_at(checkpoint, NodeKind::Field);
+            let field = self.parse_field(
+                doc,
+                &category.name,
+                &category.fields,
+                &mut category.title_field,
+            )?;
+            self.finish_nodes(depth);
+            category.fields.push(field);
         }
-        let end = self.tokens[self.count].1.end;
+        let end = self.span.end;
         self.eat(Token::RightCurlyBrace)?;
-        Ok(Category {
-            name,
-            fields,
-            source: self.source[start..end].to_string(),
-        })
+        Ok(end)
+    }
+    // 模式 名稱 = /正則表達式/
+    fn parse_pattern(&mut self) -> ForceResult<()> {
+        self.eat(Token::Pattern)?;
+        let span = self.cur_span();
+        let name = self.get_identifier()?;
+        if self.patterns.contains_key(&name) {
+            return Err(ForceError::DuplicatePattern {
+                pattern: name,
+                span,
+            });
+        }
+        self.eat(Token::Equal)?;
+        match self.cur {
+            Token::Regex(_) => {
+                let span = self.cur_span();
+                let s = self.advance_str();
+                let regex = self.compile_regex(&s, span)?;
+                self.patterns.insert(name, regex);
+                Ok(())
+            }
+            _ => Err(self.unexpected(&[Token::Regex("".into())])),
+        }
+    }
+    fn patterns(&self) -> Patterns {
+        self.patterns
+            .iter()
+            .map(|(name, regex)| (name.clone(), regex.as_str().to_owned()))
+            .collect()
+    }
+    fn insert_category(
+        &self,
+        categories: &mut Categories,
+        category: Category,
+        span: Span,
+    ) -> ForceResult<()> {
+        if categories.contains_key(&category.name) && !self.options.overwrite_duplicates {
+            return Err(ForceError::DuplicateCategory {
+                category: category.name,
+                span,
+            });
+        }
+        categories.insert(category.name.clone(), category);
+        Ok(())
+    }
+    // 匯入 "路徑" ，被匯入檔案的分類與模式併入目前的檔案
+    fn parse_import(&mut self, categories: &mut Categories) -> ForceResult<()> {
+        let start = self.span.start;
+        self.eat(Token::Import)?;
+        let path = match self.cur {
+            Token::StringLiteral(_) => self.advance_string(),
+            _ => return Err(self.unexpected(&[Token::StringLiteral("".into())])),
+        };
+        let span = start..self.prev_end;
+        let loader = self.loader.ok_or_else(|| ForceError::LoadFailed {
+            path: path.clone(),
+            message: "未提供載入器".to_owned(),
+            span: span.clone(),
+        })?;
+        let path = join_path(&self.path, &path);
+        if self.importing.contains(&path) {
+            return Err(ForceError::ImportCycle { path, span });
+        }
+        if let Some(patterns) = self.loaded.get(&path).cloned() {
+            return self.merge_patterns(patterns, span);
+        }
+        let source = loader
+            .load(&path)
+            .map_err(|message| ForceError::LoadFailed {
+                path: path.clone(),
+                message,
+                span: span.clone(),
+            })?;
+        let mut child = Parser::with_loader(&source, &path, loader);
+        child.set_options(self.options.clone());
+        child.importing = self.importing.clone();
+        child.importing.push(path.clone());
+        child.loaded = mem::take(&mut self.loaded);
+        let result = child.parse_categories();
+        let result = child.check_lexer(result);
+        self.loaded = mem::take(&mut child.loaded);
+        let imported = result.map_err(|error| ForceError::ImportError {
+            path: path.clone(),
+            error: Box::new(error),
+            span: span.clone(),
+        })?;
+        let mut patterns: Vec<(String, Regex)> = child.patterns.into_iter().collect();
+        patterns.sort_by(|a, b| a.0.cmp(&b.0));
+        self.loaded.insert(path, patterns.clone());
+        self.merge_patterns(patterns, span.clone())?;
+        let mut imported: Vec<Category> = imported.into_values().collect();
+        imported.sort_by(|a, b| a.name.cmp(&b.name));
+        for category in imported {
+            self.insert_category(categories, category, span.clone())?;
+        }
+        Ok(())
+    }
+    // 同名且相同的模式視為同一個
+    fn merge_patterns(&mut self, patterns: Vec<(String, Regex)>, span: Span) -> ForceResult<()> {
+        for (name, regex) in patterns {
+            match self.patterns.get(&name) {
+                Some(existing) if existing.as_str() == regex.as_str() => {}
+                Some(_) => {
+                    return Err(ForceError::DuplicatePattern {
+                        pattern: name,
+                        span,
+                    })
+                }
+                None => {
+                    self.patterns.insert(name, regex);
+                }
+            }
+        }
+        Ok(())
+    }
+    // 命名空間 名稱 { ，其內的分類名稱皆加上前綴，直到對應的 } 為止
+    fn parse_namespace(&mut self) -> ForceResult<()> {
+        let start = self.cur_span().start;
+        self.eat(Token::Namespace)?;
+        let name = self.parse_name()?;
+        self.check_depth(self.namespace.len() + 1, start..self.prev_end)?;
+        self.eat(Token::LeftCurlyBrace)?;
+        self.namespace.push(name);
+        Ok(())
+    }
+    // 處理頂層的 命名空間 與結束命名空間的 } ，若目前 token 不是兩者之一則回傳 false
+    fn parse_namespace_boundary(&mut self) -> ForceResult<bool> {
+        match self.cur {
+            Token::Namespace => self.parse_namespace()?,
+            Token::RightCurlyBrace if !self.namespace.is_empty() => {
+                self.advance();
+                self.namespace.pop();
+            }
+            Token::End if !self.namespace.is_empty() => {
+                self.namespace.clear();
+                self.eat(Token::RightCurlyBrace)?;
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
     }
     fn parse_categories(&mut self) -> ForceResult<Categories> {
-        let mut categories = HashMap::new();
+        self.check_size()?;
+        let mut categories = Map::new();
         loop {
-            if let Token::End = self.cur {
-                break;
-            } else {
-                let category = self.parse_category()?;
-                categories.insert(category.name.clone(), category);
+            // 檔案結尾的文件註解直接忽略
+            let doc = self.parse_docs();
+            if self.parse_namespace_boundary()? {
+                continue;
+            }
+            match self.cur {
+                Token::End => break,
+                Token::Import => self.parse_import(&mut categories)?,
+                Token::Pattern => self.parse_pattern()?,
+                _ => {
+                    let span = self.cur_span();
+                    let category = self.parse_category_body(doc)?;
+                    self.insert_category(&mut categories, category, span)?;
+                }
             }
         }
-        return Ok(categories);
+        Ok(categories)
+    }
+    // 匯入的分類可能繼承其他檔案的分類，故繼承的展開交由呼叫者在所有檔案併入後進行
+    pub(crate) fn parse_unflattened(&mut self) -> ForceResult<Force> {
+        let result = self.parse_categories();
+        let categories = self.check_lexer(result)?;
+        Ok(Force {
+            categories,
+            patterns: self.patterns(),
+        })
     }
     pub fn parse(&mut self) -> ForceResult<Force> {
-        let categories = self.parse_categories()?;
-        return Ok(Force { categories });
+        let mut force = self.parse_unflattened()?;
+        link(&mut force.categories)?;
+        Ok(force)
+    }
+    // 若已進入分類的大括號，跳到與之對應的 } 之後，否則跳過下一組大括號
+    // 一開始就遇到多餘的 } 時只跳過它
+    // 命名空間的大括號不算在內
+    fn synchronize(&mut self) {
+        let base = self.namespace.len();
+        if self.depth == base {
+            loop {
+                match self.cur {
+                    Token::End => return,
+                    Token::RightCurlyBrace => {
+                        self.advance();
+                        return;
+                    }
+                    Token::LeftCurlyBrace => break,
+                    _ => {
+                        self.advance();
+                    }
+                }
+            }
+        }
+        while self.cur != Token::End {
+            self.advance();
+            if self.depth == base {
+                break;
+            }
+        }
+    }
+    // 遇到錯誤時跳過該分類繼續解析，回傳成功解析的分類以及所有錯誤
+    pub fn parse_all_errors(&mut self) -> (Force, Vec<ForceError>) {
+        let (mut categories, mut lex_errors, mut errors) = self.parse_unlinked_all_errors();
+        if let Err(error) = link(&mut categories) {
+            errors.push(error);
+        }
+        lex_errors.append(&mut errors);
+        (
+            Force {
+                categories,
+                patterns: self.patterns(),
+            },
+            lex_errors,
+        )
+    }
+    pub fn parse_partial(&mut self) -> PartialForce {
+        self.partials.clear();
+        let (force, errors) = self.parse_all_errors();
+        PartialForce {
+            force,
+            partial: mem::take(&mut self.partials),
+            errors,
+        }
+    }
+    // 不展開繼承的 parse_all_errors ，詞法錯誤與其餘錯誤分開回傳
+    pub(crate) fn parse_unlinked_all_errors(
+        &mut self,
+    ) -> (Categories, Vec<ForceError>, Vec<ForceError>) {
+        let mut errors = Vec::new();
+        let mut categories = Map::new();
+        if let Err(error) = self.check_size() {
+            return (categories, Vec::new(), vec![error]);
+        }
+        loop {
+            let depth = self.cst_depth();
+            let checkpoint = self.checkpoint();
+            let doc = self.parse_docs();
+            match self.parse_namespace_boundary() {
+                Ok(true) => continue,
+                Ok(false) => {}
+                Err(error) => {
+                    errors.push(error);
+                    continue;
+                }
+            }
+            match self.cur {
+                Token::End => break,
+                Token::Import => {
+                    self.start_node_at(checkpoint, NodeKind::Import);
+                    if let Err(error) = self.parse_import(&mut categories) {
+                        errors.push(error);
+                    }
+                    self.finish_nodes(depth);
+                    continue;
+                }
+                Token::Pattern => {
+                    self.start_node_at(checkpoint, NodeKind::Pattern);
+                    if let Err(error) = self.parse_pattern() {
+                        errors.push(error);
+                        // 跳到下一個模式或分類
+                        self.advance();
+                        while !matches!(
+                            self.cur,
+                            Token::Pattern
+                                | Token::Namespace
+                                | Token::RightCurlyBrace
+                                | Token::Identifier(_)
+                                | Token::End
+                        ) {
+                            self.advance();
+                        }
+                    }
+                    self.finish_nodes(depth);
+                    continue;
+                }
+                _ => {}
+            }
+            let span = self.cur_span();
+            self.start_node_at(checkpoint, NodeKind::Category);
+            match self.parse_category_body(doc) {
+                Ok(category) => {
+                    if let Err(error) = self.insert_category(&mut categories, category, span) {
+                        errors.push(error);
+                    }
+                }
+                Err(error) => {
+                    // 詞法錯誤另外回報
+                    match &error {
+                        ForceError::Unexpected {
+                            fact: Token::Error, ..
+                        } => {}
+                        _ => errors.push(error),
+                    }
+                    self.synchronize();
+                }
+            }
+            self.finish_nodes(depth);
+        }
+        (categories, mem::take(&mut self.lex_errors), errors)
     }
 }
 
+pub fn parse_all_errors(source: &str) -> (Force, Vec<ForceError>) {
+    Parser::new(source).parse_all_errors()
+}
+
 pub fn parse(source: &str) -> ForceResult<Force> {
     Parser::new(source).parse()
 }
 
+pub fn parse_partial(source: &str) -> PartialForce {
+    Parser::new(source).parse_partial()
+}
+
 pub fn parse_category(source: &str) -> ForceResult<Category> {
     Parser::new(source).parse_category()
 }
 
+impl core::str::FromStr for Force {
+    type Err = ForceError;
+    fn from_str(source: &str) -> ForceResult<Force> {
+        parse(source)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::lexer::Location;
     #[test]
     fn test_simple_category() -> ForceResult<()> {
         let source = "新聞 {單行 記者 單行 網址}";
@@ -203,14 +1447,32 @@ mod test {
             name: "新聞".to_owned(),
             fields: vec![
                 Field {
-                    datatype: DataType::OneLine,
+                    datatype: DataType::OneLine(Bounds::default()),
                     name: "記者".to_owned(),
+                    optional: false,
+                    default: None,
+                    doc: None,
+                    unique: false,
+                    key: false,
+                    attributes: vec![],
+                    computed: None,
                 },
                 Field {
-                    datatype: DataType::OneLine,
+                    datatype: DataType::OneLine(Bounds::default()),
                     name: "網址".to_owned(),
+                    optional: false,
+                    default: None,
+                    doc: None,
+                    unique: false,
+                    key: false,
+                    attributes: vec![],
+                    computed: None,
                 },
             ],
+            title_field: None,
+            doc: None,
+            parents: vec![],
+            attributes: vec![],
             source: source.to_owned(),
         };
         assert_eq!(force.categories.get("新聞").unwrap(), ans);
@@ -229,10 +1491,881 @@ mod test {
             fields: vec![Field {
                 datatype: DataType::Text(Some(Regex::new("我的志願是.+").unwrap())),
                 name: "文章".to_owned(),
+                optional: false,
+                default: None,
+                doc: None,
+                unique: false,
+                key: false,
+                attributes: vec![],
+                computed: None,
             }],
+            title_field: None,
+            doc: None,
+            parents: vec![],
+            attributes: vec![],
             source: source.to_owned(),
         };
         assert_eq!(&parse_category(source).unwrap(), ans);
         Ok(())
     }
+    #[test]
+    fn test_title_field() -> ForceResult<()> {
+        let source = "新聞 {單行 記者 @title 單行 標題}";
+        let category = parse_category(source)?;
+        assert_eq!(category.title_field, Some("標題".to_owned()));
+        assert_eq!(category.title_field().unwrap().name, "標題");
+
+        let category = parse_category("新聞 {單行 記者}")?;
+        assert_eq!(category.title_field(), None);
+        Ok(())
+    }
+    #[test]
+    fn test_title_without_field() {
+        let source = "新聞 {單行 記者 @title}";
+        match parse_category(source) {
+            Err(ForceError::TitleWithoutField { category, span }) => {
+                assert_eq!(category, "新聞");
+                assert_eq!(&source[span], "@title");
+            }
+            other => panic!("預期 TitleWithoutField ，但得到 {:?}", other),
+        }
+    }
+    #[test]
+    fn test_duplicate_title() {
+        let source = "新聞 {@title 單行 記者 @title 單行 標題}";
+        match parse_category(source) {
+            Err(ForceError::DuplicateTitle { category, span }) => {
+                assert_eq!(category, "新聞");
+                assert_eq!(span.start, source.rfind('@').unwrap());
+            }
+            other => panic!("預期 DuplicateTitle ，但得到 {:?}", other),
+        }
+    }
+    #[test]
+    fn test_error_span() {
+        let source = "新聞 {\n    單行 記者\n    單行 網址 }\n問卦 {\n    文本 內文\n    數字 }";
+        match parse(source) {
+            Err(ForceError::Unexpected { span, .. }) => {
+                assert_eq!(&source[span.clone()], "}");
+                let location = Location::new(source, span.start);
+                assert_eq!((location.line, location.column), (6, 8));
+            }
+            other => panic!("預期 Unexpected ，但得到 {:?}", other),
+        }
+    }
+    #[test]
+    fn test_array() -> ForceResult<()> {
+        let category =
+            parse_category("文章 {列表<單行> 標籤 列表<鍵結[*]>(1..5) 來源 列表<數字>(..3) 分數}")?;
+        let datatypes: Vec<&DataType> = category.fields.iter().map(|f| &f.datatype).collect();
+        assert_eq!(
+            datatypes,
+            vec![
+                &DataType::Array {
+                    inner: Box::new(DataType::OneLine(Bounds::default())),
+                    length: Bounds::default()
+                },
+                &DataType::Array {
+                    inner: Box::new(DataType::Bond(Bondee::All, Relation::default())),
+                    length: Bounds {
+                        min: Some(1),
+                        max: Some(5)
+                    }
+                },
+                &DataType::Array {
+                    inner: Box::new(DataType::Number(Bounds::default())),
+                    length: Bounds {
+                        min: None,
+                        max: Some(3)
+                    }
+                },
+            ]
+        );
+        assert!(matches!(
+            parse_category("文章 {列表<單行>(5..1) 標籤}"),
+            Err(ForceError::InvalidRange { .. })
+        ));
+        assert!(matches!(
+            parse_category("文章 {列表<單行>(-1..) 標籤}"),
+            Err(ForceError::InvalidRange { .. })
+        ));
+        Ok(())
+    }
+    #[test]
+    fn test_optional() -> ForceResult<()> {
+        let category = parse_category("文章 {數字? 分數 列表<單行>? 標籤 單行 標題}")?;
+        let optional: Vec<bool> = category.fields.iter().map(|f| f.optional).collect();
+        assert_eq!(optional, vec![true, true, false]);
+        Ok(())
+    }
+    #[test]
+    fn test_enum() -> ForceResult<()> {
+        let category = parse_category("文章 {列舉 { 草稿, 已發佈, 封存 } 狀態}")?;
+        assert_eq!(
+            category.fields[0].datatype,
+            DataType::Enum(vec![
+                "草稿".to_owned(),
+                "已發佈".to_owned(),
+                "封存".to_owned()
+            ])
+        );
+        assert!(matches!(
+            parse_category("文章 {列舉 {草稿, 草稿} 狀態}"),
+            Err(ForceError::DuplicateVariant { .. })
+        ));
+        assert!(parse_category("文章 {列舉 {} 狀態}").is_err());
+        Ok(())
+    }
+    #[test]
+    fn test_default() -> ForceResult<()> {
+        let source = r#"文章 {數字 讚數 = 0 單行 標題 = "未命名" 列舉 {草稿, 已發佈} 狀態 = "草稿" 單行 作者}"#;
+        let category = parse_category(source)?;
+        let defaults: Vec<Option<Value>> =
+            category.fields.iter().map(|f| f.default.clone()).collect();
+        assert_eq!(
+            defaults,
+            vec![
+                Some(Value::from(0)),
+                Some(Value::from("未命名")),
+                Some(Value::from("草稿")),
+                None
+            ]
+        );
+        for source in &[
+            r#"文章 {數字 讚數 = "0"}"#,
+            r#"文章 {單行 標題 = "a\nb"}"#,
+            r#"文章 {列舉 {草稿} 狀態 = "封存"}"#,
+            r#"文章 {鍵結[*] 來源 = "1"}"#,
+        ] {
+            assert!(matches!(
+                parse_category(source),
+                Err(ForceError::InvalidDefault { .. })
+            ));
+        }
+        Ok(())
+    }
+    #[test]
+    fn test_tagged_bond() -> ForceResult<()> {
+        let source = "回覆 {帶籤鍵結[*] { 挺 { 輸能: [1] } 戰 { 輸能: [-1] } 回 {} } 原文}";
+        let category = parse_category(source)?;
+        let tag = |name: &str, transfuse: Vec<i64>| Tag {
+            name: name.to_owned(),
+            transfuse,
+        };
+        assert_eq!(
+            category.fields[0].datatype,
+            DataType::TaggedBond(
+                Bondee::All,
+                vec![tag("挺", vec![1]), tag("戰", vec![-1]), tag("回", vec![])]
+            )
+        );
+        assert!(matches!(
+            parse_category("回覆 {帶籤鍵結[*] { 挺 {} 挺 {} } 原文}"),
+            Err(ForceError::DuplicateTag { .. })
+        ));
+        assert!(parse_category("回覆 {帶籤鍵結[*] { 挺 { 讚 } } 原文}").is_err());
+        assert!(parse_category("回覆 {帶籤鍵結[*] { 挺 {").is_err());
+        Ok(())
+    }
+    #[test]
+    fn test_all_except() -> ForceResult<()> {
+        let source = "管理公告 {} 文章 {} 回覆 {列表<鍵結[* except 管理公告]> 引用}";
+        let force = parse(source)?;
+        let bondee = force.categories["回覆"].fields[0]
+            .datatype
+            .bondee()
+            .unwrap();
+        assert_eq!(bondee, &Bondee::AllExcept(vec!["管理公告".to_owned()]));
+        assert_eq!(force.bond_targets(bondee), vec!["回覆", "文章"]);
+        #[cfg(feature = "std")]
+        assert!(crate::format::format_force(&force).contains("列表<鍵結[* except 管理公告]> 引用"));
+        match parse_category("回覆 {鍵結[* 管理公告] 原文}") {
+            Err(error @ ForceError::Unexpected { .. }) => {
+                assert_eq!(
+                    error.to_string(),
+                    "預期 「except」 或 「]」 ，但得到 識別子"
+                )
+            }
+            other => panic!("預期 Unexpected ，但得到 {:?}", other),
+        }
+        let force = parse("回覆 {鍵結[* except 管理] 原文}")?;
+        assert!(matches!(
+            force.validate().unwrap_err()[..],
+            [ForceError::UnknownBondee { .. }]
+        ));
+        Ok(())
+    }
+    #[test]
+    fn test_parse_partial() {
+        let source = "/// 新聞\n新聞 {單行 記者 @title 單行 標題 數字(0..) 讚} 問卦 {文本 內文 鍵結[ 回 答} \
+                      @隱藏 公告 : 新聞 {布林";
+        let partial = parse_partial(source);
+        assert!(!partial.is_complete());
+        assert_eq!(partial.errors.len(), 2);
+        assert!(partial.force.categories.contains_key("新聞"));
+        let names: Vec<&str> = partial.partial.iter().map(|c| c.name()).collect();
+        assert_eq!(names, vec!["問卦", "公告"]);
+        let fields: Vec<&str> = partial.partial[0].fields.iter().map(|f| f.name()).collect();
+        assert_eq!(fields, vec!["內文"]);
+        assert_eq!(partial.partial[0].source, "問卦 {文本 內文 鍵結[ 回");
+        assert_eq!(partial.partial[1].parents, vec!["新聞".to_owned()]);
+        assert!(partial.partial[1].attribute("隱藏").is_some());
+        let names: Vec<&str> = partial.categories().iter().map(|c| c.name()).collect();
+        assert_eq!(names, vec!["公告", "問卦", "新聞"]);
+        assert!(parse_partial("新聞 {單行 記者}").is_complete());
+    }
+    #[test]
+    fn test_lex_error() {
+        let source = "新聞 {單行 記者 單行 \"網址}";
+        match parse(source) {
+            Err(ForceError::LexError { text, span }) => {
+                assert!(text.starts_with('"'));
+                assert_eq!(span.start, source.find('"').unwrap());
+            }
+            other => panic!("預期 LexError ，但得到 {:?}", other),
+        }
+        assert!(matches!(
+            parse_category(source),
+            Err(ForceError::LexError { .. })
+        ));
+        assert!(matches!(
+            source.parse::<Force>(),
+            Err(ForceError::LexError { .. })
+        ));
+        assert!("新聞 {單行 記者}".parse::<Force>().is_ok());
+    }
+    #[test]
+    fn test_parse_all_errors() {
+        let source = "新聞 {單行 記者 數字}
+            問卦 {文本 內文}
+            解答 {鍵結[問卦 問題 {}}
+            回覆 {文本 內文}
+            留言 {文本 \"內文}";
+        let (force, errors) = parse_all_errors(source);
+        let mut names: Vec<&String> = force.categories.keys().collect();
+        names.sort();
+        assert_eq!(names, vec!["問卦", "回覆"]);
+        assert_eq!(errors.len(), 3);
+        assert!(matches!(errors[0], ForceError::LexError { .. }));
+        assert!(matches!(errors[1], ForceError::Unexpected { .. }));
+        assert!(matches!(errors[2], ForceError::Unexpected { .. }));
+        assert_eq!(&source[errors[1].span().unwrap()], "}");
+        assert_eq!(&source[errors[2].span().unwrap()], "問題");
+
+        let (force, errors) = parse_all_errors("} 新聞 {單行 記者}");
+        assert_eq!(force.categories.len(), 1);
+        assert_eq!(errors.len(), 1);
+
+        // 錯誤發生在巢狀的大括號內
+        let (force, errors) =
+            parse_all_errors("新聞 {帶籤鍵結[*] {挺 {輸能: [甲]}} 原文} 回覆 {文本 內文}");
+        assert_eq!(errors.len(), 1);
+        assert!(force.categories.contains_key("回覆"));
+    }
+    #[test]
+    fn test_doc() -> ForceResult<()> {
+        let source = "/// 新聞
+            /// 來自各大媒體
+            新聞 {
+                /// 記者姓名
+                @title 單行 記者
+                單行 網址
+                /// 沒有對應的域
+            }
+            /// 檔案結尾";
+        let force = parse(source)?;
+        let category = &force.categories["新聞"];
+        assert_eq!(category.doc, Some("新聞\n來自各大媒體".to_owned()));
+        assert!(category.source.starts_with("新聞 {"));
+        assert_eq!(category.fields[0].doc, Some("記者姓名".to_owned()));
+        assert_eq!(category.fields[1].doc, None);
+        Ok(())
+    }
+    #[test]
+    fn test_constraint() -> ForceResult<()> {
+        let category = parse_category("文章 {數字(0..100) 評分 單行(..64) 標題 數字(-5..) 溫度}")?;
+        let datatypes: Vec<&DataType> = category.fields.iter().map(|f| &f.datatype).collect();
+        assert_eq!(
+            datatypes,
+            vec![
+                &DataType::Number(Bounds {
+                    min: Some(0),
+                    max: Some(100)
+                }),
+                &DataType::OneLine(Bounds {
+                    min: None,
+                    max: Some(64)
+                }),
+                &DataType::Number(Bounds {
+                    min: Some(-5),
+                    max: None
+                }),
+            ]
+        );
+        assert_eq!(datatypes[0].to_string(), "數字(0..100)");
+        let named =
+            parse_category("文章 {數字(min=0, max=100) 評分 單行(max=64) 標題 數字(min=-5) 溫度}")?;
+        let named: Vec<&DataType> = named.fields.iter().map(|f| &f.datatype).collect();
+        assert_eq!(named, datatypes);
+        assert_eq!(
+            parse_category("文章 {列表<單行>(max=3, min=1) 標籤}")?.fields[0].datatype,
+            parse_category("文章 {列表<單行>(1..3) 標籤}")?.fields[0].datatype
+        );
+        for source in [
+            "文章 {單行(-1..) 標題}",
+            "文章 {單行(min=-1) 標題}",
+            "文章 {數字(min=5, max=1) 評分}",
+            "文章 {數字(max=1, max=2) 評分}",
+            "文章 {單行(len=64) 標題}",
+        ] {
+            assert!(matches!(
+                parse_category(source),
+                Err(ForceError::InvalidRange { .. })
+            ));
+        }
+        assert!(matches!(
+            parse_category("文章 {單行(max=) 標題}"),
+            Err(ForceError::Unexpected { .. })
+        ));
+        assert!(matches!(
+            parse_category("文章 {數字 讚數 = 200 數字(0..100) 評分 = 101}"),
+            Err(ForceError::InvalidDefault { .. })
+        ));
+        Ok(())
+    }
+    #[test]
+    fn test_inheritance() -> ForceResult<()> {
+        let source = "元資料 {單行 作者 數字 時間}
+            文章 : 元資料 {@title 單行 標題 文本 內文}
+            回覆 : 文章, 元資料 {鍵結[文章] 原文 文本/.{1,256}/ 內文}";
+        let force = parse(source)?;
+        let reply = &force.categories["回覆"];
+        let names: Vec<&str> = reply.fields.iter().map(|f| f.name.as_str()).collect();
+        assert_eq!(names, vec!["作者", "時間", "標題", "內文", "原文"]);
+        assert_eq!(reply.fields[3].datatype.to_string(), "文本/.{1,256}/");
+        assert_eq!(reply.parents, vec!["文章", "元資料"]);
+        assert_eq!(reply.title_field, Some("標題".to_owned()));
+
+        match parse("甲 : 乙 {單行 a} 乙 : 甲 {單行 b}") {
+            Err(ForceError::InheritanceCycle { categories }) => {
+                assert_eq!(categories, vec!["乙", "甲", "乙"]);
+            }
+            other => panic!("預期 InheritanceCycle ，但得到 {:?}", other),
+        }
+        assert!(matches!(
+            parse("文章 : 元資料 {單行 標題}"),
+            Err(ForceError::UnknownParent { .. })
+        ));
+        Ok(())
+    }
+    #[test]
+    fn test_pattern() -> ForceResult<()> {
+        let source = "模式 信箱 = /.+@.+/
+            會員 {文本 信箱 聯絡 文本 信箱? 備用 列表<文本 信箱> 其他 文本 信箱 = \"a@b\"}";
+        let force = parse(source)?;
+        let fields = &force.categories["會員"].fields;
+        assert_eq!(fields[0].datatype.to_string(), "文本/.+@.+/");
+        assert!(fields[1].optional);
+        assert_eq!(fields[2].datatype.to_string(), "列表<文本/.+@.+/>");
+        assert_eq!(fields[3].name, "信箱");
+        assert_eq!(fields[3].datatype, DataType::Text(None));
+        assert_eq!(force.patterns["信箱"], ".+@.+");
+
+        match parse("會員 {文本 電話 聯絡}") {
+            Err(ForceError::UnknownPattern { pattern, span }) => {
+                assert_eq!(pattern, "電話");
+                assert_eq!(span, 15..21);
+            }
+            other => panic!("預期 UnknownPattern ，但得到 {:?}", other),
+        }
+        assert!(matches!(
+            parse("模式 信箱 = /a/ 模式 信箱 = /b/"),
+            Err(ForceError::DuplicatePattern { .. })
+        ));
+        Ok(())
+    }
+    #[test]
+    fn test_duplicate_name() {
+        match parse("新聞 {單行 記者}\n/// 重複\n新聞 {文本 內文}") {
+            Err(ForceError::DuplicateCategory { category, span }) => {
+                assert_eq!(category, "新聞");
+                assert_eq!(span, 34..40);
+            }
+            other => panic!("預期 DuplicateCategory ，但得到 {:?}", other),
+        }
+        match parse("新聞 {單行 記者 文本 記者}") {
+            Err(ForceError::DuplicateField {
+                category,
+                field,
+                span,
+            }) => {
+                assert_eq!((category.as_str(), field.as_str()), ("新聞", "記者"));
+                assert_eq!(span, 29..35);
+            }
+            other => panic!("預期 DuplicateField ，但得到 {:?}", other),
+        }
+        let (force, errors) =
+            parse_all_errors("新聞 {單行 記者} 新聞 {文本 內文} 回覆 {文本 內文}");
+        assert_eq!(errors.len(), 1);
+        assert_eq!(force.categories["新聞"].fields[0].name, "記者");
+        assert!(force.categories.contains_key("回覆"));
+    }
+    #[test]
+    fn test_namespace() -> ForceResult<()> {
+        let source = "命名空間 論壇 {
+                文章 {單行 標題}
+                命名空間 八卦 { 回覆 : 文章 {鍵結[文章, 論壇.文章] 原文 鍵結[使用者] 作者} }
+            }
+            使用者 {單行 名稱}
+            文章 {鍵結[論壇.八卦.回覆] 引用}";
+        let force = parse(source)?;
+        let mut names: Vec<&String> = force.categories.keys().collect();
+        names.sort();
+        assert_eq!(names, vec!["使用者", "文章", "論壇.八卦.回覆", "論壇.文章"]);
+        let reply = &force.categories["論壇.八卦.回覆"];
+        assert_eq!(reply.parents, vec!["論壇.文章"]);
+        assert_eq!(reply.fields[0].name, "標題");
+        assert_eq!(
+            reply.fields[1].datatype,
+            DataType::Bond(
+                Bondee::Choices(vec!["論壇.文章".to_owned(), "論壇.文章".to_owned()]),
+                Relation::default()
+            )
+        );
+        assert_eq!(
+            reply.fields[2].datatype,
+            DataType::Bond(
+                Bondee::Choices(vec!["使用者".to_owned()]),
+                Relation::default()
+            )
+        );
+        assert!(force.validate().is_ok());
+
+        assert!(matches!(
+            parse("命名空間 論壇 { 文章 {單行 標題}"),
+            Err(ForceError::UnexpectedEof { .. })
+        ));
+        let (force, errors) =
+            parse_all_errors("命名空間 論壇 { 文章 {單行} 回覆 {單行 內文} } 留言 {}");
+        assert_eq!(errors.len(), 1);
+        assert!(force.categories.contains_key("論壇.回覆"));
+        assert!(force.categories.contains_key("留言"));
+        Ok(())
+    }
+    #[test]
+    fn test_relation() -> ForceResult<()> {
+        let category = parse_category(
+            "回覆 {鍵結[文章] 多個 原文 反向 回覆 鍵結[文章] 單一? 置頂 列表<鍵結[*]> 引用 反向 被引用}",
+        )?;
+        let relation = |i: usize| category.fields[i].datatype.relation().unwrap().clone();
+        assert_eq!(
+            relation(0),
+            Relation {
+                cardinality: Cardinality::Many,
+                inverse: Some("回覆".to_owned())
+            }
+        );
+        assert_eq!(relation(1).cardinality, Cardinality::One);
+        assert!(category.fields[1].optional);
+        assert_eq!(relation(2).inverse, Some("被引用".to_owned()));
+        assert_eq!(category.fields[2].name, "引用");
+        assert!(matches!(
+            parse_category("回覆 {單行 原文 反向 回覆}"),
+            Err(ForceError::NoMeet { .. })
+        ));
+        Ok(())
+    }
+    #[test]
+    fn test_category_attributes() -> ForceResult<()> {
+        let force = parse("@隱藏 @限定(管理員, \"版主\") 公告 {單行 標題} 回覆 : 公告 {}")?;
+        let category = &force.categories["公告"];
+        assert_eq!(
+            category.attributes(),
+            &[
+                Attribute {
+                    name: "隱藏".to_owned(),
+                    arguments: vec![],
+                },
+                Attribute {
+                    name: "限定".to_owned(),
+                    arguments: vec!["管理員".to_owned(), "版主".to_owned()],
+                },
+            ]
+        );
+        assert!(category.attribute("隱藏").is_some());
+        assert!(force.categories["回覆"].attributes().is_empty());
+        match parse("@隱藏 @隱藏 公告 {}") {
+            Err(ForceError::DuplicateAttribute { attribute, span }) => {
+                assert_eq!(attribute, "隱藏");
+                assert_eq!(span, 8..15);
+            }
+            other => panic!("預期 DuplicateAttribute ，但得到 {:?}", other),
+        }
+        assert!(matches!(
+            parse("@限定(管理員 版主) 公告 {}"),
+            Err(ForceError::Unexpected { .. })
+        ));
+        Ok(())
+    }
+    #[test]
+    fn test_field_attributes() -> ForceResult<()> {
+        let category = parse_category("會員 {@key @唯讀 單行 帳號 @索引 日期時間 註冊}")?;
+        assert!(category.fields[0].is_key());
+        assert!(category.fields[0].is_readonly());
+        assert!(!category.fields[0].is_indexed());
+        assert!(category.fields[1].is_indexed());
+
+        let source = "會員 {@欄位(\"user_name\") 單行 帳號}";
+        match parse(source) {
+            Err(ForceError::UnknownAttribute { attribute, span }) => {
+                assert_eq!(attribute, "欄位");
+                assert_eq!(&source[span], "@欄位");
+            }
+            other => panic!("預期 UnknownAttribute ，但得到 {:?}", other),
+        }
+        let options = ParserOptions {
+            unknown_attributes: true,
+            ..ParserOptions::default()
+        };
+        let mut parser = Parser::with_options(source, options);
+        let force = parser.parse()?;
+        let field = &force.categories["會員"].fields[0];
+        assert_eq!(
+            field.attribute("欄位").unwrap().arguments,
+            vec!["user_name".to_owned()]
+        );
+        assert!(matches!(
+            parse("會員 {@唯讀 @唯讀 單行 帳號}"),
+            Err(ForceError::DuplicateAttribute { .. })
+        ));
+        for attribute in &["title", "unique", "key"] {
+            let source = format!("會員 {{@{0} @{0} 單行 帳號}}", attribute);
+            match parse(&source) {
+                Err(ForceError::DuplicateAttribute {
+                    attribute: name,
+                    span,
+                }) => {
+                    assert_eq!(&name, attribute);
+                    assert_eq!(span.start, source.rfind('@').unwrap());
+                }
+                other => panic!("預期 DuplicateAttribute ，但得到 {:?}", other),
+            }
+        }
+        Ok(())
+    }
+    #[test]
+    fn test_invalid_regex() {
+        let source = "模式 信箱 = /.+@.+/ 文章 {文本/(a/ 標題 文本 信箱 聯絡}";
+        match parse(source) {
+            Err(error @ ForceError::InvalidRegex { .. }) => {
+                assert_eq!(&source[error.span().unwrap()], "/(a/");
+                assert_eq!(error.to_string(), "不合法的正則表達式 /(a/");
+                assert_eq!(error.help().unwrap(), "正則表達式語法錯誤：unclosed group");
+            }
+            other => panic!("預期 InvalidRegex ，但得到 {:?}", other),
+        }
+        let (force, errors) = parse_all_errors(&source.replace("(a", "a+"));
+        assert!(errors.is_empty());
+        let fields = &force.categories["文章"].fields;
+        assert_eq!(fields[0].datatype, DataType::Text(Regex::new("a+").ok()));
+        assert!(matches!(
+            parse("模式 信箱 = /[/"),
+            Err(ForceError::InvalidRegex { .. })
+        ));
+    }
+    #[test]
+    fn test_options() -> ForceResult<()> {
+        let source = "文章 {列舉 {草稿, 已發佈,} 狀態 帶籤鍵結[文章,] {挺 {輸能: [1, 2,]}} 評價} \
+                      回覆 : 文章, {} 文章 {@唯讀 @欄位(甲, 乙,) 單行 標題}";
+        assert!(parse(source).is_err());
+        let force = Parser::with_options(source, ParserOptions::lenient()).parse()?;
+        let category = &force.categories["文章"];
+        assert_eq!(category.fields.len(), 1);
+        assert_eq!(
+            category.fields[0]
+                .attribute("欄位")
+                .unwrap()
+                .arguments
+                .len(),
+            2
+        );
+        assert_eq!(force.categories["回覆"].parents, vec!["文章".to_owned()]);
+        let category = parse_category("文章 {列舉 {草稿,} 狀態}");
+        assert!(matches!(category, Err(ForceError::Unexpected { .. })));
+
+        let options = ParserOptions {
+            max_depth: Some(2),
+            ..ParserOptions::default()
+        };
+        let source = "文章 {列表<列表<單行>> 標籤}";
+        assert!(Parser::with_options(source, options.clone())
+            .parse()
+            .is_ok());
+        let source = "文章 {列表<列表<列表<單行>>> 標籤}";
+        match Parser::with_options(source, options.clone()).parse() {
+            Err(ForceError::TooDeep { limit, span }) => {
+                assert_eq!(limit, 2);
+                assert_eq!(&source[span], "列表");
+            }
+            other => panic!("預期 TooDeep ，但得到 {:?}", other),
+        }
+        let source = "命名空間 甲 { 命名空間 乙 { 命名空間 丙 { 文章 {} } } }";
+        let (_, errors) = Parser::with_options(source, options.clone()).parse_all_errors();
+        assert!(matches!(errors[0], ForceError::TooDeep { .. }));
+        let options = ParserOptions {
+            max_size: Some(64),
+            ..ParserOptions::default()
+        };
+        let source = "文章 {單行 標題} 回覆 {單行 內文} 公告 {單行 標題}";
+        match Parser::with_options(source, options).parse() {
+            Err(ForceError::TooLarge { limit, span }) => {
+                assert_eq!(limit, 64);
+                assert_eq!(span, 64..source.len());
+            }
+            other => panic!("預期 TooLarge ，但得到 {:?}", other),
+        }
+        Ok(())
+    }
+    #[test]
+    fn test_identifier_policy() -> ForceResult<()> {
+        let source = "文章 {單行 title 單行 標題}";
+        assert!(parse(source).is_ok());
+        let options = ParserOptions {
+            identifiers: IdentifierPolicy::Pattern(Regex::new(r"^[\p{Han}_]+$").unwrap()),
+            ..ParserOptions::default()
+        };
+        match Parser::with_options(source, options).parse() {
+            Err(ForceError::InvalidIdentifier { name, span }) => {
+                assert_eq!(name, "title");
+                assert_eq!(&source[span], "title");
+            }
+            other => panic!("預期 InvalidIdentifier ，但得到 {:?}", other),
+        }
+        let options = ParserOptions {
+            identifiers: IdentifierPolicy::Ascii,
+            ..ParserOptions::default()
+        };
+        let (force, errors) = Parser::with_options("文章 {} post {}", options).parse_all_errors();
+        assert_eq!(errors.len(), 1);
+        assert!(matches!(&errors[0], ForceError::InvalidIdentifier { name, .. } if name == "文章"));
+        assert!(force.categories.contains_key("post"));
+
+        let force = parse("命名空間 論壇 { 公告 {} } `發布 文章` : 論壇.`公告` {單行 `單行`}")?;
+        assert_eq!(force.categories["發布 文章"].fields[0].name, "單行");
+        Ok(())
+    }
+    #[test]
+    fn test_legacy_identifier() -> ForceResult<()> {
+        // 舊版的識別子可含 - ，現在須以 ` 括起
+        let legacy = |source: &str| match parse(source) {
+            Err(error @ ForceError::LegacyIdentifier { .. }) => {
+                let span = error.span().unwrap();
+                (
+                    source[span].to_owned(),
+                    error.to_string(),
+                    error.help().unwrap(),
+                )
+            }
+            other => panic!("預期 LegacyIdentifier ，但得到 {:?}", other),
+        };
+        let field = "
+新聞 {
+    單行 媒體
+    單行 play-boy
+    文本 內文
+}
+";
+        assert_eq!(
+            legacy(field),
+            (
+                "play-boy".to_owned(),
+                "play-boy 在舊版是一個識別子，現在會被拆開".to_owned(),
+                "以 ` 括起，寫成 `play-boy`".to_owned()
+            )
+        );
+        let category = "
+問-卦 {
+    文本/.{256,}/ 內文
+}
+";
+        assert_eq!(legacy(category).0, "問-卦");
+        assert_eq!(legacy("解答 {鍵結[問卦,留-言] 問題}").0, "留-言");
+        assert_eq!(legacy("回覆 : 論壇.留-言 {}").0, "留-言");
+        assert_eq!(legacy("新聞 {數字 讚數-1}").0, "讚數-1");
+        assert!(parse("新聞 {單行 `play-boy` 數字 讚數 計算 數字 熱度 = 讚數-1}").is_ok());
+        Ok(())
+    }
+    #[test]
+    fn test_computed() -> ForceResult<()> {
+        let source = "文章 {數字 讚數 數字 噓數 計算 數字 熱度 = 讚數 -噓數*2 - -1}";
+        let force = parse(source)?;
+        let field = &force.categories["文章"].fields[2];
+        assert_eq!(field.default, None);
+        let field_ref = |name: &str| Box::new(Expr::Field(name.to_owned()));
+        assert_eq!(
+            field.computed(),
+            Some(&Expr::Binary {
+                op: BinaryOp::Sub,
+                left: Box::new(Expr::Binary {
+                    op: BinaryOp::Sub,
+                    left: field_ref("讚數"),
+                    right: Box::new(Expr::Binary {
+                        op: BinaryOp::Mul,
+                        left: field_ref("噓數"),
+                        right: Box::new(Expr::Number(2)),
+                    }),
+                }),
+                right: Box::new(Expr::Number(-1)),
+            })
+        );
+        assert_eq!(
+            field.computed().unwrap().to_string(),
+            "讚數 - 噓數 * 2 - -1"
+        );
+        #[cfg(feature = "std")]
+        {
+            let span = crate::analysis::Analysis::new(source)
+                .name_span("文章", Some("熱度"))
+                .unwrap();
+            assert_eq!(&source[span], "熱度");
+        }
+
+        assert!(matches!(
+            parse("文章 {計算 單行 標題 = 1}"),
+            Err(ForceError::Unexpected {
+                fact: Token::OneLine,
+                ..
+            })
+        ));
+        assert!(matches!(
+            parse("文章 {數字 讚數 計算 數字 熱度}"),
+            Err(ForceError::Unexpected {
+                fact: Token::RightCurlyBrace,
+                ..
+            })
+        ));
+        assert!(matches!(
+            parse("文章 {數字 讚數 計算 數字 熱度 = 讚數 +}"),
+            Err(ForceError::Unexpected {
+                fact: Token::RightCurlyBrace,
+                ..
+            })
+        ));
+        let options = ParserOptions {
+            max_depth: Some(2),
+            ..ParserOptions::default()
+        };
+        let source = "文章 {數字 讚數 計算 數字 熱度 = (-讚數)}";
+        assert!(Parser::with_options(source, options.clone())
+            .parse()
+            .is_ok());
+        let source = "文章 {數字 讚數 計算 數字 熱度 = ((-讚數))}";
+        assert!(matches!(
+            Parser::with_options(source, options.clone()).parse(),
+            Err(ForceError::TooDeep { limit: 2, .. })
+        ));
+        let source = "文章 {數字 讚數 計算 數字 熱度 = 讚數 + 1 * 2 * 3}";
+        assert!(matches!(
+            Parser::with_options(source, options).parse(),
+            Err(ForceError::TooDeep { limit: 2, .. })
+        ));
+        // 預設的上限足以擋下耗盡堆疊的輸入
+        let sources = |n: usize| {
+            vec![
+                format!("文章 {{{}單行{} 標籤}}", "列表<".repeat(n), ">".repeat(n)),
+                format!(
+                    "文章 {{數字 讚數 計算 數字 熱度 = {}讚數{}}}",
+                    "(".repeat(n),
+                    ")".repeat(n)
+                ),
+                format!(
+                    "文章 {{數字 讚數 計算 數字 熱度 = 讚數{}}}",
+                    " + 讚數".repeat(n)
+                ),
+            ]
+        };
+        for source in sources(DEFAULT_MAX_DEPTH) {
+            assert!(parse(&source).is_ok());
+        }
+        for source in sources(10_000) {
+            assert!(matches!(
+                parse(&source),
+                Err(ForceError::TooDeep {
+                    limit: DEFAULT_MAX_DEPTH,
+                    ..
+                })
+            ));
+        }
+        Ok(())
+    }
+    #[test]
+    fn test_malformed_input() {
+        let source = "模式 信箱 = /.+@.+/ 匯入 \"其他.force\" 命名空間 論壇 { \
+                      /// 文章\n@限定(管理員) 文章 {@title @唯讀 單行 標題 文本 信箱 聯絡 = \"a@b\"} \
+                      回覆 : 文章 {鍵結[* except 文章] 原文 反向 回覆 列表<數字(0..)>(1..3) 分數 \
+                      帶籤鍵結[文章] {挺 {輸能: [1, 2]}} 評價 列舉 {草稿, 已發佈} 狀態 = \"草稿\"} }";
+        let boundaries = (0..=source.len()).filter(|i| source.is_char_boundary(*i));
+        for end in boundaries {
+            let prefix = &source[..end];
+            let _ = parse_all_errors(prefix);
+            let _ = parse_partial(prefix);
+            let _ = crate::cst::parse_cst(prefix);
+            #[cfg(feature = "std")]
+            let _ = crate::analysis::Analysis::new(prefix);
+            if let Err(ForceError::UnexpectedEof { span, .. }) = parse(prefix) {
+                assert_eq!(span, end..end, "{}", prefix);
+            }
+        }
+        for source in ["", "  ", "// 註解"] {
+            assert!(parse(source).is_ok());
+        }
+        for source in [
+            "新聞",
+            "新聞 {",
+            "新聞 {單行",
+            "模式",
+            "模式 信箱 =",
+            "匯入",
+            "命名空間 甲 {",
+            "@",
+            "@隱藏(",
+            "回覆 {鍵結[",
+        ] {
+            match parse(source) {
+                Err(ForceError::UnexpectedEof { span, .. }) => {
+                    assert_eq!(span, source.len()..source.len())
+                }
+                other => panic!("{} 預期 UnexpectedEof ，但得到 {:?}", source, other),
+            }
+        }
+        let error = parse("新聞 {單行 記者").unwrap_err();
+        assert_eq!(error.help().as_deref(), Some("可能少了一個 }"));
+        // 過深的巢狀須回報錯誤而非耗盡堆疊，未閉合時亦然
+        let n = 10_000;
+        let deep = [
+            format!("文章 {{{}單行{} 標籤}}", "列表<".repeat(n), ">".repeat(n)),
+            format!("文章 {{{}", "列表<".repeat(n)),
+            format!(
+                "文章 {{數字 讚數 計算 數字 熱度 = {}讚數{}}}",
+                "(".repeat(n),
+                ")".repeat(n)
+            ),
+            format!("文章 {{數字 讚數 計算 數字 熱度 = {}", "(".repeat(n)),
+            format!("文章 {{數字 讚數 計算 數字 熱度 = {}讚數}}", "-".repeat(n)),
+            format!(
+                "文章 {{數字 讚數 計算 數字 熱度 = 讚數{}}}",
+                " * 讚數".repeat(n)
+            ),
+            format!("{}文章 {{}}{}", "命名空間 甲 {".repeat(n), "}".repeat(n)),
+            "命名空間 甲 {".repeat(n),
+        ];
+        for source in &deep {
+            assert!(parse(source).is_err());
+            assert!(!parse_all_errors(source).1.is_empty());
+            let _ = parse_partial(source);
+            let _ = crate::cst::parse_cst(source);
+            #[cfg(feature = "std")]
+            let _ = crate::analysis::Analysis::new(source);
+        }
+    }
 }
@@ -0,0 +1,207 @@
+// 互動式的力語言環境，輸入的分類與模式會累積起來，可隨時以 validate 驗證實例
+// 大括號未閉合時繼續讀取下一行，直到片段完整
+use crate::format::format_force;
+use crate::lexer::{lexer, Token};
+use crate::*;
+use std::io::{self, BufRead, Write};
+
+const HELP: &str = "\
+輸入分類或模式的定義即可併入目前的力，並印出解析結果
+  validate <分類> <JSON>  驗證一個實例
+  ast [分類]              以 JSON 印出整份力或某個分類
+  show                    以原始碼格式印出目前的力
+  reset                   清空目前的力
+  help                    顯示此說明
+  quit                    離開";
+
+#[derive(Default)]
+pub struct Repl {
+    source: String,  // 至今所有成功解析的片段
+    pending: String, // 尚未閉合的片段
+    force: Force,
+    done: bool,
+}
+
+fn to_json<T: serde::Serialize>(value: &T) -> String {
+    serde_json::to_string_pretty(value).unwrap()
+}
+
+impl Repl {
+    pub fn new() -> Repl {
+        Repl::default()
+    }
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+    // 是否正在等待片段的後續幾行
+    pub fn is_pending(&self) -> bool {
+        !self.pending.is_empty()
+    }
+    pub fn force(&self) -> &Force {
+        &self.force
+    }
+    // 處理一行輸入，回傳要印出的內容
+    pub fn eval_line(&mut self, line: &str) -> String {
+        if self.is_pending() {
+            self.pending.push('\n');
+            self.pending.push_str(line);
+        } else {
+            let line = line.trim();
+            let (command, rest) = match line.find(char::is_whitespace) {
+                Some(i) => (&line[..i], line[i..].trim()),
+                None => (line, ""),
+            };
+            match (command, rest) {
+                ("", _) => return String::new(),
+                ("help", "") => return HELP.to_owned(),
+                ("quit", "") | ("exit", "") => {
+                    self.done = true;
+                    return String::new();
+                }
+                ("show", "") => return format_force(&self.force),
+                ("reset", "") => {
+                    *self = Repl::new();
+                    return "已清空".to_owned();
+                }
+                ("ast", "") => return to_json(&self.force),
+                ("ast", name) => {
+                    return match self.force.category(name) {
+                        Some(category) => to_json(category),
+                        None => format!("未定義的分類 {}", name),
+                    }
+                }
+                ("validate", rest) if !rest.is_empty() => return self.validate(rest),
+                _ => self.pending.push_str(line),
+            }
+        }
+        if self.is_open() {
+            return String::new();
+        }
+        let snippet = std::mem::take(&mut self.pending);
+        self.define(&snippet)
+    }
+    // 大括號尚未閉合
+    fn is_open(&self) -> bool {
+        let mut depth = 0i32;
+        for (token, _) in lexer(&self.pending) {
+            match token {
+                Token::LeftCurlyBrace => depth += 1,
+                Token::RightCurlyBrace => depth -= 1,
+                _ => {}
+            }
+        }
+        depth > 0
+    }
+    fn define(&mut self, snippet: &str) -> String {
+        let source = format!("{}{}\n", self.source, snippet);
+        let force = match parse(&source) {
+            Ok(force) => force,
+            Err(error) => return error.render(&source),
+        };
+        let mut out = Vec::new();
+        for name in force.category_names() {
+            let category = &force.categories[name];
+            if self.force.category(name) != Some(category) {
+                out.push(to_json(category));
+            }
+        }
+        // 鍵結到尚未定義的分類等錯誤只提示，之後補上定義即可
+        if let Err(errors) = force.validate() {
+            for error in errors {
+                out.push(error.render(&source).trim_end().to_owned());
+            }
+        }
+        self.source = source;
+        self.force = force;
+        out.join("\n")
+    }
+    fn validate(&self, rest: &str) -> String {
+        let (category, json) = match rest.find(char::is_whitespace) {
+            Some(i) => (&rest[..i], rest[i..].trim()),
+            None => return "用法：validate <分類> <JSON>".to_owned(),
+        };
+        let data: serde_json::Value = match serde_json::from_str(json) {
+            Ok(data) => data,
+            Err(error) => return format!("無法解析 JSON：{}", error),
+        };
+        match validate_instance(&self.force, category, &data) {
+            Ok(()) => "通過".to_owned(),
+            Err(errors) => {
+                let errors: Vec<String> = errors.iter().map(|error| error.to_string()).collect();
+                errors.join("\n")
+            }
+        }
+    }
+}
+
+// 自標準輸入逐行讀取，直到 quit 或輸入結束
+pub fn run() -> io::Result<()> {
+    let stdin = io::stdin();
+    let mut stdout = io::stdout();
+    let mut repl = Repl::new();
+    let mut lines = stdin.lock().lines();
+    loop {
+        write!(
+            stdout,
+            "{}",
+            if repl.is_pending() { "..> " } else { "力> " }
+        )?;
+        stdout.flush()?;
+        let line = match lines.next() {
+            Some(line) => line?,
+            None => break,
+        };
+        let out = repl.eval_line(&line);
+        if !out.is_empty() {
+            writeln!(stdout, "{}", out)?;
+        }
+        if repl.is_done() {
+            break;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_repl() {
+        let mut repl = Repl::new();
+        assert_eq!(repl.eval_line("文章 {"), "");
+        assert!(repl.is_pending());
+        let out = repl.eval_line("單行 標題 數字 讚數 = 0 }");
+        assert!(!repl.is_pending());
+        assert!(out.contains("\"name\": \"文章\""));
+
+        // 只印出新增或改變的分類，鍵結到未定義的分類僅提示
+        let out = repl.eval_line("留言 {鍵結[回覆] 原文}");
+        assert!(!out.contains("\"文章\""));
+        assert!(out.ends_with(
+            "錯誤：分類 留言 的域 原文 鍵結到未定義的分類 回覆\n\
+                               提示：請確認 回覆 的拼寫，命名空間中的分類可寫完整名稱"
+        ));
+        assert_eq!(repl.force().category_names(), vec!["文章", "留言"]);
+
+        assert_eq!(repl.eval_line("validate 文章 {\"標題\": \"你好\"}"), "通過");
+        assert!(!repl.eval_line("validate 文章 {\"讚數\": 1}").is_empty());
+        assert!(repl
+            .eval_line("validate 文章 {")
+            .starts_with("無法解析 JSON"));
+        assert!(repl
+            .eval_line("ast 文章")
+            .contains("\n  \"name\": \"文章\""));
+        assert_eq!(repl.eval_line("ast 問卦"), "未定義的分類 問卦");
+
+        // 解析失敗時不影響目前的力
+        assert!(repl
+            .eval_line("問卦 {單行}")
+            .starts_with("錯誤：預期 識別子"));
+        assert_eq!(repl.force().category_names(), vec!["文章", "留言"]);
+
+        repl.eval_line("reset");
+        assert!(repl.force().categories.is_empty());
+        repl.eval_line("quit");
+        assert!(repl.is_done());
+    }
+}
@@ -0,0 +1,364 @@
+// 語意檢查：解析成功的力未必合法，例如鍵結到不存在的分類
+use crate::prelude::*;
+use crate::visit::{walk_category_mut, VisitorMut};
+use crate::*;
+
+// 深度優先搜尋繼承關係，回傳第一個找到的循環
+fn find_cycle(
+    categories: &Categories,
+    name: &str,
+    stack: &mut Vec<String>,
+    done: &mut Vec<String>,
+) -> Option<Vec<String>> {
+    if let Some(i) = stack.iter().position(|n| n == name) {
+        let mut cycle = stack[i..].to_vec();
+        cycle.push(name.to_owned());
+        return Some(cycle);
+    }
+    if done.iter().any(|n| n == name) {
+        return None;
+    }
+    stack.push(name.to_owned());
+    if let Some(category) = categories.get(name) {
+        for parent in &category.parents {
+            if let Some(cycle) = find_cycle(categories, parent, stack, done) {
+                return Some(cycle);
+            }
+        }
+    }
+    stack.pop();
+    done.push(name.to_owned());
+    None
+}
+
+pub(crate) fn find_inheritance_cycle(categories: &Categories) -> Option<Vec<String>> {
+    let mut names: Vec<&String> = categories.keys().collect();
+    names.sort();
+    let mut done = Vec::new();
+    for name in names {
+        if let Some(cycle) = find_cycle(categories, name, &mut Vec::new(), &mut done) {
+            return Some(cycle);
+        }
+    }
+    None
+}
+
+fn flatten(
+    categories: &Categories,
+    name: &str,
+    flattened: &mut Map<String, (Vec<Field>, Option<String>)>,
+) -> ForceResult<()> {
+    if flattened.contains_key(name) {
+        return Ok(());
+    }
+    let category = &categories[name];
+    let mut fields: Vec<Field> = Vec::new();
+    let mut title_field = None;
+    for parent in &category.parents {
+        if !categories.contains_key(parent) {
+            return Err(ForceError::UnknownParent {
+                category: name.to_owned(),
+                parent: parent.clone(),
+            });
+        }
+        flatten(categories, parent, flattened)?;
+        let (parent_fields, parent_title) = &flattened[parent];
+        for field in parent_fields {
+            match fields.iter_mut().find(|f| f.name == field.name) {
+                Some(f) => *f = field.clone(),
+                None => fields.push(field.clone()),
+            }
+        }
+        title_field = parent_title.clone().or(title_field);
+    }
+    // 子分類的同名域覆蓋繼承而來的域
+    for field in &category.fields {
+        match fields.iter_mut().find(|f| f.name == field.name) {
+            Some(f) => *f = field.clone(),
+            None => fields.push(field.clone()),
+        }
+    }
+    // 各自只有一個 @key 的分類，合併後仍可能有多個
+    if fields.iter().filter(|field| field.key).count() > 1 {
+        return Err(ForceError::DuplicateKey {
+            category: name.to_owned(),
+            span: None,
+        });
+    }
+    let title_field = category.title_field.clone().or(title_field);
+    flattened.insert(name.to_owned(), (fields, title_field));
+    Ok(())
+}
+
+// 依序嘗試由內而外的各層命名空間，找不到則維持原樣
+fn resolve_name(categories: &Categories, category: &str, name: &str) -> Option<String> {
+    let mut namespace: Vec<&str> = category.split('.').collect();
+    namespace.pop();
+    while !namespace.is_empty() {
+        let candidate = format!("{}.{}", namespace.join("."), name);
+        if categories.contains_key(&candidate) {
+            return Some(candidate);
+        }
+        namespace.pop();
+    }
+    None
+}
+
+struct NameResolver<'a> {
+    categories: &'a Categories,
+    category: String,
+}
+
+impl<'a> VisitorMut for NameResolver<'a> {
+    fn visit_bondee_mut(&mut self, bondee: &mut Bondee) {
+        for choice in bondee.names_mut() {
+            if let Some(name) = resolve_name(self.categories, &self.category, choice) {
+                *choice = name;
+            }
+        }
+    }
+}
+
+// 將命名空間中的鍵結對象與父分類改寫為完整名稱
+fn resolve_names(categories: &mut Categories) {
+    let mut resolved: Vec<(String, Category)> = Vec::new();
+    for (name, category) in categories.iter() {
+        if !name.contains('.') {
+            continue;
+        }
+        let mut category = category.clone();
+        for parent in &mut category.parents {
+            if let Some(resolved) = resolve_name(categories, name, parent) {
+                *parent = resolved;
+            }
+        }
+        let mut resolver = NameResolver {
+            categories,
+            category: name.clone(),
+        };
+        walk_category_mut(&mut resolver, &mut category);
+        resolved.push((name.clone(), category));
+    }
+    categories.extend(resolved);
+}
+
+// 解析名稱並展開繼承，在所有分類都解析完後進行
+pub(crate) fn link(categories: &mut Categories) -> ForceResult<()> {
+    resolve_names(categories);
+    flatten_inheritance(categories)
+}
+
+// 將繼承而來的域展開到各分類中
+fn flatten_inheritance(categories: &mut Categories) -> ForceResult<()> {
+    if let Some(cycle) = find_inheritance_cycle(categories) {
+        return Err(ForceError::InheritanceCycle { categories: cycle });
+    }
+    let mut flattened = Map::new();
+    let mut names: Vec<String> = categories.keys().cloned().collect();
+    names.sort();
+    for name in &names {
+        flatten(categories, name, &mut flattened)?;
+    }
+    for (name, (fields, title_field)) in flattened {
+        let category = categories.get_mut(&name).unwrap();
+        category.fields = fields;
+        category.title_field = title_field;
+    }
+    Ok(())
+}
+
+impl Force {
+    fn check_bondee(&self, category: &Category, field: &Field, errors: &mut Vec<ForceError>) {
+        // 排除的分類不存在多半是拼錯，一併回報
+        if let Some(bondee) = field.datatype.bondee() {
+            for choice in bondee.names() {
+                if !self.categories.contains_key(choice) {
+                    errors.push(ForceError::UnknownBondee {
+                        category: category.name.clone(),
+                        field: field.name.clone(),
+                        bondee: choice.clone(),
+                    });
+                }
+            }
+        }
+    }
+    // 反向連結會成為被鍵結分類的一個屬性，不可與其中的域同名
+    fn check_inverse(&self, category: &Category, field: &Field, errors: &mut Vec<ForceError>) {
+        let inverse = match field.datatype.relation().and_then(|r| r.inverse.as_ref()) {
+            Some(inverse) => inverse,
+            None => return,
+        };
+        let mut targets = match field.datatype.bondee() {
+            Some(bondee) => self.bond_targets(bondee),
+            None => return,
+        };
+        targets.sort();
+        for target in targets {
+            let conflict = self
+                .categories
+                .get(&target)
+                .is_some_and(|target| target.field(inverse).is_some());
+            if conflict {
+                errors.push(ForceError::InverseConflict {
+                    category: category.name.clone(),
+                    field: field.name.clone(),
+                    inverse: inverse.clone(),
+                    target,
+                });
+            }
+        }
+    }
+    // 計算域依展開後的域檢查，故可引用繼承而來的域
+    fn check_computed(&self, category: &Category, field: &Field, errors: &mut Vec<ForceError>) {
+        let computed = match &field.computed {
+            Some(computed) => computed,
+            None => return,
+        };
+        let mut reported: Vec<&str> = Vec::new();
+        for reference in computed.references() {
+            let valid = category
+                .field(reference)
+                .is_some_and(|f| f.computed.is_none() && matches!(f.datatype, DataType::Number(_)));
+            if !valid && !reported.contains(&reference) {
+                reported.push(reference);
+                errors.push(ForceError::InvalidReference {
+                    category: category.name.clone(),
+                    field: field.name.clone(),
+                    reference: reference.to_owned(),
+                });
+            }
+        }
+    }
+    // 回傳所有語意錯誤，依分類名稱排序
+    pub fn validate(&self) -> Result<(), Vec<ForceError>> {
+        let mut names: Vec<&String> = self.categories.keys().collect();
+        names.sort();
+        let mut errors = Vec::new();
+        if let Some(cycle) = find_inheritance_cycle(&self.categories) {
+            errors.push(ForceError::InheritanceCycle { categories: cycle });
+        }
+        for name in names {
+            let category = &self.categories[name];
+            for parent in &category.parents {
+                if !self.categories.contains_key(parent) {
+                    errors.push(ForceError::UnknownParent {
+                        category: name.clone(),
+                        parent: parent.clone(),
+                    });
+                }
+            }
+            for field in &category.fields {
+                self.check_bondee(category, field, &mut errors);
+                self.check_inverse(category, field, &mut errors);
+                self.check_computed(category, field, &mut errors);
+            }
+        }
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_inverse_conflict() -> ForceResult<()> {
+        let force = parse("文章 {單行 回覆} 回覆 {鍵結[文章] 原文 反向 回覆}")?;
+        assert_eq!(
+            force.validate().unwrap_err()[0].to_string(),
+            "分類 回覆 的域 原文 的反向連結 回覆 與分類 文章 中的域同名"
+        );
+        assert!(parse("文章 {單行 標題} 回覆 {鍵結[文章] 原文 反向 回覆}")?
+            .validate()
+            .is_ok());
+        Ok(())
+    }
+    #[test]
+    fn test_invalid_reference() -> ForceResult<()> {
+        let source = "文章 {數字 讚數 單行 標題 計算 數字 熱度 = 讚數 * 2 \
+                      計算 數字 分數 = 熱度 + 標題 / 標題 - 回數}";
+        let messages: Vec<String> = parse(source)?
+            .validate()
+            .unwrap_err()
+            .iter()
+            .map(|error| error.to_string())
+            .collect();
+        assert_eq!(
+            messages,
+            vec![
+                "分類 文章 的計算域 分數 引用了無法計算的域 熱度",
+                "分類 文章 的計算域 分數 引用了無法計算的域 標題",
+                "分類 文章 的計算域 分數 引用了無法計算的域 回數",
+            ]
+        );
+        assert!(
+            parse("元資料 {數字 讚數} 文章 : 元資料 {計算 數字 熱度 = 讚數}")?
+                .validate()
+                .is_ok()
+        );
+        Ok(())
+    }
+    #[test]
+    fn test_valid_bondee() -> ForceResult<()> {
+        let force = parse("問卦 {文本 內文} 解答 {鍵結[問卦] 問題} 留言 {鍵結[*] 本體}")?;
+        assert!(force.validate().is_ok());
+        Ok(())
+    }
+    #[test]
+    fn test_unknown_bondee() -> ForceResult<()> {
+        let force = parse("問卦 {文本 內文} 解答 {列表<鍵結[問卦,留言]> 問題}")?;
+        let errors = force.validate().unwrap_err();
+        assert_eq!(errors.len(), 1);
+        match &errors[0] {
+            ForceError::UnknownBondee {
+                category,
+                field,
+                bondee,
+            } => {
+                assert_eq!(category, "解答");
+                assert_eq!(field, "問題");
+                assert_eq!(bondee, "留言");
+            }
+            other => panic!("預期 UnknownBondee ，但得到 {:?}", other),
+        }
+        Ok(())
+    }
+    #[test]
+    fn test_inherited_key() -> ForceResult<()> {
+        let duplicate_key = |source: &str| match parse(source) {
+            Err(ForceError::DuplicateKey { category, span }) => {
+                assert_eq!(span, None);
+                category
+            }
+            other => panic!("預期 DuplicateKey ，但得到 {:?}", other),
+        };
+        assert_eq!(
+            duplicate_key("會員 {@key 數字 編號} 管理員 : 會員 {@key 單行 帳號}"),
+            "管理員"
+        );
+        assert_eq!(
+            duplicate_key("甲 {@key 數字 編號} 乙 {@key 單行 帳號} 丙 : 甲, 乙 {}"),
+            "丙"
+        );
+        // 覆蓋父分類的 @key 域不算重複
+        let force = parse("會員 {@key 數字 編號} 管理員 : 會員 {@key 單行 編號}")?;
+        assert!(force.categories["管理員"].fields[0].key);
+        Ok(())
+    }
+    #[test]
+    fn test_inheritance_cycle() -> ForceResult<()> {
+        let mut force = parse("文章 {單行 標題} 回覆 : 文章 {文本 內文}")?;
+        force.categories.get_mut("文章").unwrap().parents = vec!["回覆".to_owned()];
+        let errors = force.validate().unwrap_err();
+        match &errors[0] {
+            ForceError::InheritanceCycle { categories } => {
+                assert_eq!(categories, &vec!["回覆", "文章", "回覆"]);
+            }
+            other => panic!("預期 InheritanceCycle ，但得到 {:?}", other),
+        }
+        Ok(())
+    }
+}
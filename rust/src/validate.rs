@@ -1,21 +1,129 @@
+use crate::evaluate::EvaluateError;
+use crate::prelude::*;
 use crate::*;
+use core::fmt;
 use serde_json::Value;
+#[cfg(feature = "std")]
+use std::collections::HashSet;
+
+fn digits(s: &str) -> Option<u32> {
+    if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    s.parse().ok()
+}
+
+fn days_in_month(year: u32, month: u32) -> u32 {
+    match month {
+        2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => {
+            29
+        }
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// RFC 3339 的 date-time ，如 2020-02-29T12:00:00.5+08:00
+pub fn is_rfc3339(s: &str) -> bool {
+    if !s.is_ascii() || s.len() < 20 {
+        return false;
+    }
+    let (date, rest) = s.split_at(10);
+    let date: Vec<&str> = date.split('-').collect();
+    let (year, month, day) = match date.as_slice() {
+        [year, month, day] if year.len() == 4 && month.len() == 2 && day.len() == 2 => {
+            match (digits(year), digits(month), digits(day)) {
+                (Some(year), Some(month), Some(day)) => (year, month, day),
+                _ => return false,
+            }
+        }
+        _ => return false,
+    };
+    if !(1..=12).contains(&month) || day == 0 || day > days_in_month(year, month) {
+        return false;
+    }
+    if !matches!(rest.as_bytes()[0], b'T' | b't' | b' ') {
+        return false;
+    }
+    let time = &rest[1..];
+    let valid_clock = |clock: &str, max_hour: u32| match clock.split(':').collect::<Vec<_>>()[..] {
+        [hour, minute] if hour.len() == 2 && minute.len() == 2 => {
+            digits(hour).is_some_and(|h| h <= max_hour) && digits(minute).is_some_and(|m| m < 60)
+        }
+        _ => false,
+    };
+    if time.len() < 9 || !valid_clock(&time[..5], 23) || &time[5..6] != ":" {
+        return false;
+    }
+    // 秒數可為 60 以容納閏秒
+    if digits(&time[6..8]).is_none_or(|sec| sec > 60) {
+        return false;
+    }
+    let mut offset = &time[8..];
+    if let Some(fraction) = offset.strip_prefix('.') {
+        let len = fraction.bytes().take_while(|b| b.is_ascii_digit()).count();
+        if len == 0 {
+            return false;
+        }
+        offset = &fraction[len..];
+    }
+    match offset {
+        "Z" | "z" => true,
+        _ if offset.len() == 6 && (offset.starts_with('+') || offset.starts_with('-')) => {
+            valid_clock(&offset[1..], 23)
+        }
+        _ => false,
+    }
+}
+
+// 帶籤鍵結的值須多帶一個 "tag" 字串，且為定義中的標籤之一
+fn has_valid_tag(tags: &[Tag], data: &Value) -> bool {
+    match data.get("tag") {
+        Some(Value::String(tag)) => tags.iter().any(|t| &t.name == tag),
+        _ => false,
+    }
+}
 
 pub trait ValidatorTrait {
     fn validate_bond(&self, bondee: &Bondee, data: &Value) -> bool;
     fn validate_datatype(&self, data_type: &DataType, data: &Value) -> bool {
         match (data_type, data) {
-            (DataType::Number, Value::Number(n)) => n.is_i64(),
-            (DataType::OneLine, Value::String(s)) => !s.contains('\n'),
+            (DataType::Number(bounds), Value::Number(n)) => {
+                n.as_i64().is_some_and(|n| bounds.contains(n))
+            }
+            (DataType::OneLine(bounds), Value::String(s)) => {
+                !s.contains('\n') && bounds.contains(s.chars().count())
+            }
             (DataType::Text(None), Value::String(_)) => true,
             (DataType::Text(Some(regex)), Value::String(s)) => regex.is_match(s),
-            (DataType::Bond(bondee), data) => self.validate_bond(bondee, data),
+            (DataType::Enum(variants), Value::String(s)) => variants.contains(s),
+            (DataType::Bool, Value::Bool(_)) => true,
+            (DataType::DateTime, Value::String(s)) => is_rfc3339(s),
+            (DataType::Bond(bondee, _), data) => self.validate_bond(bondee, data),
+            (DataType::TaggedBond(bondee, tags), data) => {
+                has_valid_tag(tags, data) && self.validate_bond(bondee, data)
+            }
+            (DataType::Array { inner, length }, Value::Array(items)) => {
+                length.contains(items.len())
+                    && items.iter().all(|item| self.validate_datatype(inner, item))
+            }
             _ => false,
         }
     }
     fn validate_category(&self, category: &Category, data: &Value) -> bool {
         for field in &category.fields {
-            if self.validate_datatype(&field.datatype, &data[&field.name]) == false {
+            if let Some(expr) = &field.computed {
+                if !computed_matches(expr, data, &data[&field.name]) {
+                    return false;
+                }
+                continue;
+            }
+            let value = &data[&field.name];
+            if (field.optional || field.default.is_some()) && value.is_null() {
+                continue;
+            }
+            if !self.validate_datatype(&field.datatype, value) {
                 return false;
             }
         }
@@ -23,6 +131,350 @@ pub trait ValidatorTrait {
     }
 }
 
+// 計算域可省略，給出時須與算出的值相同，如 fill_computed 填入的值
+fn computed_matches(expr: &Expr, data: &Value, value: &Value) -> bool {
+    value.is_null()
+        || value
+            .as_f64()
+            .is_some_and(|value| evaluate(expr, data) == Ok(value))
+}
+
+// 實例中違反定義之處
+#[derive(Debug, PartialEq)]
+pub enum InstanceError {
+    UnknownCategory(String),
+    NotObject,
+    MissingField(String),
+    TypeMismatch { field: String, expect: String },
+    NewlineInOneLine(String),
+    RegexMismatch { field: String, regex: String },
+    // 鍵結的值須為 {"category": 分類名, ...} 形式的物件，帶籤鍵結另須有 "tag"
+    MalformedBond(String),
+    BondNotAllowed { field: String, category: String },
+    LengthOutOfRange { field: String, length: usize },
+    OutOfRange { field: String, value: i64 },
+    NotInEnum { field: String, value: String },
+    InvalidTag(String),
+    InvalidDateTime { field: String, value: String },
+    // 由 evaluate::fill_computed 回報
+    ComputeFailed { field: String, error: EvaluateError },
+    ComputedMismatch(String),
+}
+
+impl fmt::Display for InstanceError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            InstanceError::UnknownCategory(category) => write!(f, "未定義的分類 {}", category),
+            InstanceError::NotObject => write!(f, "實例必須是物件"),
+            InstanceError::MissingField(field) => write!(f, "缺少域 {}", field),
+            InstanceError::TypeMismatch { field, expect } => {
+                write!(f, "域 {} 的型別應為 {}", field, expect)
+            }
+            InstanceError::NewlineInOneLine(field) => write!(f, "單行域 {} 含有換行", field),
+            InstanceError::RegexMismatch { field, regex } => {
+                write!(f, "域 {} 不符合 /{}/", field, regex)
+            }
+            InstanceError::MalformedBond(field) => write!(f, "域 {} 不是合法的鍵結", field),
+            InstanceError::BondNotAllowed { field, category } => {
+                write!(f, "域 {} 不能鍵結到分類 {}", field, category)
+            }
+            InstanceError::LengthOutOfRange { field, length } => {
+                write!(f, "域 {} 的長度 {} 超出範圍", field, length)
+            }
+            InstanceError::OutOfRange { field, value } => {
+                write!(f, "域 {} 的值 {} 超出範圍", field, value)
+            }
+            InstanceError::NotInEnum { field, value } => {
+                write!(f, "域 {} 的值 {} 不在列舉中", field, value)
+            }
+            InstanceError::InvalidTag(field) => write!(f, "域 {} 的標籤不合法", field),
+            InstanceError::InvalidDateTime { field, value } => {
+                write!(
+                    f,
+                    "域 {} 的值 {} 不是 RFC 3339 格式的日期時間",
+                    field, value
+                )
+            }
+            InstanceError::ComputeFailed { field, error } => {
+                write!(f, "無法計算域 {}：{}", field, error)
+            }
+            InstanceError::ComputedMismatch(field) => {
+                write!(f, "計算域 {} 的值與算出的值不同", field)
+            }
+        }
+    }
+}
+
+// 判斷鍵結對象是否合法的依據
+pub(crate) enum BondCheck<'a> {
+    Force(&'a Force),
+    #[cfg(feature = "std")]
+    Targets(&'a HashSet<String>), // 事先算好的可鍵結分類
+}
+
+pub(crate) struct InstanceValidator<'a> {
+    pub(crate) bonds: BondCheck<'a>,
+    pub(crate) errors: Vec<InstanceError>,
+}
+
+impl<'a> InstanceValidator<'a> {
+    fn validate_bond(&mut self, field: &str, bondee: &Bondee, data: &Value) {
+        let category = match data.get("category") {
+            Some(Value::String(category)) => category,
+            _ => {
+                self.errors
+                    .push(InstanceError::MalformedBond(field.to_owned()));
+                return;
+            }
+        };
+        let allowed = match (&self.bonds, bondee) {
+            #[cfg(feature = "std")]
+            (BondCheck::Targets(targets), _) => targets.contains(category),
+            (BondCheck::Force(_), Bondee::Choices(choices)) => choices.contains(category),
+            (BondCheck::Force(force), bondee) => {
+                force.categories.contains_key(category) && bondee.accepts(category)
+            }
+        };
+        if !allowed {
+            self.errors.push(InstanceError::BondNotAllowed {
+                field: field.to_owned(),
+                category: category.clone(),
+            });
+        }
+    }
+    // path 為出錯值的位置，列表中的元素以 域名[索引] 表示
+    fn validate_value(&mut self, path: &str, datatype: &DataType, data: &Value) {
+        match (datatype, data) {
+            (DataType::Number(bounds), Value::Number(n)) if n.is_i64() => {
+                let value = n.as_i64().unwrap();
+                if !bounds.contains(value) {
+                    self.errors.push(InstanceError::OutOfRange {
+                        field: path.to_owned(),
+                        value,
+                    });
+                }
+            }
+            (DataType::OneLine(bounds), Value::String(s)) => {
+                if s.contains('\n') {
+                    self.errors
+                        .push(InstanceError::NewlineInOneLine(path.to_owned()));
+                }
+                let length = s.chars().count();
+                if !bounds.contains(length) {
+                    self.errors.push(InstanceError::LengthOutOfRange {
+                        field: path.to_owned(),
+                        length,
+                    });
+                }
+            }
+            (DataType::Text(None), Value::String(_)) => {}
+            (DataType::Text(Some(regex)), Value::String(s)) => {
+                if !regex.is_match(s) {
+                    self.errors.push(InstanceError::RegexMismatch {
+                        field: path.to_owned(),
+                        regex: regex.as_str().to_owned(),
+                    });
+                }
+            }
+            (DataType::Enum(variants), Value::String(s)) => {
+                if !variants.contains(s) {
+                    self.errors.push(InstanceError::NotInEnum {
+                        field: path.to_owned(),
+                        value: s.clone(),
+                    });
+                }
+            }
+            (DataType::Bool, Value::Bool(_)) => {}
+            (DataType::DateTime, Value::String(s)) => {
+                if !is_rfc3339(s) {
+                    self.errors.push(InstanceError::InvalidDateTime {
+                        field: path.to_owned(),
+                        value: s.clone(),
+                    });
+                }
+            }
+            (DataType::Bond(bondee, _), _) => self.validate_bond(path, bondee, data),
+            (DataType::TaggedBond(bondee, tags), _) => {
+                if !has_valid_tag(tags, data) {
+                    self.errors.push(InstanceError::InvalidTag(path.to_owned()));
+                }
+                self.validate_bond(path, bondee, data);
+            }
+            (DataType::Array { inner, length }, Value::Array(items)) => {
+                if !length.contains(items.len()) {
+                    self.errors.push(InstanceError::LengthOutOfRange {
+                        field: path.to_owned(),
+                        length: items.len(),
+                    });
+                }
+                for (i, item) in items.iter().enumerate() {
+                    self.validate_value(&format!("{}[{}]", path, i), inner, item);
+                }
+            }
+            (datatype, _) => {
+                self.errors.push(InstanceError::TypeMismatch {
+                    field: path.to_owned(),
+                    expect: datatype.to_string(),
+                });
+            }
+        }
+    }
+    // instance 為整個實例，計算域須以其中的其他域驗算
+    pub(crate) fn validate_field(&mut self, field: &Field, instance: &Value) {
+        let data = &instance[&field.name];
+        if let Some(expr) = &field.computed {
+            if !computed_matches(expr, instance, data) {
+                self.errors
+                    .push(InstanceError::ComputedMismatch(field.name.clone()));
+            }
+            return;
+        }
+        if data.is_null() {
+            if field.optional || field.default.is_some() {
+                return;
+            }
+            self.errors
+                .push(InstanceError::MissingField(field.name.clone()));
+        } else {
+            self.validate_value(&field.name, &field.datatype, data);
+        }
+    }
+}
+
+// 檢查 data 是否符合 force 中名為 category 的分類，回傳所有違規之處
+pub fn validate_instance(
+    force: &Force,
+    category: &str,
+    data: &Value,
+) -> Result<(), Vec<InstanceError>> {
+    let category = match force.categories.get(category) {
+        Some(category) => category,
+        None => return Err(vec![InstanceError::UnknownCategory(category.to_owned())]),
+    };
+    if !data.is_object() {
+        return Err(vec![InstanceError::NotObject]);
+    }
+    let mut validator = InstanceValidator {
+        bonds: BondCheck::Force(force),
+        errors: Vec::new(),
+    };
+    for field in &category.fields {
+        validator.validate_field(field, data);
+    }
+    if validator.errors.is_empty() {
+        Ok(())
+    } else {
+        Err(validator.errors)
+    }
+}
+
+// 一批實例中違反定義之處， index 為實例在批次中的位置
+#[derive(Debug, PartialEq)]
+pub enum CollectionError {
+    Instance {
+        index: usize,
+        error: InstanceError,
+    },
+    // 第 index 個實例的 field 與第 first 個重複
+    Duplicate {
+        field: String,
+        value: Value,
+        first: usize,
+        index: usize,
+    },
+}
+
+impl fmt::Display for CollectionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CollectionError::Instance { index, error } => {
+                write!(f, "第 {} 個實例：{}", index, error)
+            }
+            CollectionError::Duplicate {
+                field,
+                value,
+                first,
+                index,
+            } => write!(
+                f,
+                "第 {} 個實例的域 {} 的值 {} 與第 {} 個實例重複",
+                index, field, value, first
+            ),
+        }
+    }
+}
+
+// 逐一檢查 instances 中的每個實例，並檢查 @unique 與 @key 的域在實例間不重複
+// 單一的鍵結亦不可重複，即同一對象只能被一個實例鍵結，列表中的鍵結則不檢查
+// 省略的域以其預設值比較，沒有值者不參與比較
+pub fn validate_collection(
+    force: &Force,
+    category: &str,
+    instances: &[Value],
+) -> Result<(), Vec<CollectionError>> {
+    let mut errors = Vec::new();
+    for (index, data) in instances.iter().enumerate() {
+        if let Err(instance_errors) = validate_instance(force, category, data) {
+            errors.extend(
+                instance_errors
+                    .into_iter()
+                    .map(|error| CollectionError::Instance { index, error }),
+            );
+        }
+    }
+    if let Some(category) = force.categories.get(category) {
+        let unique = |field: &&Field| {
+            field.is_unique()
+                || matches!(&field.datatype, DataType::Bond(_, relation) if relation.cardinality == Cardinality::One)
+        };
+        for field in category.fields.iter().filter(unique) {
+            // Value 無法雜湊，以其 JSON 字串代之
+            let mut seen: Map<String, usize> = Map::new();
+            for (index, data) in instances.iter().enumerate() {
+                let value = match (&data[&field.name], &field.default) {
+                    (Value::Null, Some(default)) => default,
+                    (Value::Null, None) => continue,
+                    (value, _) => value,
+                };
+                match seen.get(&value.to_string()) {
+                    Some(&first) => errors.push(CollectionError::Duplicate {
+                        field: field.name.clone(),
+                        value: value.clone(),
+                        first,
+                        index,
+                    }),
+                    None => {
+                        seen.insert(value.to_string(), index);
+                    }
+                }
+            }
+        }
+    }
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+// 將 data 中缺少的域填入預設值
+pub fn fill_defaults(force: &Force, category: &str, data: &mut Value) -> Result<(), InstanceError> {
+    let category = force
+        .categories
+        .get(category)
+        .ok_or_else(|| InstanceError::UnknownCategory(category.to_owned()))?;
+    let object = data.as_object_mut().ok_or(InstanceError::NotObject)?;
+    for field in &category.fields {
+        if let Some(default) = &field.default {
+            let value = object.entry(field.name.clone()).or_insert(Value::Null);
+            if value.is_null() {
+                *value = default.clone();
+            }
+        }
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -43,8 +495,8 @@ mod tests {
         let data2 = json!({
             "文字": "hi\nhi"
         });
-        assert!(Validator {}.validate_category(&category, &data1) == true);
-        assert!(Validator {}.validate_category(&category, &data2) == false);
+        assert!(Validator {}.validate_category(&category, &data1));
+        assert!(!Validator {}.validate_category(&category, &data2));
         Ok(())
     }
     #[test]
@@ -57,8 +509,8 @@ mod tests {
         let data2 = json!({
             "數": "1"
         });
-        assert!(Validator {}.validate_category(&category, &data1) == true);
-        assert!(Validator {}.validate_category(&category, &data2) == false);
+        assert!(Validator {}.validate_category(&category, &data1));
+        assert!(!Validator {}.validate_category(&category, &data2));
         Ok(())
     }
     #[test]
@@ -74,9 +526,348 @@ mod tests {
         let data3 = json!({
             "文字": "123456"
         });
-        assert!(Validator {}.validate_category(&category, &data1) == false);
-        assert!(Validator {}.validate_category(&category, &data2) == true);
-        assert!(Validator {}.validate_category(&category, &data3) == false);
+        assert!(!Validator {}.validate_category(&category, &data1));
+        assert!(Validator {}.validate_category(&category, &data2));
+        assert!(!Validator {}.validate_category(&category, &data3));
+        Ok(())
+    }
+    #[test]
+    fn test_validate_instance() -> ForceResult<()> {
+        let force = parse("問卦 {文本/.{3,}/ 內文} 解答 {鍵結[問卦] 問題 單行 標題 數字 讚數}")?;
+        let data = json!({
+            "問題": {"category": "問卦", "id": 1},
+            "標題": "有沒有八卦",
+            "讚數": 3
+        });
+        assert_eq!(validate_instance(&force, "解答", &data), Ok(()));
+
+        let data = json!({
+            "問題": {"category": "解答", "id": 1},
+            "標題": "有沒有\n八卦"
+        });
+        assert_eq!(
+            validate_instance(&force, "解答", &data),
+            Err(vec![
+                InstanceError::BondNotAllowed {
+                    field: "問題".to_owned(),
+                    category: "解答".to_owned()
+                },
+                InstanceError::NewlineInOneLine("標題".to_owned()),
+                InstanceError::MissingField("讚數".to_owned()),
+            ])
+        );
+
+        let data = json!({"內文": "短"});
+        assert_eq!(
+            validate_instance(&force, "問卦", &data),
+            Err(vec![InstanceError::RegexMismatch {
+                field: "內文".to_owned(),
+                regex: ".{3,}".to_owned()
+            }])
+        );
+        assert_eq!(
+            validate_instance(&force, "留言", &data),
+            Err(vec![InstanceError::UnknownCategory("留言".to_owned())])
+        );
+        Ok(())
+    }
+    #[test]
+    fn test_array() -> ForceResult<()> {
+        let source = "測試 {列表<單行>(1..2) 標籤}";
+        let category = parse_category(source)?;
+        assert!(Validator {}.validate_category(&category, &json!({"標籤": ["a", "b"]})));
+        assert!(!Validator {}.validate_category(&category, &json!({"標籤": []})));
+        assert!(!Validator {}.validate_category(&category, &json!({"標籤": ["a\nb"]})));
+
+        let force = parse(source)?;
+        assert_eq!(
+            validate_instance(&force, "測試", &json!({"標籤": ["a", 1, "b"]})),
+            Err(vec![
+                InstanceError::LengthOutOfRange {
+                    field: "標籤".to_owned(),
+                    length: 3
+                },
+                InstanceError::TypeMismatch {
+                    field: "標籤[1]".to_owned(),
+                    expect: "單行".to_owned()
+                },
+            ])
+        );
+        Ok(())
+    }
+    #[test]
+    fn test_optional() -> ForceResult<()> {
+        let source = "測試 {數字? 分數 單行 標題}";
+        let category = parse_category(source)?;
+        assert!(Validator {}.validate_category(&category, &json!({"標題": "a"})));
+        assert!(Validator {}.validate_category(&category, &json!({"標題": "a", "分數": null})));
+        assert!(!Validator {}.validate_category(&category, &json!({"標題": "a", "分數": "1"})));
+        assert!(!Validator {}.validate_category(&category, &json!({"分數": 1})));
+
+        let force = parse(source)?;
+        assert_eq!(
+            validate_instance(&force, "測試", &json!({"標題": "a"})),
+            Ok(())
+        );
+        assert_eq!(
+            validate_instance(&force, "測試", &json!({"分數": 1})),
+            Err(vec![InstanceError::MissingField("標題".to_owned())])
+        );
+        Ok(())
+    }
+    #[test]
+    fn test_enum() -> ForceResult<()> {
+        let source = "測試 {列舉 {草稿, 已發佈} 狀態}";
+        let category = parse_category(source)?;
+        assert!(Validator {}.validate_category(&category, &json!({"狀態": "草稿"})));
+        assert!(!Validator {}.validate_category(&category, &json!({"狀態": "封存"})));
+
+        let force = parse(source)?;
+        assert_eq!(
+            validate_instance(&force, "測試", &json!({"狀態": "封存"})),
+            Err(vec![InstanceError::NotInEnum {
+                field: "狀態".to_owned(),
+                value: "封存".to_owned()
+            }])
+        );
+        Ok(())
+    }
+    #[test]
+    fn test_rfc3339() {
+        for s in &[
+            "2020-02-29T12:00:00Z",
+            "1999-12-31t23:59:60.123+08:00",
+            "2021-01-01 00:00:00-05:30",
+        ] {
+            assert!(is_rfc3339(s), "{}", s);
+        }
+        for s in &[
+            "2021-02-29T12:00:00Z",
+            "2020-13-01T12:00:00Z",
+            "2020-01-01T24:00:00Z",
+            "2020-01-01T12:00:00",
+            "2020-01-01T12:00:00.Z",
+            "2020-01-01T12:00:00+0800",
+            "2020-1-01T12:00:00Z",
+            "２０２０-01-01T12:00:00Z",
+        ] {
+            assert!(!is_rfc3339(s), "{}", s);
+        }
+    }
+    #[test]
+    fn test_bool_and_datetime() -> ForceResult<()> {
+        let source = "測試 {布林 是否置頂 = 假 日期時間 發文時間}";
+        let force = parse(source)?;
+        let data = json!({"是否置頂": true, "發文時間": "2020-06-01T08:30:00+08:00"});
+        assert_eq!(validate_instance(&force, "測試", &data), Ok(()));
+        let data = json!({"是否置頂": 1, "發文時間": "昨天"});
+        assert_eq!(
+            validate_instance(&force, "測試", &data),
+            Err(vec![
+                InstanceError::TypeMismatch {
+                    field: "是否置頂".to_owned(),
+                    expect: "布林".to_owned()
+                },
+                InstanceError::InvalidDateTime {
+                    field: "發文時間".to_owned(),
+                    value: "昨天".to_owned()
+                }
+            ])
+        );
+        let mut data = json!({"發文時間": "2020-06-01T08:30:00Z"});
+        fill_defaults(&force, "測試", &mut data).unwrap();
+        assert_eq!(data["是否置頂"], json!(false));
+        assert!(parse("測試 {日期時間 發文時間 = \"昨天\"}").is_err());
+        Ok(())
+    }
+    #[test]
+    fn test_collection() -> ForceResult<()> {
+        let force = parse("會員 {@key 數字 編號 @unique 單行? 帳號 單行 暱稱 = \"匿名\"}")?;
+        let members = vec![
+            json!({"編號": 1, "帳號": "mros"}),
+            json!({"編號": 2}),
+            json!({"編號": 3}),
+        ];
+        assert_eq!(validate_collection(&force, "會員", &members), Ok(()));
+        let members = vec![
+            json!({"編號": 1, "帳號": "mros", "暱稱": "匿名"}),
+            json!({"編號": 1, "帳號": "mros"}),
+            json!({"帳號": "ptt"}),
+        ];
+        assert_eq!(
+            validate_collection(&force, "會員", &members),
+            Err(vec![
+                CollectionError::Instance {
+                    index: 2,
+                    error: InstanceError::MissingField("編號".to_owned())
+                },
+                CollectionError::Duplicate {
+                    field: "編號".to_owned(),
+                    value: json!(1),
+                    first: 0,
+                    index: 1
+                },
+                CollectionError::Duplicate {
+                    field: "帳號".to_owned(),
+                    value: json!("mros"),
+                    first: 0,
+                    index: 1
+                },
+            ])
+        );
+        // 暱稱並非唯一，預設值重複無妨；若標記為唯一則預設值亦參與比較
+        let force = parse("會員 {@unique 單行 暱稱 = \"匿名\"}")?;
+        let errors = validate_collection(&force, "會員", &[json!({}), json!({})]).unwrap_err();
+        assert_eq!(
+            errors[0].to_string(),
+            "第 1 個實例的域 暱稱 的值 \"匿名\" 與第 0 個實例重複"
+        );
+        let force = parse("會員 {} 頭像 {鍵結[會員] 單一 主人}")?;
+        let avatars = vec![
+            json!({"主人": {"category": "會員", "id": 1}}),
+            json!({"主人": {"category": "會員", "id": 1}}),
+        ];
+        assert!(matches!(
+            validate_collection(&force, "頭像", &avatars).unwrap_err()[..],
+            [CollectionError::Duplicate {
+                first: 0,
+                index: 1,
+                ..
+            }]
+        ));
+        assert!(matches!(
+            parse("會員 {@key 數字 編號 @key 單行 帳號}"),
+            Err(ForceError::DuplicateKey { .. })
+        ));
+        Ok(())
+    }
+    #[test]
+    fn test_default() -> ForceResult<()> {
+        let source = r#"測試 {數字 讚數 = 0 單行 標題 = "未命名"}"#;
+        let category = parse_category(source)?;
+        assert!(Validator {}.validate_category(&category, &json!({})));
+
+        let force = parse(source)?;
+        let mut data = json!({"標題": "有沒有八卦"});
+        assert_eq!(validate_instance(&force, "測試", &data), Ok(()));
+        fill_defaults(&force, "測試", &mut data).unwrap();
+        assert_eq!(data, json!({"讚數": 0, "標題": "有沒有八卦"}));
+        Ok(())
+    }
+    // 無 std 時分類存於 BTreeMap ，正則表達式由 regex-automata 檢查
+    #[cfg(not(feature = "std"))]
+    #[test]
+    fn test_no_std() -> ForceResult<()> {
+        let force = parse("文章 {單行(..4) 標題 文本/^[a-z]+$/ 代號 數字? 讚數}")?;
+        let _: &alloc::collections::BTreeMap<String, Category> = &force.categories;
+        let data = json!({"標題": "有沒有", "代號": "abc"});
+        assert_eq!(validate_instance(&force, "文章", &data), Ok(()));
+        let data = json!({"標題": "有沒有八卦", "代號": "ABC"});
+        assert_eq!(
+            validate_instance(&force, "文章", &data),
+            Err(vec![
+                InstanceError::LengthOutOfRange {
+                    field: "標題".to_owned(),
+                    length: 5,
+                },
+                InstanceError::RegexMismatch {
+                    field: "代號".to_owned(),
+                    regex: "^[a-z]+$".to_owned(),
+                },
+            ])
+        );
+        Ok(())
+    }
+    #[test]
+    fn test_computed() -> ForceResult<()> {
+        // 計算域可省略，給出時須等於由其他域算出的值
+        let source = "文章 {數字 讚數 計算 數字 熱度 = 讚數 / 2}";
+        let category = parse_category(source)?;
+        assert!(Validator {}.validate_category(&category, &json!({"讚數": 3})));
+        assert!(Validator {}.validate_category(&category, &json!({"讚數": 3, "熱度": 1.5})));
+        assert!(!Validator {}.validate_category(&category, &json!({"讚數": 3, "熱度": 100})));
+        assert!(!Validator {}.validate_category(&category, &json!({"讚數": 3, "熱度": "1.5"})));
+        assert!(!Validator {}.validate_category(&category, &json!({"熱度": 1})));
+
+        let force = parse(source)?;
+        assert_eq!(
+            validate_instance(&force, "文章", &json!({"讚數": 3})),
+            Ok(())
+        );
+        assert_eq!(
+            validate_instance(&force, "文章", &json!({"讚數": 3, "熱度": 100})),
+            Err(vec![InstanceError::ComputedMismatch("熱度".to_owned())])
+        );
+        // 無法驗算時亦不接受自行給出的值
+        assert_eq!(
+            validate_instance(&force, "文章", &json!({"熱度": 1.5})),
+            Err(vec![
+                InstanceError::MissingField("讚數".to_owned()),
+                InstanceError::ComputedMismatch("熱度".to_owned())
+            ])
+        );
+        // fill_computed 填入的值可通過驗證
+        let mut data = json!({"讚數": 4, "熱度": 100});
+        fill_computed(&force, "文章", &mut data).unwrap();
+        assert_eq!(validate_instance(&force, "文章", &data), Ok(()));
+        Ok(())
+    }
+    #[test]
+    fn test_tagged_bond() -> ForceResult<()> {
+        let source = "測試 {帶籤鍵結[測試] { 挺 {} 戰 {} } 原文}";
+        let category = parse_category(source)?;
+        let data1 = json!({"原文": {"category": "測試", "tag": "挺"}});
+        let data2 = json!({"原文": {"category": "測試", "tag": "回"}});
+        assert!(Validator {}.validate_category(&category, &data1));
+        assert!(!Validator {}.validate_category(&category, &data2));
+
+        let force = parse(source)?;
+        assert_eq!(validate_instance(&force, "測試", &data1), Ok(()));
+        assert_eq!(
+            validate_instance(&force, "測試", &json!({"原文": {"category": "測試"}})),
+            Err(vec![InstanceError::InvalidTag("原文".to_owned())])
+        );
+        Ok(())
+    }
+    #[test]
+    fn test_all_except() -> ForceResult<()> {
+        let force = parse("管理公告 {} 文章 {鍵結[* except 管理公告] 原文}")?;
+        let data = json!({"原文": {"category": "文章", "id": 1}});
+        assert_eq!(validate_instance(&force, "文章", &data), Ok(()));
+        for category in ["管理公告", "留言"] {
+            let data = json!({"原文": {"category": category, "id": 1}});
+            assert_eq!(
+                validate_instance(&force, "文章", &data),
+                Err(vec![InstanceError::BondNotAllowed {
+                    field: "原文".to_owned(),
+                    category: category.to_owned()
+                }])
+            );
+        }
+        Ok(())
+    }
+    #[test]
+    fn test_constraint() -> ForceResult<()> {
+        let source = "測試 {數字(0..100) 評分 單行(..3) 標題}";
+        let category = parse_category(source)?;
+        assert!(Validator {}.validate_category(&category, &json!({"評分": 100, "標題": "八卦版"})));
+        assert!(!Validator {}.validate_category(&category, &json!({"評分": 101, "標題": "八卦"})));
+        assert!(!Validator {}.validate_category(&category, &json!({"評分": 1, "標題": "八卦版版"})));
+
+        let force = parse(source)?;
+        assert_eq!(
+            validate_instance(&force, "測試", &json!({"評分": -1, "標題": "八卦版版"})),
+            Err(vec![
+                InstanceError::OutOfRange {
+                    field: "評分".to_owned(),
+                    value: -1
+                },
+                InstanceError::LengthOutOfRange {
+                    field: "標題".to_owned(),
+                    length: 4
+                },
+            ])
+        );
         Ok(())
     }
 }
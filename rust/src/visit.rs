@@ -0,0 +1,157 @@
+// 走訪力的各個節點，分類依名稱排序
+// 覆寫 visit_* 以處理感興趣的節點，在其中呼叫對應的 walk_* 以繼續往下走訪
+use crate::prelude::*;
+use crate::*;
+
+pub trait Visitor {
+    fn visit_category(&mut self, category: &Category) {
+        walk_category(self, category);
+    }
+    fn visit_field(&mut self, _category: &Category, field: &Field) {
+        walk_field(self, field);
+    }
+    fn visit_datatype(&mut self, datatype: &DataType) {
+        walk_datatype(self, datatype);
+    }
+    fn visit_bondee(&mut self, _bondee: &Bondee) {}
+    fn visit_tag(&mut self, _tag: &Tag) {}
+}
+
+pub fn walk_force<V: Visitor + ?Sized>(visitor: &mut V, force: &Force) {
+    let mut names: Vec<&String> = force.categories.keys().collect();
+    names.sort();
+    for name in names {
+        visitor.visit_category(&force.categories[name]);
+    }
+}
+
+pub fn walk_category<V: Visitor + ?Sized>(visitor: &mut V, category: &Category) {
+    for field in &category.fields {
+        visitor.visit_field(category, field);
+    }
+}
+
+pub fn walk_field<V: Visitor + ?Sized>(visitor: &mut V, field: &Field) {
+    visitor.visit_datatype(&field.datatype);
+}
+
+pub fn walk_datatype<V: Visitor + ?Sized>(visitor: &mut V, datatype: &DataType) {
+    match datatype {
+        DataType::Bond(bondee, _) => visitor.visit_bondee(bondee),
+        DataType::TaggedBond(bondee, tags) => {
+            visitor.visit_bondee(bondee);
+            for tag in tags {
+                visitor.visit_tag(tag);
+            }
+        }
+        DataType::Array { inner, .. } => visitor.visit_datatype(inner),
+        _ => {}
+    }
+}
+
+// 可修改節點的版本，可變借用無法同時交出分類與域，故域只附上分類名
+pub trait VisitorMut {
+    fn visit_category_mut(&mut self, category: &mut Category) {
+        walk_category_mut(self, category);
+    }
+    fn visit_field_mut(&mut self, _category: &str, field: &mut Field) {
+        walk_field_mut(self, field);
+    }
+    fn visit_datatype_mut(&mut self, datatype: &mut DataType) {
+        walk_datatype_mut(self, datatype);
+    }
+    fn visit_bondee_mut(&mut self, _bondee: &mut Bondee) {}
+    fn visit_tag_mut(&mut self, _tag: &mut Tag) {}
+}
+
+pub fn walk_force_mut<V: VisitorMut + ?Sized>(visitor: &mut V, force: &mut Force) {
+    let mut names: Vec<String> = force.categories.keys().cloned().collect();
+    names.sort();
+    for name in names {
+        visitor.visit_category_mut(force.categories.get_mut(&name).unwrap());
+    }
+}
+
+pub fn walk_category_mut<V: VisitorMut + ?Sized>(visitor: &mut V, category: &mut Category) {
+    for field in &mut category.fields {
+        visitor.visit_field_mut(&category.name, field);
+    }
+}
+
+pub fn walk_field_mut<V: VisitorMut + ?Sized>(visitor: &mut V, field: &mut Field) {
+    visitor.visit_datatype_mut(&mut field.datatype);
+}
+
+pub fn walk_datatype_mut<V: VisitorMut + ?Sized>(visitor: &mut V, datatype: &mut DataType) {
+    match datatype {
+        DataType::Bond(bondee, _) => visitor.visit_bondee_mut(bondee),
+        DataType::TaggedBond(bondee, tags) => {
+            visitor.visit_bondee_mut(bondee);
+            for tag in tags {
+                visitor.visit_tag_mut(tag);
+            }
+        }
+        DataType::Array { inner, .. } => visitor.visit_datatype_mut(inner),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Counter {
+        fields: Vec<String>,
+        bondees: usize,
+        tags: usize,
+    }
+    impl Visitor for Counter {
+        fn visit_field(&mut self, category: &Category, field: &Field) {
+            self.fields
+                .push(format!("{}.{}", category.name, field.name));
+            walk_field(self, field);
+        }
+        fn visit_bondee(&mut self, _bondee: &Bondee) {
+            self.bondees += 1;
+        }
+        fn visit_tag(&mut self, _tag: &Tag) {
+            self.tags += 1;
+        }
+    }
+
+    // 將所有指向 from 的鍵結改指向 to
+    struct Rename {
+        from: String,
+        to: String,
+    }
+    impl VisitorMut for Rename {
+        fn visit_bondee_mut(&mut self, bondee: &mut Bondee) {
+            if let Bondee::Choices(choices) = bondee {
+                for choice in choices.iter_mut().filter(|c| **c == self.from) {
+                    *choice = self.to.clone();
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_visitor() -> ForceResult<()> {
+        let source = "文章 {單行 標題} 回覆 {列表<鍵結[文章]> 引用 帶籤鍵結[*] {挺 {} 噓 {}} 原文}";
+        let mut force = parse(source)?;
+        let mut counter = Counter::default();
+        walk_force(&mut counter, &force);
+        assert_eq!(counter.fields, vec!["回覆.引用", "回覆.原文", "文章.標題"]);
+        assert_eq!(counter.bondees, 2);
+        assert_eq!(counter.tags, 2);
+
+        let mut rename = Rename {
+            from: "文章".to_owned(),
+            to: "貼文".to_owned(),
+        };
+        walk_force_mut(&mut rename, &mut force);
+        let bondee = force.categories["回覆"].fields[0].datatype.bondee();
+        assert_eq!(bondee, Some(&Bondee::Choices(vec!["貼文".to_owned()])));
+        Ok(())
+    }
+}
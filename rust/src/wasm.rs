@@ -0,0 +1,56 @@
+// 提供給 JavaScript 的介面，資料一律以 JSON 字串傳遞
+use crate::*;
+use wasm_bindgen::prelude::*;
+
+fn to_js_error(errors: Vec<String>) -> JsValue {
+    JsValue::from_str(&serde_json::to_string(&errors).unwrap())
+}
+
+#[wasm_bindgen]
+pub struct Schema {
+    force: Force,
+}
+
+#[wasm_bindgen]
+impl Schema {
+    // 解析失敗時拋出含錯誤訊息與原始碼片段的字串
+    #[wasm_bindgen(constructor)]
+    pub fn new(source: &str) -> Result<Schema, JsValue> {
+        let force = crate::parse(source).map_err(|e| JsValue::from_str(&e.render(source)))?;
+        Ok(Schema { force })
+    }
+    #[wasm_bindgen(js_name = categoryNames)]
+    pub fn category_names(&self) -> String {
+        let mut names: Vec<&String> = self.force.categories.keys().collect();
+        names.sort();
+        serde_json::to_string(&names).unwrap()
+    }
+    #[wasm_bindgen(js_name = toJson)]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(&self.force).unwrap()
+    }
+    // 供前端產生發文表單，分類不存在時回傳 undefined
+    #[wasm_bindgen(js_name = formModel)]
+    pub fn form_model(&self, category: &str) -> Option<String> {
+        let model = self.force.to_form_model(category)?;
+        Some(serde_json::to_string(&model).unwrap())
+    }
+    // 驗證失敗時拋出錯誤訊息的 JSON 陣列
+    pub fn validate(&self) -> Result<(), JsValue> {
+        self.force
+            .validate()
+            .map_err(|errors| to_js_error(errors.iter().map(|e| e.to_string()).collect()))
+    }
+    #[wasm_bindgen(js_name = validateInstance)]
+    pub fn validate_instance(&self, category: &str, data: &str) -> Result<(), JsValue> {
+        let data: serde_json::Value =
+            serde_json::from_str(data).map_err(|e| to_js_error(vec![e.to_string()]))?;
+        validate::validate_instance(&self.force, category, &data)
+            .map_err(|errors| to_js_error(errors.iter().map(|e| e.to_string()).collect()))
+    }
+}
+
+#[wasm_bindgen]
+pub fn parse(source: &str) -> Result<Schema, JsValue> {
+    Schema::new(source)
+}